@@ -1,18 +1,371 @@
-use rust_tic_tac_toe::game::Game;
+use rust_tic_tac_toe::ai::AI;
+use rust_tic_tac_toe::client::GameClient;
+use rust_tic_tac_toe::command::Command;
+use rust_tic_tac_toe::cube::{CubeBoard, CubeGame};
+use rust_tic_tac_toe::engine::{Engine, EngineProvider, Position};
+use rust_tic_tac_toe::events::EventLogger;
+use rust_tic_tac_toe::game::{Game, OpeningStage};
+use rust_tic_tac_toe::http::ApiServer;
+use rust_tic_tac_toe::lobby::LobbyServer;
 use rust_tic_tac_toe::player::Player;
-use rust_tic_tac_toe::types::Symbol;
+use rust_tic_tac_toe::protocol::{ClientMessage, ServerMessage};
+use rust_tic_tac_toe::rules::{
+    mirrored_stones, DeadPositionCheck, FlagFall, GameRules, GomokuRule, Opening, TimeControl,
+};
+use rust_tic_tac_toe::runner::{GameRunner, StepOutcome};
+use rust_tic_tac_toe::server::GameServer;
+use rust_tic_tac_toe::shape::BoardShape;
+use rust_tic_tac_toe::spectator::{describe_evaluation, history, Spectator};
+use rust_tic_tac_toe::theme::Theme;
+use rust_tic_tac_toe::tui::TerminalUI;
+use rust_tic_tac_toe::types::{Difficulty, Symbol, SymbolSet};
 use rust_tic_tac_toe::ui::UI;
+use rust_tic_tac_toe::ultimate::UltimateGame;
+use std::cell::RefCell;
+use std::env;
+use std::fs::{self, File};
+use std::io;
+use std::rc::Rc;
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::Duration;
+
+const DEFAULT_PORT: &str = "7878";
+const DEFAULT_HTTP_PORT: &str = "8080";
+const RECONNECT_ATTEMPTS: usize = 10;
 
 fn main() {
-    let ui = UI::new();
+    let args: Vec<String> = env::args().skip(1).collect();
+    let fullscreen = args.iter().any(|arg| arg == "--fullscreen");
+    let symbol_set = if args.iter().any(|arg| arg == "--ascii") {
+        SymbolSet::Ascii
+    } else {
+        SymbolSet::Emoji
+    };
+    let theme = match option_value(&args, "--theme") {
+        Some(name) => Theme::by_name(name).unwrap_or_else(|| {
+            println!(
+                "Unknown theme '{}', available themes: {}",
+                name,
+                Theme::NAMES.join(", ")
+            );
+            Theme::default()
+        }),
+        None => Theme::default(),
+    };
+    let mut ui = UI::with_symbol_set(symbol_set);
+    ui.set_theme(theme.clone());
+
+    if args.first().is_some_and(|arg| arg == "connect") {
+        match args.get(1) {
+            Some(addr) => play_online(&ui, addr),
+            None => println!("Usage: tic-tac-toe connect <host:port>"),
+        }
+        return;
+    }
+    if args.first().is_some_and(|arg| arg == "engine") {
+        let stdin = io::stdin();
+        if let Err(e) = Engine::new().run(stdin.lock(), io::stdout()) {
+            eprintln!("Engine error: {}", e);
+        }
+        return;
+    }
+    if args.first().is_some_and(|arg| arg == "watch") {
+        let game = args.get(2).and_then(|id| id.parse().ok());
+        match args.get(1) {
+            Some(addr) => watch_online(&ui, addr, game),
+            None => println!("Usage: tic-tac-toe watch <host:port> [<game id>]"),
+        }
+        return;
+    }
+    if args.first().is_some_and(|arg| arg == "lobby") {
+        serve_lobby(
+            symbol_set,
+            option_value(&args, "--port").unwrap_or(DEFAULT_PORT),
+        );
+        return;
+    }
+    if args.first().is_some_and(|arg| arg == "http") {
+        serve_http(
+            symbol_set,
+            option_value(&args, "--port").unwrap_or(DEFAULT_HTTP_PORT),
+        );
+        return;
+    }
+    if args.iter().any(|arg| arg == "--cube") {
+        play_cube(&ui, symbol_set);
+        return;
+    }
+    if args.iter().any(|arg| arg == "--ultimate") {
+        play_ultimate(&ui, symbol_set);
+        return;
+    }
+    let engine = option_value(&args, "--engine");
+    let serving = args.first().is_some_and(|arg| arg == "serve");
+
+    let time_control = match time_control(&args) {
+        Ok(time_control) => time_control,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+    let log = event_log(&args);
+    let mut game = match option_value(&args, "--load") {
+        Some(path) => match Game::load(path) {
+            Ok(mut game) => {
+                if let Some(log) = log {
+                    game.add_observer(log);
+                }
+                game
+            }
+            Err(e) => {
+                println!("{}", e);
+                return;
+            }
+        },
+        None => {
+            let mut rules = GameRules {
+                time_control,
+                misere: args.iter().any(|arg| arg == "--misere"),
+                ..GameRules::default()
+            };
+            if let Some(value) = option_value(&args, "--vanish") {
+                match value.parse::<usize>() {
+                    Ok(max) if max > 0 => rules.max_marks = Some(max),
+                    _ => {
+                        println!("Invalid value '{}' for --vanish", value);
+                        return;
+                    }
+                }
+            }
+            if let Some(value) = option_value(&args, "--stones") {
+                match value.parse::<usize>() {
+                    Ok(stones) if stones > 0 => rules.stones_per_turn = Some(stones),
+                    _ => {
+                        println!("Invalid value '{}' for --stones", value);
+                        return;
+                    }
+                }
+            }
+            rules.dead_position = match option_value(&args, "--early-draw") {
+                None => DeadPositionCheck::Off,
+                Some("open-lines") => DeadPositionCheck::OpenLines,
+                Some("moves-left") => DeadPositionCheck::MovesLeft,
+                Some(other) => {
+                    println!(
+                        "Unknown --early-draw '{}', use open-lines or moves-left",
+                        other
+                    );
+                    return;
+                }
+            };
+            rules.gomoku = match option_value(&args, "--gomoku") {
+                None | Some("freestyle") => GomokuRule::Freestyle,
+                Some("standard") => GomokuRule::Standard,
+                Some("renju") => GomokuRule::Renju,
+                Some(other) => {
+                    println!(
+                        "Unknown --gomoku '{}', use freestyle, standard or renju",
+                        other
+                    );
+                    return;
+                }
+            };
+            rules.opening = match option_value(&args, "--opening") {
+                None => Opening::Free,
+                Some("swap") => Opening::Swap,
+                Some("swap2") => Opening::Swap2,
+                Some("pie") => Opening::Pie,
+                Some(other) => {
+                    println!("Unknown --opening '{}', use swap, swap2 or pie", other);
+                    return;
+                }
+            };
+            let balanced = match option_value(&args, "--balanced").map(str::parse::<usize>) {
+                None => 0,
+                Some(Ok(pairs)) => pairs,
+                Some(Err(_)) => {
+                    println!("Invalid value for --balanced");
+                    return;
+                }
+            };
+            // Five in a row games and Connect6 are played on larger boards
+            let connect_win = if rules.stones_per_turn.is_some() {
+                Some(6)
+            } else if args.iter().any(|arg| arg == "--gomoku")
+                || matches!(rules.opening, Opening::Swap | Opening::Swap2)
+            {
+                Some(5)
+            } else {
+                None
+            };
+            if args.iter().any(|arg| arg == "--wild") {
+                let first = symbol_set.pick_unique(&[]).unwrap();
+                let second = symbol_set.pick_unique(&[first]).unwrap();
+                rules.wild = vec![first, second];
+            }
+            let shape_name = option_value(&args, "--shape");
+            let shape = match shape_name.filter(|name| !BoardShape::NAMES.contains(name)) {
+                Some(path) => match read_shape(path) {
+                    Ok(shape) => Some(shape),
+                    Err(e) => {
+                        println!("{}", e);
+                        return;
+                    }
+                },
+                None => None,
+            };
+            let board = BoardSetup {
+                connect_win,
+                balanced,
+                shape_name,
+                shape,
+            };
+            setup_game(&ui, symbol_set, rules, board, log, serving)
+        }
+    };
+
+    if serving {
+        serve(
+            game,
+            symbol_set,
+            option_value(&args, "--port").unwrap_or(DEFAULT_PORT),
+        );
+        return;
+    }
+
+    if fullscreen && TerminalUI::is_supported() {
+        if let Err(e) = play_fullscreen(&mut game, symbol_set, theme, engine) {
+            println!("Terminal error: {}", e);
+        }
+    } else {
+        if fullscreen {
+            println!("Full-screen mode needs an interactive terminal, using the line-based UI.");
+        }
+        play_lines(&ui, &mut game, engine);
+    }
+}
+
+fn event_log(args: &[String]) -> Option<EventLogger<File>> {
+    let path = option_value(args, "--log")?;
+    match File::create(path) {
+        Ok(file) => Some(EventLogger::new(file)),
+        Err(e) => {
+            println!("Could not open event log {}: {}", path, e);
+            None
+        }
+    }
+}
+
+// --time MINUTES[+INCREMENT_SECONDS], with --delay, --move-time and --on-flag
+fn time_control(args: &[String]) -> Result<Option<TimeControl>, String> {
+    let seconds = |name: &str, value: &str| {
+        value
+            .parse::<f64>()
+            .ok()
+            .filter(|secs| *secs >= 0.0)
+            .map(Duration::from_secs_f64)
+            .ok_or_else(|| format!("Invalid value '{}' for {}", value, name))
+    };
+
+    let mut time_control = match option_value(args, "--time") {
+        Some(value) => {
+            let (minutes, increment) = value.split_once('+').unwrap_or((value, "0"));
+            TimeControl::new(
+                seconds("--time", minutes)? * 60,
+                seconds("--time", increment)?,
+            )
+        }
+        None => return Ok(None),
+    };
+    if let Some(value) = option_value(args, "--delay") {
+        time_control.delay = seconds("--delay", value)?;
+    }
+    if let Some(value) = option_value(args, "--move-time") {
+        time_control.per_move = Some(seconds("--move-time", value)?);
+    }
+    time_control.on_flag = match option_value(args, "--on-flag") {
+        None | Some("lose") => FlagFall::Lose,
+        Some("skip") => FlagFall::SkipTurn,
+        Some(other) => return Err(format!("Unknown --on-flag '{}', use lose or skip", other)),
+    };
+    if !time_control.is_playable() {
+        return Err("The clock needs some time, an increment or a delay".to_string());
+    }
+    Ok(Some(time_control))
+}
+
+// --shape FILE: rows of '.' for playable and '#' for blocked cells
+fn read_shape(path: &str) -> Result<BoardShape, String> {
+    let text =
+        fs::read_to_string(path).map_err(|e| format!("Could not read shape {}: {}", path, e))?;
+    BoardShape::parse(&text).map_err(|e| format!("Invalid shape {}: {}", path, e))
+}
+
+// What the command line decides about the board, the rest is asked for
+struct BoardSetup<'a> {
+    // Default win length on the larger boards of five in a row and Connect6
+    connect_win: Option<usize>,
+    // Mirrored stone pairs placed for two players before the first move
+    balanced: usize,
+    // Cross, diamond or holes, drawn at the board size asked for
+    shape_name: Option<&'a str>,
+    // A shape read from a file, which also decides the board size
+    shape: Option<BoardShape>,
+}
 
+fn setup_game(
+    ui: &UI,
+    symbol_set: SymbolSet,
+    // Everything but the win length, which is asked for
+    rules: GameRules,
+    board: BoardSetup,
+    log: Option<EventLogger<File>>,
+    remote_humans: bool,
+) -> Game {
     // Get game settings
-    let (board_size, num_players) = ui.get_game_settings();
-    let mut game = Game::new(board_size, num_players);
+    let ((rows, cols), num_players, win_length) = if let Some(shape) = &board.shape {
+        let num_players = ui.get_player_count();
+        let dimensions = (shape.rows, shape.cols);
+        (dimensions, num_players, ui.get_win_length(dimensions))
+    } else if let Some(win_length) = board.connect_win {
+        ui.get_connect_settings(win_length)
+    } else {
+        let (dimensions, num_players) = ui.get_game_settings();
+        (dimensions, num_players, ui.get_win_length(dimensions))
+    };
+    let shape = board
+        .shape
+        .or_else(|| BoardShape::named(board.shape_name?, rows, cols));
+    let mut rules = GameRules {
+        win_length,
+        blocked: shape.map(|shape| shape.blocked).unwrap_or_default(),
+        ..rules
+    };
+    if num_players == 2 {
+        // Served games take the pie rule from --opening only, the host is not
+        // one of the players
+        if rules.opening == Opening::Free && !rules.is_wild() && !remote_humans && ui.get_pie_rule()
+        {
+            rules.opening = Opening::Pie;
+        }
+        if board.balanced > 0 {
+            rules.opening_stones = mirrored_stones(rows, cols, board.balanced, &rules.blocked);
+        }
+    }
+    let mut game = Game::with_dimensions(rows, cols, num_players, rules);
+    if let Some(log) = log {
+        game.add_observer(log);
+    }
 
     // Create and add players
     for i in 1..=num_players {
         let is_human = ui.get_player_type(i);
+        if is_human && remote_humans {
+            println!("Player {} will join over the network.", i);
+            continue;
+        }
         let name = ui.get_player_name(i, is_human);
 
         // Get previous players' symbols to avoid duplicates
@@ -24,42 +377,677 @@ fn main() {
             None
         };
 
-        let player = Player::new(
-            name,
-            Symbol::random_unique(&used_symbols).unwrap(),
-            is_human,
-            difficulty,
-        );
+        let symbol = if is_human {
+            ui.get_player_symbol(i, &used_symbols)
+        } else {
+            symbol_set.pick_unique(&used_symbols).unwrap()
+        };
+
+        let player = Player::new(name, symbol, is_human, difficulty);
 
         game.add_player(&player).unwrap();
     }
 
-    // Game loop
-    while !game.is_over() {
-        ui.display(&game);
+    game
+}
 
-        let position = if let Some(current_player) = game.current_player() {
-            if current_player.is_ai() {
-                current_player.get_ai_move(game.board())
-            } else {
-                ui.get_player_move(&mut game)
-            }
+// Players for the two-player variants
+fn two_players(ui: &UI, symbol_set: SymbolSet) -> Vec<Player> {
+    let mut players: Vec<Player> = Vec::new();
+    for i in 1..=2 {
+        let is_human = ui.get_player_type(i);
+        let name = ui.get_player_name(i, is_human);
+        let used_symbols: Vec<Symbol> = players.iter().map(|p| p.symbol()).collect();
+        let (symbol, difficulty) = if is_human {
+            (ui.get_player_symbol(i, &used_symbols), None)
         } else {
-            println!("No current player!");
-            break;
+            (
+                symbol_set.pick_unique(&used_symbols).unwrap(),
+                Some(ui.get_ai_difficulty()),
+            )
+        };
+        players.push(Player::new(name, symbol, is_human, difficulty));
+    }
+    players
+}
+
+fn play_ultimate(ui: &UI, symbol_set: SymbolSet) {
+    let mut game = UltimateGame::new();
+    for player in two_players(ui, symbol_set) {
+        game.add_player(&player).unwrap();
+    }
+
+    let mut show_board = true;
+    while !game.is_over() {
+        if show_board {
+            ui.display_ultimate(&game);
+        }
+        show_board = false;
+        let (name, symbol) = match game.current_player() {
+            Some(player) => (player.name().to_string(), player.symbol()),
+            None => return,
         };
+        if let Some(pos) = game.ai_move() {
+            game.make_move(pos).unwrap();
+            println!("{} plays {} {}", name, pos.0, pos.1);
+            show_board = true;
+            continue;
+        }
+        match ui.read_command() {
+            Command::Move(pos) => match game.make_move(pos) {
+                Ok(()) => show_board = true,
+                Err(e) => println!("{}", e),
+            },
+            Command::Hint => {
+                let (row, col) =
+                    AI::new(Difficulty::Hard, symbol).decide_ultimate_move(game.board());
+                println!("Hint: try {} {}", row, col);
+            }
+            Command::Board => show_board = true,
+            Command::Help => println!("Commands: <row> <col>, hint, board, help, quit"),
+            Command::Quit => return,
+            _ => println!("That command isn't available in ultimate tic-tac-toe"),
+        }
+    }
+    ui.display_ultimate(&game);
+    println!("{}", game.status());
+}
+
+fn play_cube(ui: &UI, symbol_set: SymbolSet) {
+    let (size, win_length) = ui.get_cube_settings();
+    let mut game = CubeGame::new(size, win_length);
+    for player in two_players(ui, symbol_set) {
+        game.add_player(&player).unwrap();
+    }
 
-        if let Err(e) = game.make_move(position) {
-            println!("Error: {}", e);
+    let mut show_board = true;
+    while !game.is_over() {
+        if show_board {
+            ui.display_cube(&game);
+        }
+        show_board = false;
+        let (name, symbol) = match game.current_player() {
+            Some(player) => (player.name().to_string(), player.symbol()),
+            None => return,
+        };
+        if let Some(pos) = game.ai_move() {
+            game.make_move(pos).unwrap();
+            println!("{} plays {} {} {}", name, pos.0, pos.1, pos.2);
+            show_board = true;
+            continue;
+        }
+        let Some(input) = ui.read_line("Enter your move (layer row col) or a command: ") else {
+            return;
+        };
+        if let Some(pos) = CubeBoard::parse_position(&input) {
+            match game.make_move(pos) {
+                Ok(()) => show_board = true,
+                Err(e) => println!("{}", e),
+            }
             continue;
         }
+        match Command::parse(&input) {
+            Ok(Command::Hint) => {
+                let (layer, row, col) =
+                    AI::new(Difficulty::Hard, symbol).decide_cube_move(game.board());
+                println!("Hint: try {} {} {}", layer, row, col);
+            }
+            Ok(Command::Board) => show_board = true,
+            Ok(Command::Help) => println!("Commands: <layer> <row> <col>, hint, board, help, quit"),
+            Ok(Command::Quit) => return,
+            Ok(_) => println!("That command isn't available in 3D tic-tac-toe"),
+            Err(e) => println!("{}", e),
+        }
     }
+    ui.display_cube(&game);
+    println!("{}", game.status());
+}
 
-    // Display final board and winner
-    ui.display(&game);
-    if let Some(winner) = game.winner() {
-        println!("Winner: {} ({})", winner.name(), winner.symbol());
-    } else {
-        println!("Game ended in a draw!");
+fn serve(game: Game, symbol_set: SymbolSet, port: &str) {
+    let port: u16 = match port.parse() {
+        Ok(port) => port,
+        Err(_) => {
+            println!("Invalid port '{}'", port);
+            return;
+        }
+    };
+    let server = match GameServer::bind(("0.0.0.0", port), game, symbol_set) {
+        Ok(server) => server,
+        Err(e) => {
+            println!("Could not start the server: {}", e);
+            return;
+        }
+    };
+    println!(
+        "Waiting for players on port {}, join with: tic-tac-toe connect <host>:{}",
+        port, port
+    );
+    match server.run() {
+        Ok(status) => println!("{}", status),
+        Err(e) => println!("Server error: {}", e),
+    }
+}
+
+fn serve_http(symbol_set: SymbolSet, port: &str) {
+    let server = match port.parse::<u16>() {
+        Ok(port) => ApiServer::bind(("0.0.0.0", port), symbol_set),
+        Err(_) => {
+            println!("Invalid port '{}'", port);
+            return;
+        }
+    };
+    match server {
+        Ok(server) => {
+            println!("HTTP API listening on port {}", port);
+            if let Err(e) = server.run() {
+                println!("Server error: {}", e);
+            }
+        }
+        Err(e) => println!("Could not start the server: {}", e),
+    }
+}
+
+fn serve_lobby(symbol_set: SymbolSet, port: &str) {
+    let server = match port.parse::<u16>() {
+        Ok(port) => LobbyServer::bind(("0.0.0.0", port), symbol_set),
+        Err(_) => {
+            println!("Invalid port '{}'", port);
+            return;
+        }
+    };
+    match server {
+        Ok(server) => {
+            println!(
+                "Lobby open on port {}, join with: tic-tac-toe connect <host>:{}",
+                port, port
+            );
+            if let Err(e) = server.run() {
+                println!("Server error: {}", e);
+            }
+        }
+        Err(e) => println!("Could not start the server: {}", e),
+    }
+}
+
+enum OnlineInput {
+    Line(String),
+    Server(ServerMessage),
+    Disconnected,
+}
+
+// Forwards server messages to the main loop until the connection drops
+fn forward_messages(mut client: GameClient, input: Sender<OnlineInput>) {
+    thread::spawn(move || loop {
+        match client.recv() {
+            Ok(message) => {
+                if input.send(OnlineInput::Server(message)).is_err() {
+                    return;
+                }
+            }
+            Err(_) => {
+                let _ = input.send(OnlineInput::Disconnected);
+                return;
+            }
+        }
+    });
+}
+
+// Follows a game as a spectator until it is over
+fn watch_online(ui: &UI, addr: &str, game_id: Option<u64>) {
+    let mut client = match GameClient::connect(addr) {
+        Ok(client) => client,
+        Err(e) => {
+            println!("Could not connect to {}: {}", addr, e);
+            return;
+        }
+    };
+    if let Err(e) = client.send(&ClientMessage::Spectate { game: game_id }) {
+        println!("Could not watch the game: {}", e);
+        return;
+    }
+
+    let spectator = Spectator::new(ui.clone());
+    let mut game: Option<Game> = None;
+    loop {
+        match client.recv() {
+            Ok(ServerMessage::Joined { game, .. }) => println!("Watching game #{}.", game),
+            Ok(ServerMessage::State { snapshot }) => {
+                let Ok(next) = Game::from_snapshot(&snapshot) else {
+                    continue;
+                };
+                match &game {
+                    None => spectator.show_game(&next),
+                    Some(previous) => {
+                        if next.history().len() > previous.history().len() {
+                            if let Some(line) = history(&next).last() {
+                                println!("* {}", line);
+                            }
+                        }
+                        spectator.show_position(&next);
+                    }
+                }
+                game = Some(next);
+            }
+            Ok(ServerMessage::Evaluation { evaluations }) => {
+                if let Some(game) = &game {
+                    spectator.show_evaluations(game, &evaluations);
+                }
+            }
+            Ok(ServerMessage::Chat { from, text }) => println!("[{}] {}", from, text),
+            Ok(ServerMessage::Notice { text }) => println!("* {}", text),
+            Ok(ServerMessage::Error { message }) => {
+                println!("{}", message);
+                return;
+            }
+            Ok(ServerMessage::Result { status }) => {
+                println!("{}", status);
+                return;
+            }
+            Ok(_) => {}
+            Err(_) => {
+                println!("Lost the connection to {}", addr);
+                return;
+            }
+        }
+    }
+}
+
+fn play_online(ui: &UI, addr: &str) {
+    let mut client = match GameClient::connect(addr) {
+        Ok(client) => client,
+        Err(e) => {
+            println!("Could not connect to {}: {}", addr, e);
+            return;
+        }
+    };
+    let name = ui.get_player_name(1, true);
+    let (input, messages) = mpsc::channel();
+    let stdin_input = input.clone();
+    thread::spawn(move || {
+        for line in io::stdin().lines() {
+            let Ok(line) = line else { break };
+            if stdin_input.send(OnlineInput::Line(line)).is_err() {
+                return;
+            }
+        }
+        let _ = stdin_input.send(OnlineInput::Line("quit".to_string()));
+    });
+
+    let joined = client.try_clone().and_then(|reader| {
+        forward_messages(reader, input.clone());
+        client.send(&ClientMessage::Join { name, symbol: None })
+    });
+    if let Err(e) = joined {
+        println!("Could not join the game: {}", e);
+        return;
+    }
+
+    let mut me = None;
+    let mut token = None;
+    let mut in_lobby = false;
+    let mut game: Option<Game> = None;
+    while let Ok(message) = messages.recv() {
+        let outgoing = match message {
+            OnlineInput::Server(ServerMessage::Welcome {
+                player,
+                symbol,
+                token: welcome_token,
+            }) => {
+                me = Some(player);
+                token = Some(welcome_token);
+                println!("You play {}.", symbol);
+                None
+            }
+            OnlineInput::Server(ServerMessage::State { snapshot }) => {
+                game = Game::from_snapshot(&snapshot).ok();
+                if let Some(game) = &game {
+                    ui.display(game);
+                    let my_turn = game.current_player().map(|p| p.id()) == me;
+                    let choosing = matches!(
+                        game.opening_stage(),
+                        OpeningStage::Choosing { player, .. } if Some(player) == me
+                    );
+                    let playing = !game.is_over() && game.players().len() == game.max_players();
+                    if playing && choosing {
+                        println!(
+                            "Your choice (black, white, two, swap or keep), 'say <text>' to chat:"
+                        );
+                    } else if playing && my_turn {
+                        if game.rules().is_wild() {
+                            println!("Your move (row col symbol), 'say <text>' to chat:");
+                        } else {
+                            println!("Your move (row col), 'say <text>' to chat:");
+                        }
+                    }
+                }
+                None
+            }
+            OnlineInput::Server(ServerMessage::Chat { from, text }) => {
+                println!("[{}] {}", from, text);
+                None
+            }
+            OnlineInput::Server(ServerMessage::Notice { text }) => {
+                println!("* {}", text);
+                None
+            }
+            OnlineInput::Server(ServerMessage::Error { message }) => {
+                println!("{}", message);
+                None
+            }
+            OnlineInput::Server(ServerMessage::Result { status }) => {
+                println!("{}", status);
+                if !in_lobby {
+                    return;
+                }
+                println!("{}", LOBBY_HELP);
+                None
+            }
+            OnlineInput::Server(ServerMessage::LoggedIn {
+                player,
+                rating,
+                token: login_token,
+            }) => {
+                me = Some(player);
+                token = Some(login_token);
+                in_lobby = true;
+                println!("Logged in with rating {}.\n{}", rating, LOBBY_HELP);
+                None
+            }
+            OnlineInput::Server(ServerMessage::Games { games }) => {
+                if games.is_empty() {
+                    println!("No games yet, 'create' one or wait for a 'match'.");
+                }
+                for info in games {
+                    println!(
+                        "#{} {}x{} ({} in a row) {:?}: {}/{} players [{}], {} watching",
+                        info.id,
+                        info.board_size,
+                        info.board_cols,
+                        info.win_length,
+                        info.stage,
+                        info.players.len(),
+                        info.max_players,
+                        info.players.join(", "),
+                        info.spectators
+                    );
+                }
+                None
+            }
+            OnlineInput::Server(ServerMessage::Evaluation { evaluations }) => {
+                if let Some(game) = &game {
+                    for evaluation in &evaluations {
+                        println!("  {}", describe_evaluation(game, evaluation));
+                    }
+                }
+                None
+            }
+            OnlineInput::Server(ServerMessage::Joined { game, spectator }) => {
+                if spectator {
+                    println!("Watching game #{}.", game);
+                } else {
+                    println!("Joined game #{}.", game);
+                }
+                None
+            }
+            OnlineInput::Line(line) => match lobby_command(&line).filter(|_| in_lobby) {
+                Some(Ok(message)) => Some(message),
+                Some(Err(e)) => {
+                    println!("{}", e);
+                    None
+                }
+                None => match line.trim().strip_prefix("say ") {
+                    Some(text) => Some(ClientMessage::Chat {
+                        text: text.to_string(),
+                    }),
+                    None => match Command::parse(&line) {
+                        Ok(Command::Move((row, col))) => Some(ClientMessage::Move {
+                            row,
+                            col,
+                            symbol: None,
+                        }),
+                        Ok(Command::Place((row, col), symbol)) => Some(ClientMessage::Move {
+                            row,
+                            col,
+                            symbol: Some(symbol),
+                        }),
+                        Ok(Command::Choose(choice)) => Some(ClientMessage::Choose { choice }),
+                        Ok(Command::Resign) => Some(ClientMessage::Resign),
+                        Ok(Command::Quit) => return,
+                        Ok(Command::Board) => {
+                            if let Some(game) = &game {
+                                ui.display(game);
+                            }
+                            None
+                        }
+                        Ok(_) => {
+                            println!(
+                                "Online games support moves, opening choices, 'say <text>', board, resign and quit."
+                            );
+                            None
+                        }
+                        Err(e) => {
+                            println!("{}", e);
+                            None
+                        }
+                    },
+                },
+            },
+            OnlineInput::Disconnected => {
+                let (Some(player), Some(token)) = (me, &token) else {
+                    println!("Lost the connection to {}", addr);
+                    return;
+                };
+                println!("Connection lost, reconnecting...");
+                match GameClient::rejoin(addr, player, token, RECONNECT_ATTEMPTS).and_then(
+                    |new_client| new_client.try_clone().map(|reader| (new_client, reader)),
+                ) {
+                    Ok((new_client, reader)) => {
+                        client = new_client;
+                        forward_messages(reader, input.clone());
+                    }
+                    Err(e) => {
+                        println!("Could not reconnect: {}", e);
+                        return;
+                    }
+                }
+                None
+            }
+        };
+
+        if let Some(message) = outgoing {
+            if let Err(e) = client.send(&message) {
+                println!("Could not send: {}", e);
+            }
+        }
+    }
+}
+
+const LOBBY_HELP: &str = "\
+Lobby: games | create <size> <players> | join <id> | watch <id> | match [<size> <players>] | leave";
+
+// Lobby commands typed in an online session, None for anything else
+fn lobby_command(line: &str) -> Option<Result<ClientMessage, String>> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let number = |word: &str| {
+        word.parse::<usize>()
+            .map_err(|_| format!("'{}' is not a number", word))
+    };
+    let game_id = |word: &str| {
+        word.trim_start_matches('#')
+            .parse::<u64>()
+            .map_err(|_| format!("'{}' is not a game id", word))
+    };
+    let message = match words.as_slice() {
+        ["games"] => Ok(ClientMessage::ListGames),
+        ["create", size, players] => number(size).and_then(|board_size| {
+            Ok(ClientMessage::CreateGame {
+                board_size,
+                max_players: number(players)?,
+                win_length: None,
+            })
+        }),
+        ["join", id] => game_id(id).map(|game| ClientMessage::JoinGame { game, symbol: None }),
+        ["watch", id] => game_id(id).map(|game| ClientMessage::Spectate { game: Some(game) }),
+        ["match"] => Ok(ClientMessage::FindMatch {
+            board_size: 3,
+            max_players: 2,
+        }),
+        ["match", size, players] => number(size).and_then(|board_size| {
+            Ok(ClientMessage::FindMatch {
+                board_size,
+                max_players: number(players)?,
+            })
+        }),
+        ["leave"] => Ok(ClientMessage::Leave),
+        ["games" | "create" | "join" | "watch" | "match", ..] => Err(LOBBY_HELP.to_string()),
+        _ => return None,
+    };
+    Some(message)
+}
+
+// --engine "PROGRAM ARGS..." plays the AI seats with an external engine
+fn attach_engine(runner: &mut GameRunner, game: &Game, command: Option<&str>) -> io::Result<()> {
+    let Some(command) = command else {
+        return Ok(());
+    };
+    if !Position::supports(game.rules()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "engines only play the standard rules",
+        ));
+    }
+    let mut words = command.split_whitespace();
+    let program = words.next().ok_or(io::ErrorKind::InvalidInput)?;
+    let args: Vec<&str> = words.collect();
+    for player in game.players().iter().filter(|p| p.is_ai()) {
+        let mut engine = EngineProvider::spawn(program, &args)?;
+        if let Some(difficulty) = player.difficulty() {
+            engine.set_option("difficulty", &format!("{:?}", difficulty))?;
+        }
+        runner.set_provider(player.id(), engine);
+    }
+    Ok(())
+}
+
+fn option_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == name)
+        .and_then(|idx| args.get(idx + 1))
+        .map(|value| value.as_str())
+}
+
+fn describe(game: &Game, outcome: &StepOutcome) -> Option<String> {
+    let name = |id| {
+        game.player(id)
+            .map(|p| p.name().to_string())
+            .unwrap_or_default()
+    };
+    match outcome {
+        // Only the move that completed the line is played by an eliminated player
+        StepOutcome::Played(mv) if game.is_eliminated(mv.player) => {
+            Some(format!("{} completed a line and is out.", name(mv.player)))
+        }
+        StepOutcome::Played(_) | StepOutcome::Quit(_) => None,
+        StepOutcome::OpeningChosen { player, choice } => {
+            Some(format!("{} chooses {}.", name(*player), choice))
+        }
+        StepOutcome::Rejected { error, .. } => Some(error.to_string()),
+        StepOutcome::Undone(count) => Some(format!("Took back {} move(s).", count)),
+        StepOutcome::Redone(count) => Some(format!("Replayed {} move(s).", count)),
+        StepOutcome::DrawOffered { accepted: true, .. } => Some("Draw agreed.".to_string()),
+        StepOutcome::DrawOffered { player, .. } => Some(format!(
+            "The draw offered by {} was declined.",
+            name(*player)
+        )),
+        StepOutcome::Resigned(player) => Some(format!("{} resigns.", name(*player))),
+        StepOutcome::TimedOut(player) => Some(format!("{} ran out of time.", name(*player))),
+    }
+}
+
+fn announce_result(game: &Game) -> String {
+    match game.winner() {
+        Some(winner) => format!("{} ({})", game.status(), winner.symbol()),
+        None => game.status().to_string(),
+    }
+}
+
+fn play_lines(ui: &UI, game: &mut Game, engine: Option<&str>) {
+    let mut runner = GameRunner::for_game(game, ui.clone());
+    if let Err(e) = attach_engine(&mut runner, game, engine) {
+        println!("Could not start the engine: {}", e);
+        return;
+    }
+    loop {
+        // Game loop
+        let mut show_board = true;
+        while !game.is_over() {
+            if show_board {
+                ui.display(game);
+            }
+
+            let outcome = match runner.step(game) {
+                Ok(StepOutcome::Quit(_)) => return,
+                Ok(outcome) => outcome,
+                Err(e) => {
+                    println!("Error: {}", e);
+                    return;
+                }
+            };
+            show_board = matches!(
+                outcome,
+                StepOutcome::Played(_)
+                    | StepOutcome::OpeningChosen { .. }
+                    | StepOutcome::Undone(_)
+                    | StepOutcome::Redone(_)
+            );
+            if let Some(message) = describe(game, &outcome) {
+                println!("{}", message);
+            }
+        }
+
+        // Display final board and winner
+        ui.display(game);
+        println!("{}", announce_result(game));
+
+        if !ui.ask_play_again() {
+            return;
+        }
+        game.prepare_next_round();
+    }
+}
+
+fn play_fullscreen(
+    game: &mut Game,
+    symbol_set: SymbolSet,
+    theme: Theme,
+    engine: Option<&str>,
+) -> io::Result<()> {
+    let tui = Rc::new(RefCell::new(TerminalUI::new(symbol_set, theme)?));
+    let mut runner = GameRunner::for_game(game, Rc::clone(&tui));
+    attach_engine(&mut runner, game, engine)?;
+
+    loop {
+        while !game.is_over() {
+            // Human turns draw the board while waiting for input
+            if game.current_player().is_some_and(|p| p.is_ai()) {
+                tui.borrow_mut().display(game, runner.scores())?;
+            }
+
+            let outcome = match runner.step(game) {
+                Ok(StepOutcome::Quit(_)) => return Ok(()),
+                Ok(outcome) => outcome,
+                Err(e) => return Err(io::Error::other(e.to_string())),
+            };
+            if let Some(message) = describe(game, &outcome) {
+                tui.borrow_mut().set_message(message);
+            }
+        }
+
+        let mut tui = tui.borrow_mut();
+        tui.set_message(announce_result(game));
+        if !tui.ask_play_again(game, runner.scores())? {
+            return Ok(());
+        }
+        game.prepare_next_round();
     }
 }
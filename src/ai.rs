@@ -1,6 +1,36 @@
+use crate::cube::{CubeBoard, CubePosition};
 use crate::game_board::GameBoard;
-use crate::types::{Difficulty, Symbol};
+use crate::types::{Difficulty, OpeningChoice, Symbol};
+use crate::ultimate::{UltimateBoard, SUB_SIZE};
 use rand::seq::SliceRandom;
+use std::cmp::Reverse;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+// Score of a won position in `AI::evaluate`
+pub const WIN_SCORE: i32 = 1_000_000;
+
+// Wild games with at most this many (cell, symbol) choices get a look at
+// the opponent's replies
+const WILD_LOOKAHEAD_MOVES: usize = 32;
+
+// A cell and the symbol to place there
+type WildMove = ((usize, usize), Symbol);
+
+// Plies searched with disappearing pieces, fewer once the board has more
+// than VANISHING_DEEP_CELLS empty cells
+const VANISHING_DEPTH: usize = 4;
+const VANISHING_SHALLOW_DEPTH: usize = 2;
+const VANISHING_DEEP_CELLS: usize = 16;
+
+// Cells tried in combination for a multi-stone turn
+const STONE_CANDIDATES: usize = 12;
+
+// Largest power of two a line is weighed with, see line_weight
+const MAX_LINE_SHIFT: i32 = 18;
+
+// A player's symbol and their marks on the board, oldest first
+pub type MarkQueue = (Symbol, VecDeque<(usize, usize)>);
 
 #[derive(Debug, Clone)]
 pub struct AI {
@@ -14,16 +44,34 @@ impl AI {
     }
 
     pub fn decide_move(&self, board: &GameBoard) -> (usize, usize) {
+        self.decide_move_within(board, None)
+    }
+
+    // With a clock, spend an even share of the remaining time on this move
+    pub fn decide_move_within(
+        &self,
+        board: &GameBoard,
+        time_left: Option<Duration>,
+    ) -> (usize, usize) {
+        let deadline = time_left.and_then(|left| {
+            let moves_to_come = (board.empty_positions().len() as u32).div_ceil(2).max(1);
+            Instant::now().checked_add(left / moves_to_come)
+        });
+        self.decide_move_by(board, deadline)
+    }
+
+    // Skips the slower strategies once the deadline has passed
+    pub fn decide_move_by(&self, board: &GameBoard, deadline: Option<Instant>) -> (usize, usize) {
         match self.difficulty {
             Difficulty::Easy => self.random_move(board),
             Difficulty::Medium => {
                 if rand::random::<f32>() < 0.7 {
-                    self.smart_move(board)
+                    self.smart_move(board, deadline)
                 } else {
                     self.random_move(board)
                 }
             }
-            Difficulty::Hard => self.smart_move(board),
+            Difficulty::Hard => self.smart_move(board, deadline),
         }
     }
 
@@ -32,20 +80,26 @@ impl AI {
         *empty_positions.choose(&mut rand::thread_rng()).unwrap()
     }
 
-    fn smart_move(&self, board: &GameBoard) -> (usize, usize) {
+    fn smart_move(&self, board: &GameBoard, deadline: Option<Instant>) -> (usize, usize) {
         let empty_positions = board.empty_positions();
+        let out_of_time = || deadline.is_some_and(|deadline| Instant::now() >= deadline);
+
+        // In misère play winning and blocking turn around
+        if board.is_misere() {
+            return self.misere_move(board);
+        }
 
         // If center is empty, take it (good strategy in any case)
-        let center = board.size() / 2;
-        if empty_positions.contains(&(center, center)) {
-            return (center, center);
+        let center = (board.rows() / 2, board.cols() / 2);
+        if empty_positions.contains(&center) {
+            return center;
         }
 
         // Check for winning moves
         for pos in empty_positions.iter() {
             let mut board_clone = board.clone();
             if board_clone.apply_move(*pos, self.symbol).is_ok()
-                && board_clone.has_winning_streak(3).is_some()
+                && board_clone.has_winning_streak(board.win_length()).is_some()
             {
                 return *pos;
             }
@@ -57,24 +111,31 @@ impl AI {
             for symbol in board_clone.get_all_symbols() {
                 if symbol != self.symbol {
                     if board_clone.apply_move(*pos, symbol).is_ok()
-                        && board_clone.has_winning_streak(3).is_some()
+                        && board_clone.has_winning_streak(board.win_length()).is_some()
                     {
                         return *pos;
                     }
-                    let _ = board_clone.undo_move(*pos).unwrap();
+                    board_clone.undo_move(*pos).unwrap();
                 }
             }
         }
 
-        // Try to create a fork (two ways to win)
+        // Try to create a fork (two ways to win). Both new threats run through
+        // the cell we play, so skip cells with fewer than two open lines
         for pos in empty_positions.iter() {
+            if out_of_time() {
+                break;
+            }
+            if board.open_lines_through(*pos, self.symbol) < 2 {
+                continue;
+            }
             let mut board_clone = board.clone();
             if board_clone.apply_move(*pos, self.symbol).is_ok() {
                 let mut winning_paths = 0;
                 for next_pos in board_clone.empty_positions() {
                     let mut next_board = board_clone.clone();
                     if next_board.apply_move(next_pos, self.symbol).is_ok()
-                        && next_board.has_winning_streak(3).is_some()
+                        && next_board.has_winning_streak(board.win_length()).is_some()
                     {
                         winning_paths += 1;
                     }
@@ -88,9 +149,9 @@ impl AI {
         // If no strategic move is found, take a corner if available
         let corners = [
             (0, 0),
-            (0, board.size() - 1),
-            (board.size() - 1, 0),
-            (board.size() - 1, board.size() - 1),
+            (0, board.cols() - 1),
+            (board.rows() - 1, 0),
+            (board.rows() - 1, board.cols() - 1),
         ];
         for corner in corners.iter() {
             if empty_positions.contains(corner) {
@@ -102,7 +163,665 @@ impl AI {
         self.random_move(board)
     }
 
+    // Never complete one of our own lines if there is any other move, and
+    // leave the cells that would complete an opponent's line to them. On
+    // boards with a center cell take it and answer every move with its
+    // mirror image, otherwise play where we have the fewest lines going
+    fn misere_move(&self, board: &GameBoard) -> (usize, usize) {
+        let empty_positions = board.empty_positions();
+        let safe: Vec<(usize, usize)> = empty_positions
+            .iter()
+            .copied()
+            .filter(|&pos| !board.completes_line(pos, self.symbol))
+            .collect();
+        if safe.is_empty() {
+            return self.random_move(board);
+        }
+        let opponents: Vec<Symbol> = board
+            .get_all_symbols()
+            .into_iter()
+            .filter(|&symbol| symbol != self.symbol)
+            .collect();
+        let keeps_traps: Vec<(usize, usize)> = safe
+            .iter()
+            .copied()
+            .filter(|&pos| {
+                opponents
+                    .iter()
+                    .all(|&opponent| !board.completes_line(pos, opponent))
+            })
+            .collect();
+        let candidates = if keeps_traps.is_empty() {
+            safe
+        } else {
+            keeps_traps
+        };
+
+        let (last_row, last_col) = (board.rows() - 1, board.cols() - 1);
+        let center = (last_row / 2, last_col / 2);
+        if board.rows() % 2 == 1 && board.cols() % 2 == 1 {
+            if candidates.contains(&center) {
+                return center;
+            }
+            if board.get_cell(center) == Some(self.symbol) {
+                let mirrored = candidates.iter().find(|&&(row, col)| {
+                    board
+                        .get_cell((last_row - row, last_col - col))
+                        .is_some_and(|symbol| symbol != self.symbol)
+                });
+                if let Some(&pos) = mirrored {
+                    return pos;
+                }
+            }
+        }
+
+        let exposure = |pos: (usize, usize)| -> i32 {
+            board
+                .win_lines_through(pos)
+                .filter_map(|line| board.cells_needed(line, self.symbol))
+                .map(|needed| line_weight(2 * (board.win_length() - needed) as i32))
+                .sum()
+        };
+        let least = candidates.iter().map(|&pos| exposure(pos)).min().unwrap();
+        let best_moves: Vec<(usize, usize)> = candidates
+            .into_iter()
+            .filter(|&pos| exposure(pos) == least)
+            .collect();
+        *best_moves.choose(&mut rand::thread_rng()).unwrap()
+    }
+
+    // Wild tic-tac-toe: any of `symbols` may go anywhere and a completed
+    // line is the mover's, so both symbols are tried in every cell. Win
+    // right away if possible, never leave the opponent a winning move, and
+    // on small boards prefer moves that leave them the fewest safe replies.
+    // In misère play the same with completing a line as the losing move
+    pub fn decide_wild_move(
+        &self,
+        board: &GameBoard,
+        symbols: &[Symbol],
+    ) -> ((usize, usize), Symbol) {
+        let moves = |board: &GameBoard| -> Vec<WildMove> {
+            board
+                .empty_positions()
+                .into_iter()
+                .flat_map(|pos| symbols.iter().map(move |&symbol| (pos, symbol)))
+                .collect()
+        };
+        let all_moves = moves(board);
+        let play_randomly = match self.difficulty {
+            Difficulty::Easy => true,
+            Difficulty::Medium => rand::random::<f32>() >= 0.7,
+            Difficulty::Hard => false,
+        };
+        if play_randomly {
+            return *all_moves.choose(&mut rand::thread_rng()).unwrap();
+        }
+
+        let misere = board.is_misere();
+        let completes =
+            |board: &GameBoard, (pos, symbol): WildMove| board.completes_line(pos, symbol);
+        let after = |board: &GameBoard, (pos, symbol): WildMove| {
+            let mut next = board.clone();
+            next.apply_move(pos, symbol).unwrap();
+            next
+        };
+        if !misere {
+            if let Some(&winning) = all_moves.iter().find(|&&m| completes(board, m)) {
+                return winning;
+            }
+        }
+        let candidates: Vec<WildMove> = all_moves
+            .iter()
+            .copied()
+            .filter(|&m| {
+                if misere {
+                    !completes(board, m)
+                } else {
+                    let next = after(board, m);
+                    !moves(&next)
+                        .into_iter()
+                        .any(|reply| completes(&next, reply))
+                }
+            })
+            .collect();
+        if candidates.is_empty() {
+            return *all_moves.choose(&mut rand::thread_rng()).unwrap();
+        }
+
+        let best_moves = if all_moves.len() <= WILD_LOOKAHEAD_MOVES {
+            // Replies that don't hand us the game: in misère play any reply
+            // that doesn't complete a line, otherwise any after which we
+            // can't complete one
+            let safe_replies = |m| {
+                let next = after(board, m);
+                moves(&next)
+                    .into_iter()
+                    .filter(|&reply| {
+                        if misere {
+                            !completes(&next, reply)
+                        } else {
+                            let rest = after(&next, reply);
+                            !moves(&rest).into_iter().any(|ours| completes(&rest, ours))
+                        }
+                    })
+                    .count()
+            };
+            let scored: Vec<(usize, WildMove)> =
+                candidates.iter().map(|&m| (safe_replies(m), m)).collect();
+            let fewest = scored.iter().map(|&(count, _)| count).min().unwrap();
+            scored
+                .into_iter()
+                .filter(|&(count, _)| count == fewest)
+                .map(|(_, m)| m)
+                .collect()
+        } else {
+            let center = (board.rows() / 2, board.cols() / 2);
+            let central: Vec<_> = candidates
+                .iter()
+                .copied()
+                .filter(|&(pos, _)| pos == center)
+                .collect();
+            if central.is_empty() {
+                candidates
+            } else {
+                central
+            }
+        };
+        *best_moves.choose(&mut rand::thread_rng()).unwrap()
+    }
+
+    // Disappearing pieces: minimax over the next few moves that plays out
+    // the removals, so a line that loses its oldest mark doesn't count as a
+    // threat. `queues` starts with us and lists everyone in turn order, all
+    // other players are assumed to play against us
+    pub fn decide_vanishing_move(
+        &self,
+        board: &GameBoard,
+        queues: &[MarkQueue],
+        max_marks: usize,
+    ) -> (usize, usize) {
+        let play_randomly = match self.difficulty {
+            Difficulty::Easy => true,
+            Difficulty::Medium => rand::random::<f32>() >= 0.7,
+            Difficulty::Hard => false,
+        };
+        let empty_positions = board.empty_positions();
+        if play_randomly || queues.is_empty() {
+            return self.random_move(board);
+        }
+
+        let depth = if empty_positions.len() <= VANISHING_DEEP_CELLS {
+            VANISHING_DEPTH
+        } else {
+            VANISHING_SHALLOW_DEPTH
+        };
+        let mut queues = queues.to_vec();
+        let scored: Vec<(i32, (usize, usize))> = empty_positions
+            .iter()
+            .map(|&pos| {
+                let score = self.vanishing_score(board, &mut queues, 0, max_marks, pos, depth);
+                (score, pos)
+            })
+            .collect();
+        let best = scored.iter().map(|&(score, _)| score).max().unwrap();
+        let best_moves: Vec<(usize, usize)> = scored
+            .into_iter()
+            .filter(|&(score, _)| score == best)
+            .map(|(_, pos)| pos)
+            .collect();
+        *best_moves.choose(&mut rand::thread_rng()).unwrap()
+    }
+
+    // Plays `pos` for the player at `turn` and searches on from there
+    fn vanishing_score(
+        &self,
+        board: &GameBoard,
+        queues: &mut [MarkQueue],
+        turn: usize,
+        max_marks: usize,
+        pos: (usize, usize),
+        depth: usize,
+    ) -> i32 {
+        let mut next = board.clone();
+        let (symbol, saved) = queues[turn].clone();
+        next.place_mark(&mut queues[turn].1, Some(max_marks), pos, symbol)
+            .unwrap();
+        let score = self.vanishing_search(
+            &next,
+            queues,
+            (turn + 1) % queues.len(),
+            max_marks,
+            depth - 1,
+        );
+        queues[turn].1 = saved;
+        score
+    }
+
+    fn vanishing_search(
+        &self,
+        board: &GameBoard,
+        queues: &mut [MarkQueue],
+        turn: usize,
+        max_marks: usize,
+        depth: usize,
+    ) -> i32 {
+        // Sooner wins and later losses score higher
+        let score = self.evaluate(board);
+        if score.abs() == WIN_SCORE {
+            return score + score.signum() * depth as i32;
+        }
+        let empty_positions = board.empty_positions();
+        if depth == 0 || empty_positions.is_empty() {
+            return score;
+        }
+        let ours = queues[turn].0 == self.symbol;
+        let scores = empty_positions
+            .into_iter()
+            .map(|pos| self.vanishing_score(board, queues, turn, max_marks, pos, depth));
+        if ours {
+            scores.max().unwrap()
+        } else {
+            scores.min().unwrap()
+        }
+    }
+
+    // Connect6: the `stones` still to place this turn, picked together.
+    // Finish a line if they reach, otherwise try the combinations of the
+    // most promising cells and keep the one that leaves the opponents the
+    // fewest lines they can finish with their `opponent_stones`, then the
+    // one that evaluates best
+    pub fn decide_stones(
+        &self,
+        board: &GameBoard,
+        stones: usize,
+        opponent_stones: usize,
+    ) -> Vec<(usize, usize)> {
+        let empty_positions = board.empty_positions();
+        let stones = stones.min(empty_positions.len());
+        let play_randomly = match self.difficulty {
+            Difficulty::Easy => true,
+            Difficulty::Medium => rand::random::<f32>() >= 0.7,
+            Difficulty::Hard => false,
+        };
+        if play_randomly {
+            return empty_positions
+                .choose_multiple(&mut rand::thread_rng(), stones)
+                .copied()
+                .collect();
+        }
+
+        let win_length = board.win_length();
+        let lines = board.win_lines();
+        if !board.is_misere() {
+            let finishing = lines.iter().find(|line| {
+                board
+                    .cells_needed(line, self.symbol)
+                    .is_some_and(|needed| needed <= stones)
+            });
+            if let Some(line) = finishing {
+                return line
+                    .iter()
+                    .copied()
+                    .filter(|&pos| board.get_cell(pos).is_none())
+                    .collect();
+            }
+        }
+
+        let opponents: Vec<Symbol> = board
+            .get_all_symbols()
+            .into_iter()
+            .filter(|&symbol| symbol != self.symbol)
+            .collect();
+        // Started opponent lines they can finish on their next turn
+        let threats = |board: &GameBoard| {
+            lines
+                .iter()
+                .filter(|line| {
+                    opponents.iter().any(|&opponent| {
+                        board
+                            .cells_needed(line, opponent)
+                            .is_some_and(|needed| needed <= opponent_stones && needed < win_length)
+                    })
+                })
+                .count()
+        };
+        // Open lines through the cell, the further along the more they count
+        let cell_score = |pos: (usize, usize)| -> i32 {
+            let mut score = 0;
+            for line in lines.iter().filter(|line| line.contains(&pos)) {
+                let filled = |needed: usize| (win_length - needed) as i32;
+                if let Some(needed) = board.cells_needed(line, self.symbol) {
+                    score += line_weight(2 * filled(needed));
+                }
+                for &opponent in &opponents {
+                    if let Some(needed) = board.cells_needed(line, opponent) {
+                        score += line_weight(2 * filled(needed) - 1);
+                    }
+                }
+            }
+            score
+        };
+
+        let mut candidates: Vec<(i32, (usize, usize))> = empty_positions
+            .iter()
+            .map(|&pos| (cell_score(pos), pos))
+            .collect();
+        candidates.sort_by_key(|&(score, _)| Reverse(score));
+        candidates.truncate(STONE_CANDIDATES.max(stones));
+        let cells: Vec<(usize, usize)> = candidates.into_iter().map(|(_, pos)| pos).collect();
+
+        let mut best: Vec<Vec<(usize, usize)>> = Vec::new();
+        let mut best_key = (usize::MAX, i32::MIN);
+        for combination in combinations(&cells, stones) {
+            let mut after = board.clone();
+            for &pos in &combination {
+                after.apply_move(pos, self.symbol).unwrap();
+            }
+            let key = (threats(&after), self.evaluate(&after));
+            if key.0 < best_key.0 || (key.0 == best_key.0 && key.1 > best_key.1) {
+                best_key = key;
+                best.clear();
+            }
+            if key == best_key {
+                best.push(combination);
+            }
+        }
+        best.choose(&mut rand::thread_rng())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    // Pie rule: the first move that leaves the position closest to even, so
+    // that the other player has no reason to take it over
+    pub fn decide_pie_move(&self, board: &GameBoard, opponent: Symbol) -> (usize, usize) {
+        if self.difficulty == Difficulty::Easy {
+            return self.random_move(board);
+        }
+        let theirs = AI::new(self.difficulty, opponent);
+        let mut positions = board.empty_positions();
+        positions.shuffle(&mut rand::thread_rng());
+        positions
+            .into_iter()
+            .min_by_key(|&pos| {
+                let mut next = board.clone();
+                next.apply_move(pos, self.symbol).unwrap();
+                (self.evaluate(&next) - theirs.evaluate(&next)).abs()
+            })
+            .unwrap()
+    }
+
+    // Swap openings: take black when the stones favor it, white when they
+    // favor white, and when they look even place two more if allowed. Under
+    // the pie rule black is the first move, taken over with a swap
+    pub fn choose_opening(
+        &self,
+        board: &GameBoard,
+        black: Symbol,
+        white: Symbol,
+        options: &[OpeningChoice],
+    ) -> OpeningChoice {
+        let play_randomly = match self.difficulty {
+            Difficulty::Easy => true,
+            Difficulty::Medium => rand::random::<f32>() >= 0.7,
+            Difficulty::Hard => false,
+        };
+        if play_randomly {
+            return *options.choose(&mut rand::thread_rng()).unwrap();
+        }
+        let black_score = AI::new(self.difficulty, black).evaluate(board);
+        let white_score = AI::new(self.difficulty, white).evaluate(board);
+        if options.contains(&OpeningChoice::Swap) {
+            if black_score > white_score {
+                OpeningChoice::Swap
+            } else {
+                OpeningChoice::Keep
+            }
+        } else if black_score > white_score {
+            OpeningChoice::Black
+        } else if white_score > black_score || !options.contains(&OpeningChoice::PlaceTwo) {
+            OpeningChoice::White
+        } else {
+            OpeningChoice::PlaceTwo
+        }
+    }
+
+    // How good the position looks for our symbol: a won or lost game
+    // outweighs everything, otherwise every line only one side can still
+    // complete counts, more the further along it is. In misère play all of
+    // that counts against the line's owner
+    pub fn evaluate(&self, board: &GameBoard) -> i32 {
+        let sign = if board.is_misere() { -1 } else { 1 };
+        match board.winner_symbol() {
+            Some(symbol) if symbol == self.symbol => return sign * WIN_SCORE,
+            Some(_) => return -sign * WIN_SCORE,
+            None => {}
+        }
+        let mut score = 0;
+        for line in board.win_lines() {
+            let (mut mine, mut theirs) = (0, 0);
+            for &pos in line {
+                match board.get_cell(pos) {
+                    Some(symbol) if symbol == self.symbol => mine += 1,
+                    Some(_) => theirs += 1,
+                    None => {}
+                }
+            }
+            match (mine, theirs) {
+                (0, 0) => {}
+                (mine, 0) => score += line_weight(2 * (mine - 1)),
+                (0, theirs) => score -= line_weight(2 * (theirs - 1)),
+                _ => {}
+            }
+        }
+        (sign * score).clamp(1 - WIN_SCORE, WIN_SCORE - 1)
+    }
+
+    pub fn decide_ultimate_move(&self, board: &UltimateBoard) -> (usize, usize) {
+        let moves = board.legal_moves();
+        let play_randomly = match self.difficulty {
+            Difficulty::Easy => true,
+            Difficulty::Medium => rand::random::<f32>() >= 0.7,
+            Difficulty::Hard => false,
+        };
+        if play_randomly {
+            return *moves.choose(&mut rand::thread_rng()).unwrap();
+        }
+
+        let scored: Vec<(i32, (usize, usize))> = moves
+            .iter()
+            .map(|&pos| (self.score_ultimate_move(board, pos), pos))
+            .collect();
+        let best = scored.iter().map(|&(score, _)| score).max().unwrap();
+        let best_moves: Vec<(usize, usize)> = scored
+            .into_iter()
+            .filter(|&(score, _)| score == best)
+            .map(|(_, pos)| pos)
+            .collect();
+        *best_moves.choose(&mut rand::thread_rng()).unwrap()
+    }
+
+    // Winning a sub-board counts most, then stopping the opponent from
+    // winning one, and not sending them where they win or may play anywhere
+    fn score_ultimate_move(&self, board: &UltimateBoard, pos: (usize, usize)) -> i32 {
+        let mut after = board.clone();
+        if after.apply_move(pos, self.symbol).is_err() {
+            return i32::MIN;
+        }
+        if after.winner_symbol() == Some(self.symbol) {
+            return WIN_SCORE;
+        }
+
+        let index = UltimateBoard::sub_board_of(pos);
+        let opponents: Vec<Symbol> = board
+            .get_all_symbols()
+            .into_iter()
+            .filter(|&symbol| symbol != self.symbol)
+            .collect();
+        let mut score = 0;
+        if after.sub_board_winner(index) == Some(self.symbol) {
+            score += 100;
+        }
+        for &opponent in &opponents {
+            let mut blocked = board.clone();
+            if blocked.apply_move(pos, opponent).is_ok()
+                && blocked.sub_board_winner(index) == Some(opponent)
+            {
+                score += 80;
+            }
+        }
+
+        let (row, col) = (pos.0 % SUB_SIZE, pos.1 % SUB_SIZE);
+        let target = row * SUB_SIZE + col;
+        if after.is_decided(target) {
+            score -= 50;
+        } else {
+            for &opponent in &opponents {
+                let mut sub_board = after.sub_board(target).clone();
+                if !sub_board.get_winning_positions(opponent).is_empty() {
+                    score -= 70;
+                }
+            }
+        }
+
+        let center = SUB_SIZE / 2;
+        if (row, col) == (center, center) {
+            score += 3;
+        } else if row != center && col != center {
+            score += 2;
+        }
+        score
+    }
+
+    pub fn decide_cube_move(&self, board: &CubeBoard) -> CubePosition {
+        let empty_positions = board.empty_positions();
+        let play_randomly = match self.difficulty {
+            Difficulty::Easy => true,
+            Difficulty::Medium => rand::random::<f32>() >= 0.7,
+            Difficulty::Hard => false,
+        };
+        if play_randomly {
+            return *empty_positions.choose(&mut rand::thread_rng()).unwrap();
+        }
+
+        let opponents: Vec<Symbol> = board
+            .get_all_symbols()
+            .into_iter()
+            .filter(|&symbol| symbol != self.symbol)
+            .collect();
+        // Lines `symbol` would complete or nearly complete by playing `pos`
+        let lines_after = |pos: CubePosition, symbol: Symbol, needed: usize| {
+            board
+                .lines()
+                .iter()
+                .filter(|line| line.contains(&pos))
+                .filter(|line| board.cells_needed(line, symbol) == Some(needed + 1))
+                .count()
+        };
+
+        // Win, then block, then fork: two lines one move from done
+        for pos in &empty_positions {
+            if lines_after(*pos, self.symbol, 0) > 0 {
+                return *pos;
+            }
+        }
+        for &opponent in &opponents {
+            for pos in &empty_positions {
+                if lines_after(*pos, opponent, 0) > 0 {
+                    return *pos;
+                }
+            }
+        }
+        for pos in &empty_positions {
+            if lines_after(*pos, self.symbol, 1) >= 2 {
+                return *pos;
+            }
+        }
+        for &opponent in &opponents {
+            for pos in &empty_positions {
+                if lines_after(*pos, opponent, 1) >= 2 {
+                    return *pos;
+                }
+            }
+        }
+
+        // Otherwise the cell on the most lines still open, weighing lines
+        // already started by either side higher
+        let score = |pos: CubePosition| -> i32 {
+            let mut score = 0;
+            for line in board.lines().iter().filter(|line| line.contains(&pos)) {
+                let filled = board.win_length() as i32;
+                if let Some(needed) = board.cells_needed(line, self.symbol) {
+                    score += line_weight(2 * (filled - needed as i32));
+                }
+                for &opponent in &opponents {
+                    if let Some(needed) = board.cells_needed(line, opponent) {
+                        score += line_weight(2 * (filled - needed as i32) - 1);
+                    }
+                }
+            }
+            score
+        };
+        let scored: Vec<(i32, CubePosition)> = empty_positions
+            .iter()
+            .map(|&pos| (score(pos), pos))
+            .collect();
+        let best = scored.iter().map(|&(score, _)| score).max().unwrap();
+        let best_moves: Vec<CubePosition> = scored
+            .into_iter()
+            .filter(|&(score, _)| score == best)
+            .map(|(_, pos)| pos)
+            .collect();
+        *best_moves.choose(&mut rand::thread_rng()).unwrap()
+    }
+
+    pub fn difficulty(&self) -> Difficulty {
+        self.difficulty
+    }
+
+    // Accept when we cannot win anymore, or when there is nothing left to win
+    // right away and the board is mostly filled. In misère play, accept once
+    // most of the free cells would lose for us
+    pub fn accepts_draw(&self, board: &GameBoard) -> bool {
+        if board.is_misere() {
+            let empty = board.empty_positions();
+            let losing = empty
+                .iter()
+                .filter(|&&pos| board.completes_line(pos, self.symbol))
+                .count();
+            return losing * 2 >= empty.len();
+        }
+        if !board.can_still_win(self.symbol, None) {
+            return true;
+        }
+        let mut board = board.clone();
+        let empty = board.empty_positions().len();
+        let total = board.rows() * board.cols();
+        board.get_winning_positions(self.symbol).is_empty() && empty * 2 <= total
+    }
+
     pub fn adjust_difficulty(&mut self, difficulty: Difficulty) {
         self.difficulty = difficulty;
     }
 }
+
+// Every way to pick `count` of `cells`, in their order
+fn combinations(cells: &[(usize, usize)], count: usize) -> Vec<Vec<(usize, usize)>> {
+    if count == 0 {
+        return vec![Vec::new()];
+    }
+    let mut result = Vec::new();
+    for (i, &first) in cells.iter().enumerate() {
+        for mut rest in combinations(&cells[i + 1..], count - 1) {
+            rest.insert(0, first);
+            result.push(rest);
+        }
+    }
+    result
+}
+
+// Lines count for more the further along they are. Past the cap a line
+// already outweighs anything short of a win, and the sums over long lines
+// on big boards can't overflow
+fn line_weight(shift: i32) -> i32 {
+    1 << shift.clamp(0, MAX_LINE_SHIFT)
+}
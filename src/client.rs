@@ -0,0 +1,71 @@
+use crate::protocol::{read_message, write_message, ClientMessage, ServerMessage};
+use crate::types::{PlayerId, Token};
+use std::io::{self, BufReader};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::thread;
+use std::time::Duration;
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+pub struct GameClient {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl GameClient {
+    pub fn connect(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        Ok(Self {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        })
+    }
+
+    // Connects again after a dropped connection and takes back the seat,
+    // proving it with the token from the welcome
+    pub fn rejoin(
+        addr: impl ToSocketAddrs + Copy,
+        player: PlayerId,
+        token: &Token,
+        attempts: usize,
+    ) -> io::Result<Self> {
+        let mut last_error = io::Error::from(io::ErrorKind::NotConnected);
+        for attempt in 0..attempts {
+            if attempt > 0 {
+                thread::sleep(RECONNECT_DELAY);
+            }
+            match Self::connect(addr) {
+                Ok(mut client) => {
+                    client.send(&ClientMessage::Rejoin {
+                        player,
+                        token: token.clone(),
+                    })?;
+                    return Ok(client);
+                }
+                Err(e) => last_error = e,
+            }
+        }
+        Err(last_error)
+    }
+
+    // A second handle on the same connection, e.g. to read on another thread
+    pub fn try_clone(&self) -> io::Result<Self> {
+        let stream = self.writer.try_clone()?;
+        Ok(Self {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        })
+    }
+
+    pub fn set_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.writer.set_read_timeout(timeout)
+    }
+
+    pub fn send(&mut self, message: &ClientMessage) -> io::Result<()> {
+        write_message(&mut self.writer, message)
+    }
+
+    pub fn recv(&mut self) -> io::Result<ServerMessage> {
+        read_message(&mut self.reader)?.ok_or_else(|| io::ErrorKind::UnexpectedEof.into())
+    }
+}
@@ -0,0 +1,87 @@
+use crate::types::{CommandError, OpeningChoice, Symbol};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Move((usize, usize)),
+    // Wild games: "<row> <col> <symbol>"
+    Place((usize, usize), Symbol),
+    // Swap openings: "black", "white" or "two", the pie rule "swap" or "keep"
+    Choose(OpeningChoice),
+    Undo,
+    Redo,
+    Hint,
+    Save(String),
+    Resign,
+    OfferDraw,
+    Board,
+    Score,
+    Help,
+    Quit,
+}
+
+impl Command {
+    pub const HELP: &'static str = "\
+Commands:
+  <row> <col>       place your symbol, e.g. '1 1'
+  <row> <col> <sym> in wild games, place any shared symbol, e.g. '1 1 O'
+  black / white     in swap openings, take that color
+  two               in swap2, place two more stones instead
+  swap / keep       under the pie rule, take over the first move or not
+  undo / redo       take back or replay the last move
+  hint              suggest a move
+  save <file>       save the game to a file
+  resign            give up the game
+  offer draw        propose a draw to the other players
+  board             show the board again
+  score             show the scores
+  help              show this help
+  quit              leave the game";
+
+    pub fn parse(input: &str) -> Result<Command, CommandError> {
+        let words: Vec<&str> = input.split_whitespace().collect();
+        let (first, rest) = match words.split_first() {
+            Some((first, rest)) => (first.to_lowercase(), rest),
+            None => return Err(CommandError::Empty),
+        };
+
+        if first.chars().all(|c| c.is_ascii_digit()) {
+            let row = first
+                .parse()
+                .map_err(|_| CommandError::InvalidCoordinates)?;
+            return match rest {
+                [col] => {
+                    let col = col.parse().map_err(|_| CommandError::InvalidCoordinates)?;
+                    Ok(Command::Move((row, col)))
+                }
+                [col, symbol] => {
+                    let col = col.parse().map_err(|_| CommandError::InvalidCoordinates)?;
+                    let symbol = Symbol::parse(symbol, &[])
+                        .map_err(|_| CommandError::InvalidSymbol(symbol.to_string()))?;
+                    Ok(Command::Place((row, col), symbol))
+                }
+                _ => Err(CommandError::InvalidCoordinates),
+            };
+        }
+
+        match (first.as_str(), rest) {
+            ("black", []) => Ok(Command::Choose(OpeningChoice::Black)),
+            ("white", []) => Ok(Command::Choose(OpeningChoice::White)),
+            ("two", []) => Ok(Command::Choose(OpeningChoice::PlaceTwo)),
+            ("swap", []) => Ok(Command::Choose(OpeningChoice::Swap)),
+            ("keep", []) => Ok(Command::Choose(OpeningChoice::Keep)),
+            ("undo", []) => Ok(Command::Undo),
+            ("redo", []) => Ok(Command::Redo),
+            ("hint", []) => Ok(Command::Hint),
+            ("save", []) => Err(CommandError::MissingArgument("file name")),
+            ("save", _) => Ok(Command::Save(rest.join(" "))),
+            ("resign", []) => Ok(Command::Resign),
+            ("offer", [what]) if what.eq_ignore_ascii_case("draw") => Ok(Command::OfferDraw),
+            ("draw", []) => Ok(Command::OfferDraw),
+            ("board", []) => Ok(Command::Board),
+            ("score", []) | ("scores", []) => Ok(Command::Score),
+            ("help", []) | ("?", []) => Ok(Command::Help),
+            ("quit", []) | ("exit", []) => Ok(Command::Quit),
+            _ => Err(CommandError::Unknown(input.trim().to_string())),
+        }
+    }
+}
@@ -0,0 +1,312 @@
+use crate::player::Player;
+use crate::types::{BoardError, DrawReason, GameError, GameStatus, PlayerId, Symbol, WinReason};
+
+// (layer, row, col)
+pub type CubePosition = (usize, usize, usize);
+
+// An n×n×n board, 4x4x4 with four in a row is Qubic. Lines run along the
+// rows, columns and pillars, the diagonals of every plane and the four
+// space diagonals through the cube
+#[derive(Debug, Clone)]
+pub struct CubeBoard {
+    cells: Vec<Option<Symbol>>,
+    size: usize,
+    win_length: usize,
+    lines: Vec<Vec<CubePosition>>,
+}
+
+impl CubeBoard {
+    pub fn new(size: usize, win_length: usize) -> Self {
+        Self {
+            cells: vec![None; size * size * size],
+            size,
+            win_length,
+            lines: Self::all_lines(size, win_length),
+        }
+    }
+
+    // One direction out of each pair of opposite ones
+    fn directions() -> Vec<(isize, isize, isize)> {
+        let mut directions = Vec::new();
+        for d_layer in -1..=1 {
+            for d_row in -1..=1 {
+                for d_col in -1..=1 {
+                    let first = [d_layer, d_row, d_col].into_iter().find(|&d| d != 0);
+                    if first == Some(1) {
+                        directions.push((d_layer, d_row, d_col));
+                    }
+                }
+            }
+        }
+        directions
+    }
+
+    fn all_lines(size: usize, length: usize) -> Vec<Vec<CubePosition>> {
+        let mut lines = Vec::new();
+        if length == 0 || length > size {
+            return lines;
+        }
+        let inside = |start: usize, d: isize| {
+            let end = start as isize + d * (length as isize - 1);
+            end >= 0 && end < size as isize
+        };
+        for (d_layer, d_row, d_col) in Self::directions() {
+            for layer in 0..size {
+                for row in 0..size {
+                    for col in 0..size {
+                        if !inside(layer, d_layer) || !inside(row, d_row) || !inside(col, d_col) {
+                            continue;
+                        }
+                        lines.push(
+                            (0..length as isize)
+                                .map(|i| {
+                                    (
+                                        (layer as isize + d_layer * i) as usize,
+                                        (row as isize + d_row * i) as usize,
+                                        (col as isize + d_col * i) as usize,
+                                    )
+                                })
+                                .collect(),
+                        );
+                    }
+                }
+            }
+        }
+        lines
+    }
+
+    // "layer row col", e.g. "0 3 3"
+    pub fn parse_position(input: &str) -> Option<CubePosition> {
+        let coords: Vec<usize> = input
+            .split_whitespace()
+            .map(|word| word.parse().ok())
+            .collect::<Option<_>>()?;
+        match coords[..] {
+            [layer, row, col] => Some((layer, row, col)),
+            _ => None,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn win_length(&self) -> usize {
+        self.win_length
+    }
+
+    // Every run of win_length cells, 76 of them for Qubic
+    pub fn lines(&self) -> &[Vec<CubePosition>] {
+        &self.lines
+    }
+
+    fn index(&self, pos: CubePosition) -> Option<usize> {
+        let (layer, row, col) = pos;
+        if layer < self.size && row < self.size && col < self.size {
+            Some((layer * self.size + row) * self.size + col)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_cell(&self, pos: CubePosition) -> Option<Symbol> {
+        self.index(pos).and_then(|index| self.cells[index])
+    }
+
+    pub fn check_move(&self, pos: CubePosition) -> Result<(), BoardError> {
+        match self.index(pos) {
+            None => Err(BoardError::OutOfBounds),
+            Some(index) if self.cells[index].is_some() => Err(BoardError::CellOccupied),
+            Some(_) => Ok(()),
+        }
+    }
+
+    pub fn is_move_valid(&self, pos: CubePosition) -> bool {
+        self.check_move(pos).is_ok()
+    }
+
+    pub fn apply_move(&mut self, pos: CubePosition, symbol: Symbol) -> Result<(), BoardError> {
+        self.check_move(pos)?;
+        let index = self.index(pos).unwrap();
+        self.cells[index] = Some(symbol);
+        Ok(())
+    }
+
+    pub fn undo_move(&mut self, pos: CubePosition) -> Result<(), BoardError> {
+        let index = self.index(pos).ok_or(BoardError::OutOfBounds)?;
+        self.cells[index] = None;
+        Ok(())
+    }
+
+    pub fn empty_positions(&self) -> Vec<CubePosition> {
+        let mut positions = Vec::new();
+        for layer in 0..self.size {
+            for row in 0..self.size {
+                for col in 0..self.size {
+                    if self.get_cell((layer, row, col)).is_none() {
+                        positions.push((layer, row, col));
+                    }
+                }
+            }
+        }
+        positions
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.cells.iter().all(Option::is_some)
+    }
+
+    // Number of empty cells `symbol` still needs to fill the line, None if
+    // another symbol already blocks it
+    pub fn cells_needed(&self, line: &[CubePosition], symbol: Symbol) -> Option<usize> {
+        let mut needed = 0;
+        for &pos in line {
+            match self.get_cell(pos) {
+                Some(other) if other != symbol => return None,
+                Some(_) => {}
+                None => needed += 1,
+            }
+        }
+        Some(needed)
+    }
+
+    pub fn winning_line(&self) -> Option<&[CubePosition]> {
+        self.lines
+            .iter()
+            .find(|line| {
+                let first = self.get_cell(line[0]);
+                first.is_some() && line.iter().all(|&pos| self.get_cell(pos) == first)
+            })
+            .map(|line| &line[..])
+    }
+
+    pub fn winner_symbol(&self) -> Option<Symbol> {
+        self.winning_line().and_then(|line| self.get_cell(line[0]))
+    }
+
+    pub fn get_all_symbols(&self) -> Vec<Symbol> {
+        let mut symbols = Vec::new();
+        for symbol in self.cells.iter().flatten() {
+            if !symbols.contains(symbol) {
+                symbols.push(*symbol);
+            }
+        }
+        symbols
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CubeMove {
+    pub position: CubePosition,
+    pub symbol: Symbol,
+    pub player: PlayerId,
+}
+
+// Two players taking turns on a `CubeBoard`
+#[derive(Debug, Clone)]
+pub struct CubeGame {
+    board: CubeBoard,
+    players: Vec<Player>,
+    current_player_idx: usize,
+    history: Vec<CubeMove>,
+}
+
+impl CubeGame {
+    pub const MAX_PLAYERS: usize = 2;
+
+    pub fn new(size: usize, win_length: usize) -> Self {
+        Self {
+            board: CubeBoard::new(size, win_length),
+            players: Vec::new(),
+            current_player_idx: 0,
+            history: Vec::new(),
+        }
+    }
+
+    // 4x4x4, four in a row
+    pub fn qubic() -> Self {
+        Self::new(4, 4)
+    }
+
+    pub fn add_player(&mut self, player: &Player) -> Result<(), GameError> {
+        if self.players.len() >= Self::MAX_PLAYERS {
+            return Err(GameError::MaxPlayersReached);
+        }
+        if self.players.iter().any(|p| p.id() == player.id()) {
+            return Err(GameError::PlayerAlreadyJoined);
+        }
+        self.players.push(player.clone());
+        Ok(())
+    }
+
+    pub fn board(&self) -> &CubeBoard {
+        &self.board
+    }
+
+    pub fn players(&self) -> &[Player] {
+        &self.players
+    }
+
+    pub fn current_player(&self) -> Option<&Player> {
+        self.players.get(self.current_player_idx)
+    }
+
+    pub fn history(&self) -> &[CubeMove] {
+        &self.history
+    }
+
+    pub fn last_move(&self) -> Option<CubeMove> {
+        self.history.last().copied()
+    }
+
+    pub fn is_move_valid(&self, pos: CubePosition) -> bool {
+        !self.is_over() && self.board.is_move_valid(pos)
+    }
+
+    pub fn make_move(&mut self, pos: CubePosition) -> Result<(), GameError> {
+        if self.players.len() < Self::MAX_PLAYERS {
+            return Err(GameError::InvalidMove);
+        }
+        if self.is_over() {
+            return Err(GameError::GameOver);
+        }
+        let player = &self.players[self.current_player_idx];
+        let (symbol, id) = (player.symbol(), player.id());
+        self.board.apply_move(pos, symbol)?;
+        self.history.push(CubeMove {
+            position: pos,
+            symbol,
+            player: id,
+        });
+        self.current_player_idx = (self.current_player_idx + 1) % self.players.len();
+        Ok(())
+    }
+
+    // The move the current player's AI would play, None for humans
+    pub fn ai_move(&self) -> Option<CubePosition> {
+        let ai = self.current_player()?.ai.as_ref()?;
+        if self.is_over() {
+            return None;
+        }
+        Some(ai.decide_cube_move(&self.board))
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.board.winner_symbol().is_some() || self.board.is_full()
+    }
+
+    pub fn winner(&self) -> Option<&Player> {
+        let symbol = self.board.winner_symbol()?;
+        self.players.iter().find(|p| p.symbol() == symbol)
+    }
+
+    pub fn status(&self) -> GameStatus {
+        if let Some(winner) = self.winner() {
+            GameStatus::Win(winner.name().to_string(), WinReason::Streak)
+        } else if self.is_over() {
+            GameStatus::Draw(DrawReason::BoardFull)
+        } else {
+            GameStatus::InProgress
+        }
+    }
+}
@@ -0,0 +1,403 @@
+use crate::ai::AI;
+use crate::game::Game;
+use crate::game_board::GameBoard;
+use crate::rules::{GameRules, GomokuRule, Opening};
+use crate::runner::{MoveProvider, PlayerAction, TurnContext};
+use crate::types::{Difficulty, EngineError, Symbol, SymbolSet};
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const DEFAULT_MOVE_TIME: Duration = Duration::from_secs(1);
+const QUIT_POLLS: usize = 25;
+const QUIT_POLL_INTERVAL: Duration = Duration::from_millis(20);
+// Time on top of the move time for the reply to arrive, and for `readyok`
+const REPLY_GRACE: Duration = Duration::from_secs(2);
+
+// One line of the engine protocol, e.g.
+//   position size 3 win 3 turn 1 moves 0:1,1 1:0,0 0:2,2
+//   position size 4 cols 5 win 4 turn 0
+//   go movetime 500
+#[derive(Debug, Clone, PartialEq)]
+pub enum EngineCommand {
+    IsReady,
+    SetOption { name: String, value: String },
+    Position(Position),
+    Go { movetime: Option<Duration> },
+    Quit,
+}
+
+// Players are numbered from 0 in turn order
+#[derive(Debug, Clone, PartialEq)]
+pub struct Position {
+    // Rows, and columns too unless `cols` is given
+    pub size: usize,
+    pub cols: usize,
+    pub win_length: usize,
+    pub turn: usize,
+    pub moves: Vec<(usize, (usize, usize))>,
+}
+
+impl Position {
+    pub fn of(game: &Game) -> Self {
+        let index_of = |id| game.players().iter().position(|p| p.id() == id);
+        Self {
+            size: game.board().rows(),
+            cols: game.board().cols(),
+            win_length: game.board().win_length(),
+            turn: game.current_player_idx(),
+            moves: game
+                .history()
+                .iter()
+                .filter_map(|mv| Some((index_of(mv.player)?, mv.position)))
+                .collect(),
+        }
+    }
+
+    // Whether positions can describe games under these rules. They only
+    // carry the board size, win length and moves, one stone per turn, so
+    // marks that vanish, cells that never were moves, other kinds of lines
+    // and opening choices are out
+    pub fn supports(rules: &GameRules) -> bool {
+        rules.max_marks.is_none()
+            && rules.blocked.is_empty()
+            && rules.opening_stones.iter().all(Vec::is_empty)
+            && rules.stones_per_turn.unwrap_or(1) <= 1
+            && !rules.misere
+            && !rules.is_wild()
+            && rules.gomoku == GomokuRule::Freestyle
+            && rules.opening == Opening::Free
+    }
+
+    fn parse(args: &[&str]) -> Result<Self, EngineError> {
+        let number = |value: Option<&&str>, name| {
+            value
+                .and_then(|value| value.parse().ok())
+                .ok_or(EngineError::InvalidArgument(name))
+        };
+        let mut position = Position {
+            size: 0,
+            cols: 0,
+            win_length: 0,
+            turn: 0,
+            moves: Vec::new(),
+        };
+        let mut words = args.iter();
+        while let Some(&word) = words.next() {
+            match word {
+                "size" => position.size = number(words.next(), "size")?,
+                "cols" => position.cols = number(words.next(), "cols")?,
+                "win" => position.win_length = number(words.next(), "win length")?,
+                "turn" => position.turn = number(words.next(), "turn")?,
+                "moves" => {
+                    for mv in words.by_ref() {
+                        position.moves.push(parse_move(mv)?);
+                    }
+                }
+                _ => return Err(EngineError::InvalidArgument("position")),
+            }
+        }
+        if position.size == 0 {
+            return Err(EngineError::InvalidArgument("size"));
+        }
+        if position.cols == 0 {
+            position.cols = position.size;
+        }
+        if position.win_length == 0 {
+            position.win_length = position.size.min(position.cols);
+        }
+        Ok(position)
+    }
+
+    fn board(&self) -> Result<GameBoard, EngineError> {
+        let mut board = GameBoard::with_dimensions(self.size, self.cols, self.win_length);
+        for &(player, pos) in &self.moves {
+            board
+                .apply_move(pos, player_symbol(player)?)
+                .map_err(|_| EngineError::IllegalMove(pos))?;
+        }
+        Ok(board)
+    }
+}
+
+impl std::fmt::Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "position size {}", self.size)?;
+        if self.cols != self.size {
+            write!(f, " cols {}", self.cols)?;
+        }
+        write!(f, " win {} turn {}", self.win_length, self.turn)?;
+        if !self.moves.is_empty() {
+            write!(f, " moves")?;
+            for (player, (row, col)) in &self.moves {
+                write!(f, " {}:{},{}", player, row, col)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// "<player>:<row>,<col>"
+fn parse_move(input: &str) -> Result<(usize, (usize, usize)), EngineError> {
+    let invalid = || EngineError::InvalidArgument("move");
+    let (player, pos) = input.split_once(':').ok_or_else(invalid)?;
+    let pos = parse_position(pos).ok_or_else(invalid)?;
+    Ok((player.parse().map_err(|_| invalid())?, pos))
+}
+
+fn parse_position(input: &str) -> Option<(usize, usize)> {
+    let (row, col) = input.split_once(',')?;
+    Some((row.parse().ok()?, col.parse().ok()?))
+}
+
+// The engine only tells players apart, so the symbols are just labels
+fn player_symbol(player: usize) -> Result<Symbol, EngineError> {
+    SymbolSet::Ascii
+        .symbols()
+        .get(player)
+        .map(|&c| Symbol(c))
+        .ok_or(EngineError::InvalidArgument("player"))
+}
+
+fn parse_difficulty(value: &str) -> Option<Difficulty> {
+    match value.to_lowercase().as_str() {
+        "easy" => Some(Difficulty::Easy),
+        "medium" => Some(Difficulty::Medium),
+        "hard" => Some(Difficulty::Hard),
+        _ => None,
+    }
+}
+
+impl EngineCommand {
+    pub fn parse(input: &str) -> Result<Self, EngineError> {
+        let words: Vec<&str> = input.split_whitespace().collect();
+        match words.as_slice() {
+            ["isready"] => Ok(EngineCommand::IsReady),
+            ["setoption", "name", name, "value", value @ ..] if !value.is_empty() => {
+                Ok(EngineCommand::SetOption {
+                    name: name.to_lowercase(),
+                    value: value.join(" "),
+                })
+            }
+            ["setoption", ..] => Err(EngineError::InvalidArgument("option")),
+            ["position", args @ ..] => Ok(EngineCommand::Position(Position::parse(args)?)),
+            ["go"] => Ok(EngineCommand::Go { movetime: None }),
+            ["go", "movetime", ms] => ms
+                .parse()
+                .map(|ms| EngineCommand::Go {
+                    movetime: Some(Duration::from_millis(ms)),
+                })
+                .map_err(|_| EngineError::InvalidArgument("movetime")),
+            ["quit"] => Ok(EngineCommand::Quit),
+            _ => Err(EngineError::UnknownCommand(input.trim().to_string())),
+        }
+    }
+}
+
+// Answers the engine protocol with the built-in AI
+pub struct Engine {
+    difficulty: Difficulty,
+    position: Option<Position>,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        Self {
+            difficulty: Difficulty::Hard,
+            position: None,
+        }
+    }
+
+    // Returns the reply to send back, if any
+    pub fn handle(&mut self, command: EngineCommand) -> Result<Option<String>, EngineError> {
+        match command {
+            EngineCommand::IsReady => Ok(Some("readyok".to_string())),
+            EngineCommand::SetOption { name, value } => match name.as_str() {
+                "difficulty" => {
+                    self.difficulty = parse_difficulty(&value)
+                        .ok_or(EngineError::InvalidArgument("difficulty"))?;
+                    Ok(None)
+                }
+                _ => Err(EngineError::InvalidArgument("option name")),
+            },
+            EngineCommand::Position(position) => {
+                position.board()?;
+                player_symbol(position.turn)?;
+                self.position = Some(position);
+                Ok(None)
+            }
+            EngineCommand::Go { movetime } => {
+                let position = self
+                    .position
+                    .as_ref()
+                    .ok_or(EngineError::InvalidArgument("position"))?;
+                let board = position.board()?;
+                if board.empty_positions().is_empty() {
+                    return Err(EngineError::NoLegalMoves);
+                }
+                let ai = AI::new(self.difficulty, player_symbol(position.turn)?);
+                let deadline = Instant::now() + movetime.unwrap_or(DEFAULT_MOVE_TIME);
+                let (row, col) = ai.decide_move_by(&board, Some(deadline));
+                Ok(Some(format!("bestmove {},{}", row, col)))
+            }
+            EngineCommand::Quit => Ok(None),
+        }
+    }
+
+    // Reads commands until `quit` or the end of the input
+    pub fn run(&mut self, input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let reply = match EngineCommand::parse(&line) {
+                Ok(EngineCommand::Quit) => return Ok(()),
+                Ok(command) => self.handle(command),
+                Err(e) => Err(e),
+            };
+            match reply {
+                Ok(Some(reply)) => writeln!(output, "{}", reply)?,
+                Ok(None) => {}
+                Err(e) => writeln!(output, "error {}", e)?,
+            }
+            output.flush()?;
+        }
+        Ok(())
+    }
+}
+
+// Plays with an external engine speaking the protocol above over stdin/stdout
+pub struct EngineProvider {
+    child: Child,
+    input: ChildStdin,
+    // Lines from the engine, read on a thread of their own so a silent
+    // engine can be given up on
+    replies: Receiver<String>,
+    move_time: Duration,
+}
+
+impl EngineProvider {
+    pub fn spawn(program: &str, args: &[&str]) -> io::Result<Self> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let input = child.stdin.take().ok_or(io::ErrorKind::BrokenPipe)?;
+        let output = child.stdout.take().ok_or(io::ErrorKind::BrokenPipe)?;
+        let (sender, replies) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(output).lines() {
+                let Ok(line) = line else { return };
+                if sender.send(line).is_err() {
+                    return;
+                }
+            }
+        });
+        let mut engine = Self {
+            child,
+            input,
+            replies,
+            move_time: DEFAULT_MOVE_TIME,
+        };
+        engine.send("isready")?;
+        engine.expect("readyok", REPLY_GRACE)?;
+        Ok(engine)
+    }
+
+    // The time the engine gets per move, unless the clock allows less
+    pub fn set_move_time(&mut self, move_time: Duration) {
+        self.move_time = move_time;
+    }
+
+    pub fn set_option(&mut self, name: &str, value: &str) -> io::Result<()> {
+        self.send(&format!("setoption name {} value {}", name, value))?;
+        // Options have no reply, so an error would otherwise go unnoticed
+        self.send("isready")?;
+        self.expect("readyok", REPLY_GRACE).map(|_| ())
+    }
+
+    pub fn best_move(&mut self, game: &Game, move_time: Duration) -> io::Result<(usize, usize)> {
+        self.send(&Position::of(game).to_string())?;
+        self.send(&format!("go movetime {}", move_time.as_millis()))?;
+        let reply = self.expect("bestmove", move_time + REPLY_GRACE)?;
+        reply
+            .strip_prefix("bestmove")
+            .and_then(|pos| parse_position(pos.trim()))
+            .ok_or_else(|| invalid_reply(&reply))
+    }
+
+    fn send(&mut self, line: &str) -> io::Result<()> {
+        writeln!(self.input, "{}", line)?;
+        self.input.flush()
+    }
+
+    // Reads the next reply starting with `keyword`, failing on an error reply
+    // or when none comes within `timeout`
+    fn expect(&mut self, keyword: &str, timeout: Duration) -> io::Result<String> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            let line = match self.replies.recv_timeout(left) {
+                Ok(line) => line,
+                Err(RecvTimeoutError::Timeout) => return Err(io::ErrorKind::TimedOut.into()),
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(io::ErrorKind::UnexpectedEof.into())
+                }
+            };
+            let line = line.trim();
+            if line.starts_with(keyword) {
+                return Ok(line.to_string());
+            }
+            if line.starts_with("error") {
+                return Err(invalid_reply(line));
+            }
+        }
+    }
+}
+
+fn invalid_reply(reply: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("engine: {}", reply))
+}
+
+impl MoveProvider for EngineProvider {
+    // An engine that crashes, answers nonsense or doesn't answer in time
+    // forfeits the game
+    fn next_action(&mut self, game: &Game, ctx: &TurnContext) -> PlayerAction {
+        let mut move_time = self.move_time;
+        if let Some(limit) = ctx.time_limit {
+            move_time = move_time.min(limit / 2);
+        }
+        if let Some(left) = ctx.time_left {
+            move_time = move_time.min(left / 10);
+        }
+        match self.best_move(game, move_time) {
+            Ok(pos) => PlayerAction::Move(pos),
+            Err(_) => PlayerAction::Resign,
+        }
+    }
+}
+
+impl Drop for EngineProvider {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        // Don't hang on an engine that ignores `quit`
+        for _ in 0..QUIT_POLLS {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            thread::sleep(QUIT_POLL_INTERVAL);
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
@@ -0,0 +1,118 @@
+use crate::game::Game;
+use crate::types::{DrawReason, Move, OpeningChoice, PlayerId, Symbol, WinReason};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::sync::mpsc::Sender;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum GameEvent {
+    PlayerJoined {
+        player: PlayerId,
+        name: String,
+        symbol: Symbol,
+    },
+    RoundStarted {
+        round: usize,
+    },
+    MovePlayed(Move),
+    MoveUndone(Move),
+    // Disappearing pieces: the player's oldest mark left the board
+    MarkRemoved {
+        player: PlayerId,
+        position: (usize, usize),
+    },
+    // Swap openings: the player answered the choice of colors
+    OpeningChosen {
+        player: PlayerId,
+        choice: OpeningChoice,
+    },
+    TurnChanged {
+        player: PlayerId,
+    },
+    // The mover can complete a line at any of `positions` on their next turn
+    ThreatCreated {
+        player: PlayerId,
+        positions: Vec<(usize, usize)>,
+    },
+    PlayerResigned {
+        player: PlayerId,
+    },
+    // Misère: the player completed a line and is out of the game
+    PlayerEliminated {
+        player: PlayerId,
+    },
+    // The player's clock ran out, see `FlagFall` for what happens next
+    FlagFell {
+        player: PlayerId,
+    },
+    GameWon {
+        winner: PlayerId,
+        reason: WinReason,
+    },
+    GameDrawn {
+        reason: DrawReason,
+    },
+}
+
+// Called after the game has been updated, so `game` already reflects the event
+pub trait GameObserver: Send {
+    fn on_event(&mut self, game: &Game, event: &GameEvent);
+}
+
+impl<F> GameObserver for F
+where
+    F: FnMut(&Game, &GameEvent) + Send,
+{
+    fn on_event(&mut self, game: &Game, event: &GameEvent) {
+        self(game, event)
+    }
+}
+
+// Writes every event as one line of JSON
+pub struct EventLogger<W: Write + Send> {
+    out: W,
+}
+
+impl<W: Write + Send> EventLogger<W> {
+    pub fn new(out: W) -> Self {
+        Self { out }
+    }
+}
+
+impl<W: Write + Send> GameObserver for EventLogger<W> {
+    fn on_event(&mut self, _game: &Game, event: &GameEvent) {
+        if let Ok(line) = serde_json::to_string(event) {
+            let _ = writeln!(self.out, "{}", line);
+            let _ = self.out.flush();
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct EventHub {
+    observers: Vec<Box<dyn GameObserver>>,
+    subscribers: Vec<Sender<GameEvent>>,
+}
+
+impl EventHub {
+    pub(crate) fn add_observer(&mut self, observer: Box<dyn GameObserver>) {
+        self.observers.push(observer);
+    }
+
+    pub(crate) fn add_subscriber(&mut self, sender: Sender<GameEvent>) {
+        self.subscribers.push(sender);
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.observers.is_empty() && self.subscribers.is_empty()
+    }
+
+    pub(crate) fn dispatch(&mut self, game: &Game, event: &GameEvent) {
+        for observer in self.observers.iter_mut() {
+            observer.on_event(game, event);
+        }
+        // Drop subscribers whose receiver has gone away
+        self.subscribers
+            .retain(|sender| sender.send(event.clone()).is_ok());
+    }
+}
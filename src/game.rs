@@ -1,33 +1,224 @@
-use crate::game_board::GameBoard;
+use crate::ai::{MarkQueue, AI};
+use crate::events::{EventHub, GameEvent, GameObserver};
+use crate::game_board::{Cell, GameBoard, LineLength};
+use crate::gomoku;
 use crate::player::Player;
-use crate::types::GameError;
+use crate::rules::{DeadPositionCheck, FlagFall, GameRules, GomokuRule, Opening};
+use crate::snapshot::GameSnapshot;
+use crate::types::{
+    Difficulty, DrawReason, ForbiddenMove, GameError, GameStatus, Move, OpeningChoice,
+    OpeningDecision, PlayerId, Symbol, WinReason,
+};
 use rand::Rng;
+use std::collections::VecDeque;
+use std::fs;
+use std::mem;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
 
 pub struct Game {
     board: GameBoard,
+    rules: GameRules,
     players: Vec<Player>,
     current_player_idx: usize,
+    // Stones the current player has placed so far in a multi-stone turn
+    stones_placed: usize,
     max_players: usize,
+    history: Vec<Move>,
+    undone: Vec<Move>,
+    // Everyone out of the game, in order: resigned players and lost flags
+    resigned: Vec<PlayerId>,
+    timed_out: Vec<PlayerId>,
+    // Misère players who completed a line
+    eliminated: Vec<PlayerId>,
+    // Choices made during a swap opening
+    opening: Vec<OpeningDecision>,
+    clocks: Vec<Duration>,
+    // Each player's cells, oldest first, for disappearing pieces
+    marks: Vec<VecDeque<(usize, usize)>>,
+    turn_started: Instant,
+    draw_offer: Option<DrawOffer>,
+    draw_agreed: bool,
+    round: usize,
+    events: EventHub,
+}
+
+// Where a swap opening stands
+#[derive(Debug, Clone, PartialEq)]
+pub enum OpeningStage {
+    // `player` places the next opening stone, in `symbol`
+    Placing {
+        player: PlayerId,
+        symbol: Symbol,
+    },
+    // `player` has to pick one of `options` before play goes on
+    Choosing {
+        player: PlayerId,
+        options: Vec<OpeningChoice>,
+    },
+    Done,
+}
+
+#[derive(Debug, Clone)]
+struct DrawOffer {
+    offered_by: PlayerId,
+    accepted_by: Vec<PlayerId>,
 }
 
 impl Game {
     pub fn new(board_size: usize, max_players: usize) -> Self {
-        Self {
-            board: GameBoard::new(board_size),
+        Self::with_rules(board_size, max_players, GameRules::default())
+    }
+
+    pub fn with_rules(board_size: usize, max_players: usize, rules: GameRules) -> Self {
+        Self::with_dimensions(board_size, board_size, max_players, rules)
+    }
+
+    // A board of `rows` by `cols` cells
+    pub fn with_dimensions(rows: usize, cols: usize, max_players: usize, rules: GameRules) -> Self {
+        let mut game = Self {
+            board: Self::new_board(rows, cols, &rules),
+            rules,
             players: Vec::new(),
             current_player_idx: 0,
+            stones_placed: 0,
             max_players,
-        }
+            history: Vec::new(),
+            undone: Vec::new(),
+            resigned: Vec::new(),
+            timed_out: Vec::new(),
+            eliminated: Vec::new(),
+            opening: Vec::new(),
+            clocks: Vec::new(),
+            marks: Vec::new(),
+            turn_started: Instant::now(),
+            draw_offer: None,
+            draw_agreed: false,
+            round: 1,
+            events: EventHub::default(),
+        };
+        game.update_line_length();
+        game
     }
 
     pub fn add_player(&mut self, player: &Player) -> Result<(), GameError> {
         if self.players.len() >= self.max_players {
             return Err(GameError::MaxPlayersReached);
         }
+        if self.player(player.id()).is_some() {
+            return Err(GameError::PlayerAlreadyJoined);
+        }
         self.players.push(player.clone());
+        self.clocks.push(self.initial_clock());
+        self.marks.push(VecDeque::new());
+        self.place_opening_stones();
+        // Nobody's clock runs while players are still joining
+        self.turn_started = Instant::now();
+        self.emit(GameEvent::PlayerJoined {
+            player: player.id(),
+            name: player.name().to_string(),
+            symbol: player.symbol(),
+        });
         Ok(())
     }
 
+    pub fn add_observer(&mut self, observer: impl GameObserver + 'static) {
+        self.events.add_observer(Box::new(observer));
+    }
+
+    // Events are also delivered through a channel, e.g. to another thread
+    pub fn subscribe(&mut self) -> Receiver<GameEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.events.add_subscriber(sender);
+        receiver
+    }
+
+    fn new_board(rows: usize, cols: usize, rules: &GameRules) -> GameBoard {
+        let mut board = GameBoard::with_dimensions(rows, cols, rules.win_length);
+        board.set_misere(rules.misere);
+        for &pos in &rules.blocked {
+            let _ = board.block(pos);
+        }
+        board
+    }
+
+    // Opening stones keep the symbol their seat had when they were placed,
+    // before the players swapped sides in the opening
+    fn place_opening_stones(&mut self) {
+        let swapped = self.opening.iter().filter(|d| d.swapped).count() % 2 == 1;
+        let seats = self.players.len();
+        for idx in 0..seats {
+            let owner = if swapped { seats - 1 - idx } else { idx };
+            let symbol = self.players[owner].symbol();
+            for &cell in self.rules.opening_stones.get(idx).into_iter().flatten() {
+                let _ = self.board.undo_move(cell);
+                let _ = self.board.apply_move(cell, symbol);
+            }
+        }
+    }
+
+    // Renju's exact five only binds the first player, who is known once
+    // the first stone is down
+    fn update_line_length(&mut self) {
+        let line_length = match self.rules.gomoku {
+            GomokuRule::Freestyle => LineLength::AtLeast,
+            GomokuRule::Standard => LineLength::Exactly,
+            GomokuRule::Renju => match self.black_symbol() {
+                Some(symbol) => LineLength::ExactlyFor(symbol),
+                None => LineLength::AtLeast,
+            },
+        };
+        self.board.set_line_length(line_length);
+    }
+
+    // Symbol of the first stone of the game, or of the player about to
+    // place it
+    fn black_symbol(&self) -> Option<Symbol> {
+        match self.history.first() {
+            Some(mv) => Some(mv.symbol),
+            None => self.current_player().map(|p| p.symbol()),
+        }
+    }
+
+    fn emit(&mut self, event: GameEvent) {
+        if self.events.is_empty() {
+            return;
+        }
+        let mut events = mem::take(&mut self.events);
+        events.dispatch(self, &event);
+        self.events = events;
+    }
+
+    // Either who plays next, or how the game ended
+    fn emit_turn_or_result(&mut self) {
+        match self.status() {
+            GameStatus::InProgress => {
+                if let Some(player) = self.current_player().map(|p| p.id()) {
+                    self.emit(GameEvent::TurnChanged { player });
+                }
+            }
+            GameStatus::Win(_, reason) => {
+                if let Some(winner) = self.winner().map(|w| w.id()) {
+                    self.emit(GameEvent::GameWon { winner, reason });
+                }
+            }
+            GameStatus::Draw(reason) => self.emit(GameEvent::GameDrawn { reason }),
+        }
+    }
+
+    pub fn round(&self) -> usize {
+        self.round
+    }
+
+    pub fn player(&self, id: PlayerId) -> Option<&Player> {
+        self.players.iter().find(|p| p.id() == id)
+    }
+
+    fn player_idx(&self, id: PlayerId) -> Option<usize> {
+        self.players.iter().position(|p| p.id() == id)
+    }
+
     pub fn current_player(&self) -> Option<&Player> {
         if self.players.is_empty() {
             None
@@ -37,26 +228,300 @@ impl Game {
     }
 
     pub fn is_over(&self) -> bool {
-        self.winner().is_some() || self.board.is_full()
+        self.status() != GameStatus::InProgress
+    }
+
+    pub fn status(&self) -> GameStatus {
+        if let Some((winner, reason)) = self.winner_with_reason() {
+            return GameStatus::Win(winner.name().to_string(), reason);
+        }
+        if self.draw_agreed {
+            return GameStatus::Draw(DrawReason::Agreement);
+        }
+        if self.board.is_full() {
+            return GameStatus::Draw(DrawReason::BoardFull);
+        }
+        if self.is_dead_position() {
+            return GameStatus::Draw(DrawReason::NoWinPossible);
+        }
+        GameStatus::InProgress
     }
 
     pub fn winner(&self) -> Option<&Player> {
-        if let Some((row, col)) = self.board.has_winning_streak(3) {
-            if let Some(symbol) = self.board.get_cell((row, col)) {
-                return self.players.iter().find(|p| p.symbol() == symbol);
+        self.winner_with_reason().map(|(winner, _)| winner)
+    }
+
+    fn winner_with_reason(&self) -> Option<(&Player, WinReason)> {
+        // In misère play a line only knocks its owner out
+        if let Some(symbol) = self.board.winner_symbol().filter(|_| !self.rules.misere) {
+            // In wild games the line can be anyone's symbol, the game ends
+            // with the move that completed it
+            if self.rules.is_wild() {
+                let mover = self.history.last()?.player;
+                return self.player(mover).map(|winner| (winner, WinReason::Streak));
+            }
+            return self
+                .players
+                .iter()
+                .find(|p| p.symbol() == symbol)
+                .map(|winner| (winner, WinReason::Streak));
+        }
+
+        // Last player standing after everyone else resigned, lost on time
+        // or completed a line in misère play
+        if let Some(last_out) = self.resigned.last() {
+            let active = self.active_players();
+            if active.len() == 1 {
+                let reason = if self.timed_out.contains(last_out) {
+                    WinReason::Timeout
+                } else if self.eliminated.contains(last_out) {
+                    WinReason::Elimination
+                } else {
+                    WinReason::Resignation
+                };
+                return Some((&self.players[active[0]], reason));
             }
         }
         None
     }
 
+    // True when none of the remaining players can complete a line anymore
+    pub fn is_dead_position(&self) -> bool {
+        let active = self.active_players();
+        // Lines open up again as marks disappear
+        if active.is_empty() || self.rules.max_marks.is_some() {
+            return false;
+        }
+        // Wild symbols belong to everyone, so only the lines matter
+        if self.rules.is_wild() && self.rules.dead_position != DeadPositionCheck::Off {
+            return self
+                .rules
+                .wild
+                .iter()
+                .all(|&symbol| !self.board.can_still_win(symbol, None));
+        }
+        match self.rules.dead_position {
+            DeadPositionCheck::Off => false,
+            DeadPositionCheck::OpenLines => active
+                .iter()
+                .all(|&idx| !self.board.can_still_win(self.players[idx].symbol(), None)),
+            DeadPositionCheck::MovesLeft => active.iter().all(|&idx| {
+                let moves = self.moves_left(self.players[idx].id());
+                !self
+                    .board
+                    .can_still_win(self.players[idx].symbol(), Some(moves))
+            }),
+        }
+    }
+
+    // Moves the player still gets if the game runs until the board is full
+    pub fn moves_left(&self, player: PlayerId) -> usize {
+        let player_idx = match self.player_idx(player) {
+            Some(idx) => idx,
+            None => return 0,
+        };
+        let active = self.active_players();
+        let current = match active
+            .iter()
+            .position(|&idx| idx == self.current_player_idx)
+        {
+            Some(current) => current,
+            None => return 0,
+        };
+        if !active.contains(&player_idx) {
+            return 0;
+        }
+        // Turns go round from the current one, which may have stones left
+        let per_turn = self.rules.stones_per_turn.unwrap_or(1).max(1);
+        let mut stones = self.stones_this_turn() - self.stones_placed;
+        let mut empty = self.board.empty_positions().len();
+        let mut moves = 0;
+        for seat in (current..).map(|seat| seat % active.len()) {
+            if empty == 0 {
+                break;
+            }
+            let placed = stones.min(empty);
+            if active[seat] == player_idx {
+                moves += placed;
+            }
+            empty -= placed;
+            stones = per_turn;
+        }
+        moves
+    }
+
+    pub fn is_draw_agreed(&self) -> bool {
+        self.draw_agreed
+    }
+
+    pub fn has_resigned(&self, player: PlayerId) -> bool {
+        self.resigned.contains(&player)
+    }
+
+    pub fn has_timed_out(&self, player: PlayerId) -> bool {
+        self.timed_out.contains(&player)
+    }
+
+    pub fn is_eliminated(&self, player: PlayerId) -> bool {
+        self.eliminated.contains(&player)
+    }
+
+    fn initial_clock(&self) -> Duration {
+        self.rules
+            .time_control
+            .as_ref()
+            .map_or(Duration::ZERO, |tc| tc.initial)
+    }
+
+    // Remaining time, counting down for the player to move. None without a
+    // time control
+    pub fn time_left(&self, player: PlayerId) -> Option<Duration> {
+        let time_control = self.rules.time_control.as_ref()?;
+        let idx = self.player_idx(player)?;
+        if idx == self.current_player_idx && !self.is_over() {
+            let charged = self
+                .turn_started
+                .elapsed()
+                .saturating_sub(time_control.delay);
+            Some(self.clocks[idx].saturating_sub(charged))
+        } else {
+            Some(self.clocks[idx])
+        }
+    }
+
+    // When the player to move runs out of time or reaches the per-move
+    // limit. None without a time control
+    pub fn flag_deadline(&self) -> Option<Instant> {
+        let time_control = self.rules.time_control.as_ref()?;
+        if self.players.is_empty() || self.is_over() {
+            return None;
+        }
+        let clock = time_control
+            .delay
+            .saturating_add(self.clocks[self.current_player_idx]);
+        let allowed = time_control
+            .per_move
+            .map_or(clock, |limit| clock.min(limit));
+        // Too far off to ever fall
+        self.turn_started.checked_add(allowed)
+    }
+
+    // Applies the flag fall rule once the player to move has used up their
+    // time or the per-move limit. Returns whether the flag fell
+    pub fn check_clock(&mut self) -> bool {
+        let time_control = match &self.rules.time_control {
+            Some(time_control) if !self.players.is_empty() && !self.is_over() => {
+                time_control.clone()
+            }
+            _ => return false,
+        };
+        let idx = self.current_player_idx;
+        let elapsed = self.turn_started.elapsed();
+        let charged = elapsed.saturating_sub(time_control.delay);
+        let over_move_limit = time_control.per_move.is_some_and(|limit| elapsed > limit);
+        if charged <= self.clocks[idx] && !over_move_limit {
+            return false;
+        }
+
+        let player = self.players[idx].id();
+        self.clocks[idx] = self.clocks[idx].saturating_sub(charged);
+        self.draw_offer = None;
+        match time_control.on_flag {
+            FlagFall::Lose => {
+                self.timed_out.push(player);
+                self.resigned.push(player);
+            }
+            FlagFall::SkipTurn => {
+                self.clocks[idx] = self.clocks[idx].saturating_add(time_control.increment)
+            }
+        }
+        self.advance_turn();
+        self.emit(GameEvent::FlagFell { player });
+        self.emit_turn_or_result();
+        true
+    }
+
+    fn charge_clock(&mut self) {
+        if let Some(time_control) = &self.rules.time_control {
+            let idx = self.current_player_idx;
+            let charged = self
+                .turn_started
+                .elapsed()
+                .saturating_sub(time_control.delay);
+            self.clocks[idx] = self.clocks[idx]
+                .saturating_sub(charged)
+                .saturating_add(time_control.increment);
+        }
+    }
+
+    // Indices of players who have not resigned, in turn order
+    fn active_players(&self) -> Vec<usize> {
+        (0..self.players.len())
+            .filter(|&idx| !self.resigned.contains(&self.players[idx].id()))
+            .collect()
+    }
+
+    fn advance_turn(&mut self) {
+        self.stones_placed = 0;
+        if self.active_players().is_empty() {
+            return;
+        }
+        loop {
+            self.current_player_idx = (self.current_player_idx + 1) % self.players.len();
+            if !self
+                .resigned
+                .contains(&self.players[self.current_player_idx].id())
+            {
+                break;
+            }
+        }
+        self.turn_started = Instant::now();
+    }
+
+    // Stones the current turn places: one for the first turn of the game,
+    // then the rules' stones per turn
+    fn stones_this_turn(&self) -> usize {
+        if self.history.len() == self.stones_placed {
+            1
+        } else {
+            self.rules.stones_per_turn.unwrap_or(1).max(1)
+        }
+    }
+
+    // Stones the current player has placed this turn and how many the turn has
+    pub fn turn_progress(&self) -> (usize, usize) {
+        (self.stones_placed, self.stones_this_turn())
+    }
+
+    pub fn winning_line(&self) -> Option<Vec<(usize, usize)>> {
+        self.board.winning_line(self.rules.win_length)
+    }
+
     pub fn randomize_turn(&mut self) {
         if !self.players.is_empty() {
             self.current_player_idx = rand::thread_rng().gen_range(0..self.players.len());
+            self.stones_placed = 0;
+            self.turn_started = Instant::now();
+            self.emit_turn_or_result();
         }
     }
 
     pub fn prepare_next_round(&mut self) {
-        self.board = GameBoard::new(self.board.size());
+        self.board = Self::new_board(self.board.rows(), self.board.cols(), &self.rules);
+        self.history.clear();
+        self.undone.clear();
+        self.resigned.clear();
+        self.timed_out.clear();
+        self.eliminated.clear();
+        self.opening.clear();
+        self.clocks = vec![self.initial_clock(); self.players.len()];
+        self.marks = vec![VecDeque::new(); self.players.len()];
+        self.place_opening_stones();
+        self.draw_offer = None;
+        self.draw_agreed = false;
+        self.round += 1;
+        self.update_line_length();
+        self.emit(GameEvent::RoundStarted { round: self.round });
         self.randomize_turn();
     }
 
@@ -64,25 +529,775 @@ impl Game {
         &self.board
     }
 
+    // Places the current player's symbol. In wild games that is their own
+    // symbol if it is one of the shared ones, the first shared one otherwise
     pub fn make_move(&mut self, pos: (usize, usize)) -> Result<(), GameError> {
+        self.check_opening()?;
+        let symbol = self.default_symbol().ok_or(GameError::InvalidMove)?;
+        self.make_move_with(pos, symbol)
+    }
+
+    // Places a chosen symbol, which has to be one of the wild symbols or the
+    // player's own in a regular game
+    pub fn make_move_with(&mut self, pos: (usize, usize), symbol: Symbol) -> Result<(), GameError> {
+        if !self.placeable_symbols().contains(&symbol) {
+            return Err(GameError::SymbolNotAllowed);
+        }
+        self.play(pos, symbol)?;
+        self.undone.clear();
+        Ok(())
+    }
+
+    // Like make_move, but only accepted from the player whose turn it is
+    pub fn make_move_as(&mut self, player: PlayerId, pos: (usize, usize)) -> Result<(), GameError> {
+        self.check_turn(player)?;
+        self.make_move(pos)
+    }
+
+    pub fn make_move_with_as(
+        &mut self,
+        player: PlayerId,
+        pos: (usize, usize),
+        symbol: Symbol,
+    ) -> Result<(), GameError> {
+        self.check_turn(player)?;
+        self.make_move_with(pos, symbol)
+    }
+
+    fn check_turn(&self, player: PlayerId) -> Result<(), GameError> {
+        if self.player(player).is_none() {
+            return Err(GameError::UnknownPlayer);
+        }
+        if self.is_over() {
+            return Err(GameError::GameOver);
+        }
+        if self.current_player().map(|p| p.id()) != Some(player) {
+            return Err(GameError::OutOfTurn);
+        }
+        Ok(())
+    }
+
+    // Symbols the current player may place
+    pub fn placeable_symbols(&self) -> Vec<Symbol> {
+        match self.opening_stage() {
+            OpeningStage::Placing { symbol, .. } => return vec![symbol],
+            OpeningStage::Choosing { .. } => return Vec::new(),
+            OpeningStage::Done => {}
+        }
+        if self.rules.is_wild() {
+            self.rules.wild.clone()
+        } else {
+            self.current_player()
+                .map(|p| p.symbol())
+                .into_iter()
+                .collect()
+        }
+    }
+
+    fn default_symbol(&self) -> Option<Symbol> {
+        let own = self.current_player()?.symbol();
+        let symbols = self.placeable_symbols();
+        if symbols.contains(&own) {
+            Some(own)
+        } else {
+            symbols.first().copied()
+        }
+    }
+
+    fn check_opening(&self) -> Result<(), GameError> {
+        match self.opening_stage() {
+            OpeningStage::Choosing { .. } if !self.is_over() => Err(GameError::ChoicePending),
+            _ => Ok(()),
+        }
+    }
+
+    // Renju: why the first player may not play at `pos`, when the current
+    // player's stone there would be one of theirs
+    pub fn forbidden_move(&self, pos: (usize, usize)) -> Option<ForbiddenMove> {
+        let symbol = self.default_symbol()?;
+        self.forbidden_for(pos, symbol)
+    }
+
+    fn forbidden_for(&self, pos: (usize, usize), symbol: Symbol) -> Option<ForbiddenMove> {
+        if self.rules.gomoku != GomokuRule::Renju || self.black_symbol() != Some(symbol) {
+            return None;
+        }
+        gomoku::forbidden_move(&self.board, pos, symbol)
+    }
+
+    fn opener(&self) -> Option<&Player> {
+        match self.history.first() {
+            Some(mv) => self.player(mv.player),
+            None => self.current_player(),
+        }
+    }
+
+    // Symbols of the first and second stone of a swap opening
+    pub fn opening_colors(&self) -> Option<(Symbol, Symbol)> {
+        let black = self.black_symbol()?;
+        let white = match self.history.get(1) {
+            Some(mv) => mv.symbol,
+            None => self.players.iter().find(|p| p.symbol() != black)?.symbol(),
+        };
+        Some((black, white))
+    }
+
+    // Swap openings: the opener places black, white and black, then the
+    // other player chooses. In swap2 they may place a white and a black
+    // stone instead and the opener chooses
+    pub fn opening_stage(&self) -> OpeningStage {
+        if self.rules.opening == Opening::Free || self.players.len() != 2 {
+            return OpeningStage::Done;
+        }
+        let (opener, (black, white)) = match (self.opener(), self.opening_colors()) {
+            (Some(opener), Some(colors)) => (opener.id(), colors),
+            _ => return OpeningStage::Done,
+        };
+        let second = match self.players.iter().find(|p| p.id() != opener) {
+            Some(player) => player.id(),
+            None => return OpeningStage::Done,
+        };
+        let placed = self.history.len();
+        let color = |stone: usize| {
+            if stone.is_multiple_of(2) {
+                black
+            } else {
+                white
+            }
+        };
+        let choices: Vec<OpeningChoice> = self.opening.iter().map(|d| d.choice).collect();
+        match (self.rules.opening, &choices[..]) {
+            (Opening::Pie, []) if placed < 1 => OpeningStage::Placing {
+                player: opener,
+                symbol: black,
+            },
+            (Opening::Pie, []) => OpeningStage::Choosing {
+                player: second,
+                options: vec![OpeningChoice::Swap, OpeningChoice::Keep],
+            },
+            (_, []) if placed < 3 => OpeningStage::Placing {
+                player: opener,
+                symbol: color(placed),
+            },
+            (Opening::Swap, []) => OpeningStage::Choosing {
+                player: second,
+                options: vec![OpeningChoice::Black, OpeningChoice::White],
+            },
+            (Opening::Swap2, []) => OpeningStage::Choosing {
+                player: second,
+                options: vec![
+                    OpeningChoice::Black,
+                    OpeningChoice::White,
+                    OpeningChoice::PlaceTwo,
+                ],
+            },
+            (Opening::Swap2, [OpeningChoice::PlaceTwo]) if placed < 5 => OpeningStage::Placing {
+                player: second,
+                symbol: color(placed),
+            },
+            (Opening::Swap2, [OpeningChoice::PlaceTwo]) => OpeningStage::Choosing {
+                player: opener,
+                options: vec![OpeningChoice::Black, OpeningChoice::White],
+            },
+            _ => OpeningStage::Done,
+        }
+    }
+
+    // Answers the choice of a swap opening. Taking the other player's color
+    // swaps the two players' symbols
+    pub fn choose_opening(
+        &mut self,
+        player: PlayerId,
+        choice: OpeningChoice,
+    ) -> Result<(), GameError> {
+        if self.player(player).is_none() {
+            return Err(GameError::UnknownPlayer);
+        }
+        if self.is_over() {
+            return Err(GameError::GameOver);
+        }
+        match self.opening_stage() {
+            OpeningStage::Choosing {
+                player: chooser, ..
+            } if chooser != player => return Err(GameError::OutOfTurn),
+            OpeningStage::Choosing { options, .. } if options.contains(&choice) => {}
+            _ => return Err(GameError::InvalidMove),
+        }
+        let (black, white) = self.opening_colors().ok_or(GameError::InvalidMove)?;
+        let own = self.player(player).map(|p| p.symbol());
+        let swapped = match choice {
+            OpeningChoice::Black => own != Some(black),
+            OpeningChoice::White => own != Some(white),
+            OpeningChoice::Swap => own != Some(black),
+            OpeningChoice::PlaceTwo | OpeningChoice::Keep => false,
+        };
+        if swapped {
+            self.swap_symbols();
+        }
+        self.opening.push(OpeningDecision {
+            player,
+            choice,
+            stones: self.history.len(),
+            swapped,
+        });
+        self.sync_opening_turn();
+        self.emit(GameEvent::OpeningChosen { player, choice });
+        self.emit_turn_or_result();
+        Ok(())
+    }
+
+    fn swap_symbols(&mut self) {
+        if let [first, second] = &mut self.players[..] {
+            let symbol = first.symbol();
+            first.set_symbol(second.symbol());
+            second.set_symbol(symbol);
+        }
+    }
+
+    // Hands the turn to whoever acts next in the opening, or once it is
+    // over to the color whose stone is next
+    fn sync_opening_turn(&mut self) {
+        let player = match self.opening_stage() {
+            OpeningStage::Placing { player, .. } | OpeningStage::Choosing { player, .. } => {
+                Some(player)
+            }
+            OpeningStage::Done => self.opening_colors().and_then(|(black, white)| {
+                let color = if self.history.len().is_multiple_of(2) {
+                    black
+                } else {
+                    white
+                };
+                self.players
+                    .iter()
+                    .find(|p| p.symbol() == color)
+                    .map(|p| p.id())
+            }),
+        };
+        if let Some(idx) = player.and_then(|id| self.player_idx(id)) {
+            self.current_player_idx = idx;
+            self.stones_placed = 0;
+            self.turn_started = Instant::now();
+        }
+    }
+
+    fn play(&mut self, pos: (usize, usize), symbol: Symbol) -> Result<(), GameError> {
+        if self.is_over() {
+            return Err(GameError::GameOver);
+        }
+        self.check_opening()?;
+        if let Some(reason) = self.forbidden_for(pos, symbol) {
+            return Err(GameError::ForbiddenMove(reason));
+        }
+        let in_opening = self.opening_stage() != OpeningStage::Done;
+        if self.check_clock() {
+            return Err(GameError::OutOfTime);
+        }
         let current_player = self.current_player().ok_or(GameError::InvalidMove)?;
-        self.board
-            .apply_move(pos, current_player.symbol())?;
-        self.current_player_idx = (self.current_player_idx + 1) % self.players.len();
+        let player = current_player.id();
+        let idx = self.current_player_idx;
+        self.update_line_length();
+        // Only threats the move adds are news
+        let old_threats = if self.events.is_empty() {
+            Vec::new()
+        } else {
+            self.board.clone().get_winning_positions(symbol)
+        };
+        let removed =
+            self.board
+                .place_mark(&mut self.marks[idx], self.rules.max_marks, pos, symbol)?;
+        let eliminated = self.rules.misere && self.board.in_full_line(pos);
+        let turn_over = self.stones_placed + 1 >= self.stones_this_turn() || eliminated;
+        let mv = Move {
+            position: pos,
+            symbol,
+            player,
+        };
+        self.history.push(mv);
+        self.draw_offer = None;
+        if eliminated {
+            self.eliminated.push(player);
+            self.resigned.push(player);
+        }
+        // The turn, and the clock, only move on after its last stone
+        if in_opening {
+            self.charge_clock();
+            self.sync_opening_turn();
+        } else if turn_over {
+            self.charge_clock();
+            self.advance_turn();
+        } else {
+            self.stones_placed += 1;
+        }
+
+        if !self.events.is_empty() {
+            self.emit(GameEvent::MovePlayed(mv));
+            if let Some(position) = removed {
+                self.emit(GameEvent::MarkRemoved { player, position });
+            }
+            if eliminated {
+                self.emit(GameEvent::PlayerEliminated { player });
+            }
+            let threats: Vec<(usize, usize)> = self
+                .board
+                .clone()
+                .get_winning_positions(symbol)
+                .into_iter()
+                .filter(|pos| !old_threats.contains(pos))
+                .collect();
+            let variant = self.rules.misere || self.rules.is_wild();
+            if !threats.is_empty() && !self.is_over() && !variant {
+                self.emit(GameEvent::ThreatCreated {
+                    player,
+                    positions: threats,
+                });
+            }
+            self.emit_turn_or_result();
+        }
+        Ok(())
+    }
+
+    pub fn undo(&mut self) -> Result<Move, GameError> {
+        let last = self.history.pop().ok_or(GameError::NothingToUndo)?;
+        // A choice made right after this stone goes with it
+        if let Some(decision) = self.opening.last().copied() {
+            if decision.stones == self.history.len() + 1 {
+                self.opening.pop();
+                if decision.swapped {
+                    self.swap_symbols();
+                }
+            }
+        }
+        // Eliminated players don't move again, so their last move is the
+        // one that completed the line. Taking it back brings them back
+        if self.eliminated.last() == Some(&last.player) {
+            self.eliminated.pop();
+            self.resigned.retain(|&id| id != last.player);
+        }
+        self.board.undo_move(last.position)?;
+        if let Some(idx) = self.player_idx(last.player) {
+            self.marks[idx].pop_back();
+            // The mark that disappeared with the move comes back
+            if let Some(max) = self.rules.max_marks {
+                let earlier: Vec<Move> = self
+                    .history
+                    .iter()
+                    .filter(|mv| mv.player == last.player)
+                    .copied()
+                    .collect();
+                if earlier.len() >= max.max(1) {
+                    let restored = earlier[earlier.len() - max.max(1)];
+                    self.board.apply_move(restored.position, restored.symbol)?;
+                    self.marks[idx].push_front(restored.position);
+                }
+            }
+            self.current_player_idx = idx;
+            self.turn_started = Instant::now();
+        }
+        // Back to the stone that was taken back, after the player's earlier
+        // stones of the same turn
+        self.stones_placed = self
+            .history
+            .iter()
+            .rev()
+            .take_while(|mv| mv.player == last.player)
+            .count()
+            % self.rules.stones_per_turn.unwrap_or(1).max(1);
+        self.draw_offer = None;
+        self.update_line_length();
+        self.undone.push(last);
+        self.emit(GameEvent::MoveUndone(last));
+        self.emit_turn_or_result();
+        Ok(last)
+    }
+
+    pub fn redo(&mut self) -> Result<Move, GameError> {
+        let next = *self.undone.last().ok_or(GameError::NothingToRedo)?;
+        if self.current_player().map(|p| p.id()) != Some(next.player) {
+            return Err(GameError::OutOfTurn);
+        }
+        self.play(next.position, next.symbol)?;
+        self.undone.pop();
+        Ok(next)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.history.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+
+    // Plays for AI players until a human is to move or the game ends.
+    // Returns how many moves were made
+    pub fn play_ai_turns(&mut self) -> Result<usize, GameError> {
+        let mut count = 0;
+        while !self.is_over() {
+            let (ai, player) = match self.current_player() {
+                Some(player) => match &player.ai {
+                    Some(ai) => (ai.clone(), player.id()),
+                    None => break,
+                },
+                None => break,
+            };
+            if let Some(choice) = self.ai_opening_choice(&ai) {
+                self.choose_opening(player, choice)?;
+                count += 1;
+                continue;
+            }
+            // Stones planned together are played together
+            if let Some(stones) = self.ai_stones(&ai) {
+                for position in stones {
+                    if self.is_over() {
+                        break;
+                    }
+                    self.make_move(position)?;
+                    count += 1;
+                }
+                continue;
+            }
+            let (position, symbol) = self
+                .ai_choice(&ai, self.time_left(player))
+                .ok_or(GameError::InvalidMove)?;
+            self.make_move_with(position, symbol)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    // Suggested move for the current player, as the hard AI would play it
+    pub fn hint(&self) -> Option<(usize, usize)> {
+        self.hint_with_symbol().map(|(pos, _)| pos)
+    }
+
+    // The hint together with the symbol to place, which matters in wild games
+    pub fn hint_with_symbol(&self) -> Option<((usize, usize), Symbol)> {
+        let current_player = self.current_player()?;
+        if self.is_over() {
+            return None;
+        }
+        let ai = AI::new(Difficulty::Hard, current_player.symbol());
+        self.ai_choice(&ai, None)
+    }
+
+    // The cell and symbol `ai` picks for the current player under this
+    // game's rules
+    pub(crate) fn ai_choice(
+        &self,
+        ai: &AI,
+        time_left: Option<Duration>,
+    ) -> Option<((usize, usize), Symbol)> {
+        if self.rules.is_wild() {
+            return Some(ai.decide_wild_move(&self.board, &self.rules.wild));
+        }
+        let symbol = self.default_symbol()?;
+        // Opening stones may be the other player's color
+        let opening_ai;
+        let ai = match self.opening_stage() {
+            OpeningStage::Placing { symbol, .. } => {
+                opening_ai = AI::new(ai.difficulty(), symbol);
+                &opening_ai
+            }
+            _ => ai,
+        };
+        let mut position = match (self.rules.max_marks, self.rules.stones_per_turn) {
+            // Pie rule: a first move that isn't worth taking over
+            _ if self.rules.opening == Opening::Pie
+                && self.opening_stage() != OpeningStage::Done =>
+            {
+                let (_, white) = self.opening_colors()?;
+                ai.decide_pie_move(&self.board, white)
+            }
+            (Some(max), _) => ai.decide_vanishing_move(&self.board, &self.mark_queues(), max),
+            (None, Some(stones)) if stones > 1 => {
+                let (placed, total) = self.turn_progress();
+                *ai.decide_stones(&self.board, total - placed, stones)
+                    .first()?
+            }
+            _ => ai.decide_move_within(&self.board, time_left),
+        };
+        // Renju: the nearest cell the first player may take instead
+        if self.forbidden_for(position, symbol).is_some() {
+            position = self
+                .board
+                .empty_positions()
+                .into_iter()
+                .filter(|&pos| self.forbidden_for(pos, symbol).is_none())
+                .min_by_key(|&(row, col)| row.abs_diff(position.0) + col.abs_diff(position.1))?;
+        }
+        Some((position, symbol))
+    }
+
+    // Every stone `ai` places for the rest of a multi-stone turn. None for
+    // turns of one stone, and for rules where the AI plays otherwise
+    pub(crate) fn ai_stones(&self, ai: &AI) -> Option<Vec<(usize, usize)>> {
+        let stones = self.rules.stones_per_turn.filter(|&stones| stones > 1)?;
+        if self.rules.is_wild()
+            || self.rules.max_marks.is_some()
+            || self.opening_stage() != OpeningStage::Done
+        {
+            return None;
+        }
+        let (placed, total) = self.turn_progress();
+        Some(ai.decide_stones(&self.board, total - placed, stones)).filter(|s| !s.is_empty())
+    }
+
+    // What `ai` answers when the opening waits for the current player's choice
+    pub(crate) fn ai_opening_choice(&self, ai: &AI) -> Option<OpeningChoice> {
+        let options = match self.opening_stage() {
+            OpeningStage::Choosing { options, .. } => options,
+            _ => return None,
+        };
+        let (black, white) = self.opening_colors()?;
+        Some(ai.choose_opening(&self.board, black, white, &options))
+    }
+
+    // Marks of the players still in the game, in turn order starting with
+    // the player to move
+    pub fn mark_queues(&self) -> Vec<MarkQueue> {
+        let active = self.active_players();
+        let start = active
+            .iter()
+            .position(|&idx| idx == self.current_player_idx)
+            .unwrap_or(0);
+        (0..active.len())
+            .map(|i| active[(start + i) % active.len()])
+            .map(|idx| (self.players[idx].symbol(), self.marks[idx].clone()))
+            .collect()
+    }
+
+    pub fn marks(&self, player: PlayerId) -> Option<&VecDeque<(usize, usize)>> {
+        self.player_idx(player).map(|idx| &self.marks[idx])
+    }
+
+    // The current player's mark that disappears with their next move
+    pub fn next_to_vanish(&self) -> Option<(usize, usize)> {
+        let max = self.rules.max_marks?;
+        let marks = self.marks.get(self.current_player_idx)?;
+        if self.is_over() || marks.len() < max.max(1) {
+            return None;
+        }
+        marks.front().copied()
+    }
+
+    pub fn resign(&mut self) -> Result<(), GameError> {
+        let current = self.current_player().ok_or(GameError::InvalidMove)?.id();
+        self.resign_player(current)
+    }
+
+    // A resigned player's cells stay on the board, but their turns are skipped
+    pub fn resign_player(&mut self, player: PlayerId) -> Result<(), GameError> {
+        let player_idx = self.player_idx(player).ok_or(GameError::UnknownPlayer)?;
+        if self.is_over() {
+            return Err(GameError::GameOver);
+        }
+        if self.resigned.contains(&player) {
+            return Err(GameError::InvalidMove);
+        }
+        self.resigned.push(player);
+        self.draw_offer = None;
+        if player_idx == self.current_player_idx {
+            self.advance_turn();
+        }
+        self.emit(GameEvent::PlayerResigned { player });
+        self.emit_turn_or_result();
+        Ok(())
+    }
+
+    // The current player offers a draw; it is agreed once every other
+    // remaining player accepts, and withdrawn when anyone declines or moves
+    pub fn offer_draw(&mut self) -> Result<(), GameError> {
+        let current = self.current_player().ok_or(GameError::InvalidMove)?.id();
+        self.offer_draw_as(current)
+    }
+
+    pub fn offer_draw_as(&mut self, player: PlayerId) -> Result<(), GameError> {
+        if self.player(player).is_none() {
+            return Err(GameError::UnknownPlayer);
+        }
+        if self.is_over() {
+            return Err(GameError::GameOver);
+        }
+        if self.resigned.contains(&player) {
+            return Err(GameError::InvalidMove);
+        }
+        self.draw_offer = Some(DrawOffer {
+            offered_by: player,
+            accepted_by: Vec::new(),
+        });
+        Ok(())
+    }
+
+    pub fn draw_offered_by(&self) -> Option<&Player> {
+        self.draw_offer
+            .as_ref()
+            .and_then(|offer| self.player(offer.offered_by))
+    }
+
+    // Players who still need to answer the pending draw offer
+    pub fn draw_offer_pending_for(&self) -> Vec<PlayerId> {
+        match &self.draw_offer {
+            Some(offer) => self
+                .active_players()
+                .into_iter()
+                .map(|idx| self.players[idx].id())
+                .filter(|id| *id != offer.offered_by && !offer.accepted_by.contains(id))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn accept_draw(&mut self, player: PlayerId) -> Result<(), GameError> {
+        if !self.draw_offer_pending_for().contains(&player) {
+            return Err(GameError::NoDrawOffer);
+        }
+        if let Some(offer) = self.draw_offer.as_mut() {
+            offer.accepted_by.push(player);
+        }
+        if self.draw_offer_pending_for().is_empty() {
+            self.draw_offer = None;
+            self.draw_agreed = true;
+            self.emit_turn_or_result();
+        }
         Ok(())
     }
 
+    pub fn decline_draw(&mut self, player: PlayerId) -> Result<(), GameError> {
+        if !self.draw_offer_pending_for().contains(&player) {
+            return Err(GameError::NoDrawOffer);
+        }
+        self.draw_offer = None;
+        Ok(())
+    }
+
+    pub fn snapshot(&self) -> GameSnapshot {
+        GameSnapshot::from_game(self)
+    }
+
+    pub fn from_snapshot(snapshot: &GameSnapshot) -> Result<Self, GameError> {
+        if let Some(time_control) = &snapshot.rules.time_control {
+            if !time_control.is_playable() {
+                return Err(GameError::LoadFailed("clock without time".to_string()));
+            }
+        }
+        let mut game = Game::with_dimensions(
+            snapshot.board_size,
+            snapshot.board_cols.unwrap_or(snapshot.board_size),
+            snapshot.max_players,
+            snapshot.rules.clone(),
+        );
+        for player in &snapshot.players {
+            game.add_player(&player.to_player())?;
+        }
+        for mv in &snapshot.moves {
+            let idx = game.player_idx(mv.player).ok_or(GameError::UnknownPlayer)?;
+            game.board.place_mark(
+                &mut game.marks[idx],
+                game.rules.max_marks,
+                mv.position,
+                mv.symbol,
+            )?;
+            game.history.push(*mv);
+        }
+        game.undone = snapshot.undone.clone();
+        game.resigned = snapshot.resigned.clone();
+        // Lost flags put players out of the game, like resigning
+        if snapshot
+            .timed_out
+            .iter()
+            .any(|id| game.player(*id).is_none() || !game.resigned.contains(id))
+        {
+            return Err(GameError::LoadFailed(
+                "invalid timed out players".to_string(),
+            ));
+        }
+        game.timed_out = snapshot.timed_out.clone();
+        game.eliminated = snapshot.eliminated.clone();
+        game.opening = snapshot.opening.clone();
+        game.place_opening_stones();
+        game.update_line_length();
+        if snapshot.clocks.len() == game.players.len() {
+            game.clocks = snapshot.clocks.clone();
+        }
+        if snapshot.current_player >= game.players.len().max(1) {
+            return Err(GameError::LoadFailed("invalid current player".to_string()));
+        }
+        game.current_player_idx = snapshot.current_player;
+        game.round = snapshot.round.max(1);
+        game.stones_placed = snapshot.stones_placed;
+        // A turn with all its stones down would never end
+        if game.stones_placed >= game.stones_this_turn() {
+            return Err(GameError::LoadFailed(
+                "invalid stones this turn".to_string(),
+            ));
+        }
+        Ok(game)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), GameError> {
+        let json = serde_json::to_string_pretty(&self.snapshot())
+            .map_err(|e| GameError::SaveFailed(e.to_string()))?;
+        fs::write(path, json).map_err(|e| GameError::SaveFailed(e.to_string()))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, GameError> {
+        let json = fs::read_to_string(path).map_err(|e| GameError::LoadFailed(e.to_string()))?;
+        let snapshot: GameSnapshot =
+            serde_json::from_str(&json).map_err(|e| GameError::LoadFailed(e.to_string()))?;
+        Self::from_snapshot(&snapshot)
+    }
+
     pub fn is_move_valid(&self, pos: (usize, usize)) -> bool {
         if let Some(_current_player) = self.current_player() {
-            self.board.get_cell(pos).is_none()
-                && pos.0 < self.board.size()
-                && pos.1 < self.board.size()
+            self.board.cell(pos) == Cell::Empty
+                && self.check_opening().is_ok()
+                && self.forbidden_move(pos).is_none()
         } else {
             false
         }
     }
 
+    pub fn last_move(&self) -> Option<Move> {
+        self.history.last().copied()
+    }
+
+    pub fn history(&self) -> &[Move] {
+        &self.history
+    }
+
     pub fn players(&self) -> &Vec<Player> {
         &self.players
     }
+
+    pub fn rules(&self) -> &GameRules {
+        &self.rules
+    }
+
+    pub fn max_players(&self) -> usize {
+        self.max_players
+    }
+
+    pub fn current_player_idx(&self) -> usize {
+        self.current_player_idx
+    }
+
+    pub(crate) fn timed_out(&self) -> &[PlayerId] {
+        &self.timed_out
+    }
+
+    pub(crate) fn undone(&self) -> &[Move] {
+        &self.undone
+    }
+
+    pub(crate) fn opening_decisions(&self) -> &[OpeningDecision] {
+        &self.opening
+    }
+
+    pub(crate) fn eliminated(&self) -> &[PlayerId] {
+        &self.eliminated
+    }
+
+    pub(crate) fn resigned(&self) -> &[PlayerId] {
+        &self.resigned
+    }
 }
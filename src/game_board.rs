@@ -1,22 +1,240 @@
 use crate::types::{BoardError, Symbol};
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+// Rows, columns and both diagonals, one direction of each
+pub const DIRECTIONS: [(isize, isize); 4] = [(0, 1), (1, 0), (1, 1), (1, -1)];
+
+// How long a run of one symbol has to be to win
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum LineLength {
+    // The win length or longer
+    #[default]
+    AtLeast,
+    // Exactly the win length, overlines don't count
+    Exactly,
+    // Exactly for this symbol, at least for everyone else
+    ExactlyFor(Symbol),
+}
+
+// What a cell holds. Blocked cells take no marks and break every line
+// through them
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Cell {
+    #[default]
+    Empty,
+    Blocked,
+    Mark(Symbol),
+}
+
+impl Cell {
+    pub fn symbol(self) -> Option<Symbol> {
+        match self {
+            Cell::Mark(symbol) => Some(symbol),
+            _ => None,
+        }
+    }
+}
+
+// The winning lines of a board and, for every cell, the indices of the
+// lines through it. Only blocking cells changes them, so clones share one
+#[derive(Debug, Default)]
+struct WinLines {
+    lines: Vec<Vec<(usize, usize)>>,
+    through: Vec<Vec<Vec<usize>>>,
+}
 
 #[derive(Debug, Clone)]
 pub struct GameBoard {
-    cells: Vec<Vec<Option<Symbol>>>,
-    size: usize,
+    cells: Vec<Vec<Cell>>,
+    rows: usize,
+    cols: usize,
+    win_length: usize,
+    misere: bool,
+    line_length: LineLength,
+    win_lines: Arc<WinLines>,
 }
 
 impl GameBoard {
     pub fn new(size: usize) -> Self {
-        let cells = vec![vec![None; size]; size];
-        Self { cells, size }
+        Self::with_win_length(size, 3)
+    }
+
+    pub fn with_win_length(size: usize, win_length: usize) -> Self {
+        Self::with_dimensions(size, size, win_length)
+    }
+
+    pub fn with_dimensions(rows: usize, cols: usize, win_length: usize) -> Self {
+        let cells = vec![vec![Cell::Empty; cols]; rows];
+        let mut board = Self {
+            cells,
+            rows,
+            cols,
+            win_length,
+            misere: false,
+            line_length: LineLength::default(),
+            win_lines: Arc::default(),
+        };
+        board.index_win_lines();
+        board
+    }
+
+    fn index_win_lines(&mut self) {
+        let lines = self.lines(self.win_length);
+        let mut through = vec![vec![Vec::new(); self.cols]; self.rows];
+        for (idx, line) in lines.iter().enumerate() {
+            for &(row, col) in line {
+                through[row][col].push(idx);
+            }
+        }
+        self.win_lines = Arc::new(WinLines { lines, through });
+    }
+
+    pub fn win_length(&self) -> usize {
+        self.win_length
+    }
+
+    // Makes an empty cell unplayable
+    pub fn block(&mut self, pos: (usize, usize)) -> Result<(), BoardError> {
+        self.check_free(pos)?;
+        self.cells[pos.0][pos.1] = Cell::Blocked;
+        self.index_win_lines();
+        Ok(())
+    }
+
+    pub fn is_blocked(&self, pos: (usize, usize)) -> bool {
+        self.cell(pos) == Cell::Blocked
+    }
+
+    pub fn blocked_positions(&self) -> Vec<(usize, usize)> {
+        self.positions(|cell| cell == Cell::Blocked)
+    }
+
+    // In misère play a completed line loses
+    pub fn set_misere(&mut self, misere: bool) {
+        self.misere = misere;
+    }
+
+    pub fn is_misere(&self) -> bool {
+        self.misere
+    }
+
+    pub fn set_line_length(&mut self, line_length: LineLength) {
+        self.line_length = line_length;
+    }
+
+    pub fn line_length(&self) -> LineLength {
+        self.line_length
+    }
+
+    // Whether lines of `symbol` longer than the win length don't count
+    pub fn is_exact_for(&self, symbol: Symbol) -> bool {
+        match self.line_length {
+            LineLength::AtLeast => false,
+            LineLength::Exactly => true,
+            LineLength::ExactlyFor(exact) => exact == symbol,
+        }
+    }
+
+    // Length of the unbroken run of the symbol at `pos` along `direction`,
+    // counting both ways, 0 for an empty cell
+    pub fn run_length(&self, pos: (usize, usize), direction: (isize, isize)) -> usize {
+        let symbol = match self.get_cell(pos) {
+            Some(symbol) => symbol,
+            None => return 0,
+        };
+        let mut length = 1;
+        for sign in [1, -1] {
+            let mut step = 1;
+            while let Some(next) = self.offset(pos, direction, sign * step) {
+                if self.get_cell(next) != Some(symbol) {
+                    break;
+                }
+                length += 1;
+                step += 1;
+            }
+        }
+        length
+    }
+
+    // The cell `steps` steps from `pos` along `direction`, if on the board
+    pub fn offset(
+        &self,
+        pos: (usize, usize),
+        direction: (isize, isize),
+        steps: isize,
+    ) -> Option<(usize, usize)> {
+        let row = pos.0 as isize + direction.0 * steps;
+        let col = pos.1 as isize + direction.1 * steps;
+        if row < 0 || col < 0 || row >= self.rows as isize || col >= self.cols as isize {
+            None
+        } else {
+            Some((row as usize, col as usize))
+        }
+    }
+
+    // Whether `symbol` at `pos` fills a whole line of the win length
+    pub fn completes_line(&self, pos: (usize, usize), symbol: Symbol) -> bool {
+        let mut board = self.clone();
+        if board.apply_move(pos, symbol).is_err() {
+            return false;
+        }
+        board.in_full_line(pos)
+    }
+
+    // Whether the symbol at `pos` is part of a whole line of the win length
+    pub fn in_full_line(&self, pos: (usize, usize)) -> bool {
+        let symbol = match self.get_cell(pos) {
+            Some(symbol) => symbol,
+            None => return false,
+        };
+        DIRECTIONS.iter().any(|&direction| {
+            let run = self.run_length(pos, direction);
+            if self.is_exact_for(symbol) {
+                run == self.win_length
+            } else {
+                run >= self.win_length
+            }
+        })
+    }
+
+    // Places `symbol` for the owner of `marks`, their cells oldest first.
+    // With a `max_marks` limit their oldest mark leaves the board once they
+    // already have that many. Returns the cell that was cleared
+    pub fn place_mark(
+        &mut self,
+        marks: &mut VecDeque<(usize, usize)>,
+        max_marks: Option<usize>,
+        pos: (usize, usize),
+        symbol: Symbol,
+    ) -> Result<Option<(usize, usize)>, BoardError> {
+        self.check_free(pos)?;
+        let removed = match max_marks {
+            Some(max) if marks.len() >= max.max(1) => marks.pop_front(),
+            _ => None,
+        };
+        if let Some(old) = removed {
+            self.undo_move(old)?;
+        }
+        self.apply_move(pos, symbol)?;
+        marks.push_back(pos);
+        Ok(removed)
+    }
+
+    pub fn winner_symbol(&self) -> Option<Symbol> {
+        self.has_winning_streak(self.win_length)
+            .and_then(|pos| self.get_cell(pos))
     }
 
     pub fn empty_positions(&self) -> Vec<(usize, usize)> {
+        self.positions(|cell| cell == Cell::Empty)
+    }
+
+    fn positions(&self, filter: impl Fn(Cell) -> bool) -> Vec<(usize, usize)> {
         let mut positions = Vec::new();
-        for i in 0..self.size {
-            for j in 0..self.size {
-                if self.cells[i][j].is_none() {
+        for i in 0..self.rows {
+            for j in 0..self.cols {
+                if filter(self.cells[i][j]) {
                     positions.push((i, j));
                 }
             }
@@ -25,91 +243,157 @@ impl GameBoard {
     }
 
     pub fn has_winning_streak(&self, streak_length: usize) -> Option<(usize, usize)> {
-        // Check rows
-        for row in 0..self.size {
-            for col in 0..=self.size - streak_length {
-                if let Some(symbol) = self.cells[row][col] {
-                    let mut is_streak = true;
-                    for i in 1..streak_length {
-                        if self.cells[row][col + i] != Some(symbol) {
-                            is_streak = false;
-                            break;
-                        }
-                    }
-                    if is_streak {
-                        return Some((row, col));
+        self.winning_line(streak_length).map(|line| line[0])
+    }
+
+    // Positions of the first complete streak found, checking rows, columns,
+    // then both diagonals
+    pub fn winning_line(&self, streak_length: usize) -> Option<Vec<(usize, usize)>> {
+        if streak_length == 0 || streak_length > self.rows.max(self.cols) {
+            return None;
+        }
+        for direction in DIRECTIONS {
+            for row in 0..self.rows {
+                for col in 0..self.cols {
+                    if self.is_streak((row, col), direction, streak_length) {
+                        return Some(
+                            (0..streak_length as isize)
+                                .map(|i| {
+                                    (
+                                        (row as isize + direction.0 * i) as usize,
+                                        (col as isize + direction.1 * i) as usize,
+                                    )
+                                })
+                                .collect(),
+                        );
                     }
                 }
             }
         }
 
-        // Check columns
-        for col in 0..self.size {
-            for row in 0..=self.size - streak_length {
-                if let Some(symbol) = self.cells[row][col] {
-                    let mut is_streak = true;
-                    for i in 1..streak_length {
-                        if self.cells[row + i][col] != Some(symbol) {
-                            is_streak = false;
-                            break;
-                        }
-                    }
-                    if is_streak {
-                        return Some((row, col));
-                    }
-                }
-            }
+        None
+    }
+
+    fn is_streak(&self, start: (usize, usize), direction: (isize, isize), length: usize) -> bool {
+        let symbol = match self.cells[start.0][start.1] {
+            Cell::Mark(symbol) => symbol,
+            _ => return false,
+        };
+        let last = length as isize - 1;
+        let end_row = start.0 as isize + direction.0 * last;
+        let end_col = start.1 as isize + direction.1 * last;
+        if end_row < 0
+            || end_col < 0
+            || end_row >= self.rows as isize
+            || end_col >= self.cols as isize
+        {
+            return false;
+        }
+        let streak = (1..length as isize).all(|i| {
+            let row = (start.0 as isize + direction.0 * i) as usize;
+            let col = (start.1 as isize + direction.1 * i) as usize;
+            self.cells[row][col] == Cell::Mark(symbol)
+        });
+        // An exact line may not go on past either end
+        if streak && self.is_exact_for(symbol) {
+            let before = self.offset(start, direction, -1);
+            let after = self.offset(start, direction, length as isize);
+            return [before, after]
+                .iter()
+                .all(|cell| cell.and_then(|pos| self.get_cell(pos)) != Some(symbol));
         }
+        streak
+    }
 
-        // Check diagonals (top-left to bottom-right)
-        for row in 0..=self.size - streak_length {
-            for col in 0..=self.size - streak_length {
-                if let Some(symbol) = self.cells[row][col] {
-                    let mut is_streak = true;
-                    for i in 1..streak_length {
-                        if self.cells[row + i][col + i] != Some(symbol) {
-                            is_streak = false;
-                            break;
-                        }
+    // Every run of `length` cells in a row, column or diagonal that no
+    // blocked cell breaks
+    pub fn lines(&self, length: usize) -> Vec<Vec<(usize, usize)>> {
+        let mut lines = Vec::new();
+        if length == 0 || length > self.rows.max(self.cols) {
+            return lines;
+        }
+        for (d_row, d_col) in DIRECTIONS {
+            for row in 0..self.rows {
+                for col in 0..self.cols {
+                    let last = length as isize - 1;
+                    let end_row = row as isize + d_row * last;
+                    let end_col = col as isize + d_col * last;
+                    if end_row >= self.rows as isize || end_col < 0 || end_col >= self.cols as isize
+                    {
+                        continue;
                     }
-                    if is_streak {
-                        return Some((row, col));
+                    let line: Vec<(usize, usize)> = (0..length as isize)
+                        .map(|i| {
+                            (
+                                (row as isize + d_row * i) as usize,
+                                (col as isize + d_col * i) as usize,
+                            )
+                        })
+                        .collect();
+                    if !line.iter().any(|&pos| self.is_blocked(pos)) {
+                        lines.push(line);
                     }
                 }
             }
         }
+        lines
+    }
 
-        // Check diagonals (top-right to bottom-left)
-        for row in 0..=self.size - streak_length {
-            for col in (streak_length - 1)..self.size {
-                if let Some(symbol) = self.cells[row][col] {
-                    let mut is_streak = true;
-                    for i in 1..streak_length {
-                        if self.cells[row + i][col - i] != Some(symbol) {
-                            is_streak = false;
-                            break;
-                        }
-                    }
-                    if is_streak {
-                        return Some((row, col));
-                    }
-                }
+    // The lines of the win length, worked out once for the board
+    pub fn win_lines(&self) -> &[Vec<(usize, usize)>] {
+        &self.win_lines.lines
+    }
+
+    // The lines of the win length that run through `pos`
+    pub fn win_lines_through(
+        &self,
+        pos: (usize, usize),
+    ) -> impl Iterator<Item = &Vec<(usize, usize)>> {
+        let through = self
+            .win_lines
+            .through
+            .get(pos.0)
+            .and_then(|row| row.get(pos.1))
+            .map_or(&[][..], Vec::as_slice);
+        through.iter().map(|&idx| &self.win_lines.lines[idx])
+    }
+
+    // Number of empty cells `symbol` still needs to fill the line, None if
+    // another symbol or a blocked cell is in the way
+    pub fn cells_needed(&self, line: &[(usize, usize)], symbol: Symbol) -> Option<usize> {
+        let mut needed = 0;
+        for &(row, col) in line {
+            match self.cells[row][col] {
+                Cell::Mark(other) if other != symbol => return None,
+                Cell::Blocked => return None,
+                Cell::Mark(_) => {}
+                Cell::Empty => needed += 1,
             }
         }
+        Some(needed)
+    }
 
-        None
+    // Whether `symbol` can still complete a winning line, optionally within
+    // the given number of moves
+    pub fn can_still_win(&self, symbol: Symbol, moves_left: Option<usize>) -> bool {
+        self.win_lines()
+            .iter()
+            .any(|line| match (self.cells_needed(line, symbol), moves_left) {
+                (Some(needed), Some(moves)) => needed <= moves,
+                (Some(_), None) => true,
+                (None, _) => false,
+            })
+    }
+
+    // Open winning lines through `pos` for `symbol`
+    pub fn open_lines_through(&self, pos: (usize, usize), symbol: Symbol) -> usize {
+        self.win_lines_through(pos)
+            .filter(|line| self.cells_needed(line, symbol).is_some())
+            .count()
     }
 
     pub fn get_available_positions(&self) -> Vec<(usize, usize)> {
-        let mut positions = Vec::new();
-        for row in 0..self.size {
-            for col in 0..self.size {
-                if self.cells[row][col].is_none() {
-                    positions.push((row, col));
-                }
-            }
-        }
-        positions
+        self.empty_positions()
     }
 
     pub fn record_move(
@@ -117,22 +401,24 @@ impl GameBoard {
         position: (usize, usize),
         symbol: Symbol,
     ) -> Result<(), BoardError> {
+        self.check_free(position)?;
         let (row, col) = position;
-
-        if row >= self.size || col >= self.size {
-            return Err(BoardError::OutOfBounds);
-        }
-
-        if self.cells[row][col].is_some() {
-            return Err(BoardError::CellOccupied);
-        }
-
-        self.cells[row][col] = Some(symbol);
+        self.cells[row][col] = Cell::Mark(symbol);
         Ok(())
     }
 
+    // Side length of a square board
+    #[deprecated(note = "boards can be rectangular, use rows() and cols()")]
     pub fn size(&self) -> usize {
-        self.size
+        self.rows
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
     }
 
     pub fn is_full(&self) -> bool {
@@ -140,47 +426,60 @@ impl GameBoard {
     }
 
     pub fn get_cell(&self, position: (usize, usize)) -> Option<Symbol> {
+        self.cell(position).symbol()
+    }
+
+    // Cells off the board count as blocked
+    pub fn cell(&self, position: (usize, usize)) -> Cell {
         if !self.is_valid_position(position) {
-            None
+            Cell::Blocked
         } else {
             let (row, col) = position;
             self.cells[row][col]
         }
     }
 
+    fn check_free(&self, pos: (usize, usize)) -> Result<(), BoardError> {
+        match self.cell(pos) {
+            _ if !self.is_valid_position(pos) => Err(BoardError::OutOfBounds),
+            Cell::Empty => Ok(()),
+            Cell::Blocked => Err(BoardError::CellBlocked),
+            Cell::Mark(_) => Err(BoardError::CellOccupied),
+        }
+    }
+
     fn is_valid_position(&self, pos: (usize, usize)) -> bool {
         let (row, col) = pos;
-        row < self.size && col < self.size
+        row < self.rows && col < self.cols
     }
 
     pub fn apply_move(&mut self, pos: (usize, usize), symbol: Symbol) -> Result<(), BoardError> {
-        if !self.is_valid_position(pos) {
-            return Err(BoardError::OutOfBounds);
-        }
+        self.check_free(pos)?;
         let (row, col) = pos;
-        if self.cells[row][col].is_some() {
-            return Err(BoardError::CellOccupied);
-        }
-        self.cells[row][col] = Some(symbol);
+        self.cells[row][col] = Cell::Mark(symbol);
         Ok(())
     }
 
+    // Takes a mark off the board, blocked cells stay blocked
     pub fn undo_move(&mut self, pos: (usize, usize)) -> Result<(), BoardError> {
         if !self.is_valid_position(pos) {
             return Err(BoardError::OutOfBounds);
         }
         let (row, col) = pos;
-        self.cells[row][col] = None;
+        if let Cell::Mark(_) = self.cells[row][col] {
+            self.cells[row][col] = Cell::Empty;
+        }
         Ok(())
     }
 
     pub fn evaluate(&self, ai_symbol: Symbol, player_symbol: Symbol) -> i32 {
-        if let Some((row, col)) = self.has_winning_streak(3) {
+        let score = if self.misere { -10 } else { 10 };
+        if let Some((row, col)) = self.has_winning_streak(self.win_length) {
             if let Some(winner) = self.get_cell((row, col)) {
                 if winner == ai_symbol {
-                    return 10;
+                    return score;
                 } else if winner == player_symbol {
-                    return -10;
+                    return -score;
                 }
             }
         }
@@ -190,11 +489,9 @@ impl GameBoard {
     pub fn get_all_symbols(&self) -> Vec<Symbol> {
         let mut symbols = Vec::new();
         for row in &self.cells {
-            for cell in row {
-                if let Some(symbol) = cell {
-                    if !symbols.contains(symbol) {
-                        symbols.push(*symbol);
-                    }
+            for symbol in row.iter().filter_map(|cell| cell.symbol()) {
+                if !symbols.contains(&symbol) {
+                    symbols.push(symbol);
                 }
             }
         }
@@ -205,19 +502,19 @@ impl GameBoard {
         let mut positions = Vec::new();
 
         // Check each empty position
-        for row in 0..self.size {
-            for col in 0..self.size {
-                if self.cells[row][col].is_none() {
+        for row in 0..self.rows {
+            for col in 0..self.cols {
+                if self.cells[row][col] == Cell::Empty {
                     // Try the move
-                    self.cells[row][col] = Some(symbol);
+                    self.cells[row][col] = Cell::Mark(symbol);
 
                     // Check if it's a winning move
-                    if self.has_winning_streak(3).is_some() {
+                    if self.has_winning_streak(self.win_length).is_some() {
                         positions.push((row, col));
                     }
 
                     // Undo the move
-                    self.cells[row][col] = None;
+                    self.cells[row][col] = Cell::Empty;
                 }
             }
         }
@@ -0,0 +1,94 @@
+use crate::game_board::{GameBoard, DIRECTIONS};
+use crate::types::{ForbiddenMove, Symbol};
+
+// Renju restrictions for the first player. A move that makes exactly the
+// win length always stands; otherwise an overline, two fours or two open
+// threes made by the same stone are forbidden
+pub fn forbidden_move(
+    board: &GameBoard,
+    pos: (usize, usize),
+    symbol: Symbol,
+) -> Option<ForbiddenMove> {
+    let mut after = board.clone();
+    after.apply_move(pos, symbol).ok()?;
+    let length = board.win_length();
+    let runs: Vec<usize> = DIRECTIONS
+        .iter()
+        .map(|&direction| after.run_length(pos, direction))
+        .collect();
+    if runs.contains(&length) {
+        return None;
+    }
+    if runs.iter().any(|&run| run > length) {
+        return Some(ForbiddenMove::Overline);
+    }
+    let fours: usize = DIRECTIONS
+        .iter()
+        .map(|&direction| fours(&after, pos, direction))
+        .sum();
+    if fours >= 2 {
+        return Some(ForbiddenMove::DoubleFour);
+    }
+    let threes = DIRECTIONS
+        .iter()
+        .filter(|&&direction| is_open_three(&after, pos, direction))
+        .count();
+    if threes >= 2 {
+        return Some(ForbiddenMove::DoubleThree);
+    }
+    None
+}
+
+// Empty cells along `direction` that would make the stone at `pos` part of
+// a run of exactly the win length
+fn five_cells(
+    board: &GameBoard,
+    pos: (usize, usize),
+    direction: (isize, isize),
+) -> Vec<(usize, usize)> {
+    let symbol = match board.get_cell(pos) {
+        Some(symbol) => symbol,
+        None => return Vec::new(),
+    };
+    let length = board.win_length() as isize;
+    (-length..=length)
+        .filter_map(|steps| board.offset(pos, direction, steps))
+        .filter(|&cell| board.get_cell(cell).is_none())
+        .filter(|&cell| {
+            let mut next = board.clone();
+            next.apply_move(cell, symbol).is_ok()
+                && next.run_length(pos, direction) == board.win_length()
+        })
+        .collect()
+}
+
+// Fours through `pos` along one direction. A straight four open at both
+// ends is one four, two gaps that each make five in a broken line are two
+fn fours(board: &GameBoard, pos: (usize, usize), direction: (isize, isize)) -> usize {
+    let cells = five_cells(board, pos, direction);
+    match cells.len() {
+        0 => 0,
+        1 => 1,
+        _ if board.run_length(pos, direction) == board.win_length() - 1 => 1,
+        _ => 2,
+    }
+}
+
+// Whether one more stone along `direction` turns the run through `pos`
+// into a straight four, one that makes five at either end
+fn is_open_three(board: &GameBoard, pos: (usize, usize), direction: (isize, isize)) -> bool {
+    let symbol = match board.get_cell(pos) {
+        Some(symbol) => symbol,
+        None => return false,
+    };
+    let length = board.win_length() as isize;
+    (-length..=length)
+        .filter_map(|steps| board.offset(pos, direction, steps))
+        .filter(|&cell| board.get_cell(cell).is_none())
+        .any(|cell| {
+            let mut next = board.clone();
+            next.apply_move(cell, symbol).is_ok()
+                && next.run_length(pos, direction) == board.win_length() - 1
+                && five_cells(&next, pos, direction).len() == 2
+        })
+}
@@ -0,0 +1,651 @@
+use crate::game::{Game, OpeningStage};
+use crate::player::Player;
+use crate::rules::GameRules;
+use crate::types::{
+    BoardError, Difficulty, GameError, OpeningChoice, PlayerError, PlayerId, Symbol, SymbolSet,
+    Token,
+};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Display;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Larger requests are refused: the request line and headers together, and
+// the body on its own
+const MAX_HEAD: u64 = 8 * 1024;
+const MAX_BODY: usize = 64 * 1024;
+// Time a client gets to send the whole request, and to take the response
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+// Requests handled at once, more connections are turned away
+const MAX_CONNECTIONS: usize = 64;
+// Longest starting time, increment, delay or move limit of a clock
+const MAX_CLOCK: Duration = Duration::from_secs(24 * 60 * 60);
+// Finished games stay this long for clients to fetch the result
+const FINISHED_GAME_TTL: Duration = Duration::from_secs(60 * 60);
+
+struct Request {
+    method: String,
+    path: String,
+    body: Vec<u8>,
+}
+
+struct Response {
+    status: u16,
+    body: Option<Value>,
+}
+
+impl Response {
+    fn json(status: u16, body: Value) -> Self {
+        Self {
+            status,
+            body: Some(body),
+        }
+    }
+
+    fn error(status: u16, code: &str, message: impl Display) -> Self {
+        Self::json(
+            status,
+            json!({ "error": code, "message": message.to_string() }),
+        )
+    }
+}
+
+impl From<GameError> for Response {
+    fn from(error: GameError) -> Self {
+        let (status, code) = match &error {
+            GameError::InvalidMove => (400, "invalid_move"),
+            GameError::BoardError(BoardError::InvalidPosition) => (400, "invalid_position"),
+            GameError::BoardError(BoardError::OutOfBounds) => (400, "out_of_bounds"),
+            GameError::BoardError(BoardError::CellOccupied) => (409, "cell_occupied"),
+            GameError::BoardError(BoardError::CellBlocked) => (409, "cell_blocked"),
+            GameError::BoardError(BoardError::InactiveSubBoard) => (409, "inactive_sub_board"),
+            GameError::UnknownPlayer => (404, "unknown_player"),
+            GameError::OutOfTurn => (409, "out_of_turn"),
+            GameError::GameOver => (409, "game_over"),
+            GameError::OutOfTime => (409, "out_of_time"),
+            GameError::SymbolNotAllowed => (409, "symbol_not_allowed"),
+            GameError::ForbiddenMove(_) => (409, "forbidden_move"),
+            GameError::ChoicePending => (409, "choice_pending"),
+            GameError::MaxPlayersReached => (409, "max_players_reached"),
+            GameError::PlayerAlreadyJoined => (409, "player_already_joined"),
+            GameError::NothingToUndo => (409, "nothing_to_undo"),
+            GameError::NothingToRedo => (409, "nothing_to_redo"),
+            GameError::NoDrawOffer => (409, "no_draw_offer"),
+            GameError::SaveFailed(_) => (500, "save_failed"),
+            GameError::LoadFailed(_) => (500, "load_failed"),
+        };
+        Response::error(status, code, error)
+    }
+}
+
+impl From<PlayerError> for Response {
+    fn from(error: PlayerError) -> Self {
+        let (status, code) = match &error {
+            PlayerError::SymbolTaken => (409, "symbol_taken"),
+            PlayerError::InvalidSymbol => (400, "invalid_symbol"),
+            PlayerError::NotFound => (404, "unknown_player"),
+            PlayerError::InvalidData => (400, "invalid_request"),
+        };
+        Response::error(status, code, error)
+    }
+}
+
+// Either way the handler has an answer for the client
+type Handled = Result<Response, Response>;
+
+#[derive(Deserialize)]
+struct NewGame {
+    #[serde(default = "default_board_size")]
+    board_size: usize,
+    // Columns of a rectangular board, `board_size` being its rows
+    #[serde(default)]
+    board_cols: Option<usize>,
+    #[serde(default = "default_max_players")]
+    max_players: usize,
+    #[serde(default)]
+    rules: Option<GameRules>,
+}
+
+fn default_board_size() -> usize {
+    3
+}
+
+fn default_max_players() -> usize {
+    2
+}
+
+#[derive(Deserialize)]
+struct NewPlayer {
+    name: String,
+    #[serde(default)]
+    symbol: Option<String>,
+    // Set for AI players, which then move on their own
+    #[serde(default)]
+    difficulty: Option<Difficulty>,
+}
+
+#[derive(Deserialize)]
+struct NewMove {
+    player: String,
+    // Handed out when the player joined
+    token: Token,
+    row: usize,
+    col: usize,
+    // Wild games only, the player's own symbol otherwise
+    #[serde(default)]
+    symbol: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct NewChoice {
+    player: String,
+    token: Token,
+    choice: OpeningChoice,
+}
+
+struct HostedGame {
+    game: Game,
+    // Move tokens of the human players, never part of the game's JSON
+    tokens: HashMap<PlayerId, Token>,
+    // Why the AI player to move couldn't, the moves before it still stand
+    ai_error: Option<String>,
+    // When the game was first seen over
+    finished: Option<Instant>,
+}
+
+impl HostedGame {
+    // Flags fall while nobody plays, so every look at the game checks the
+    // clock first
+    fn state(&mut self, id: u64) -> Value {
+        self.check_clock();
+        let mut state = game_state(id, &self.game);
+        state["ai_error"] = json!(self.ai_error);
+        state
+    }
+
+    fn play_ai_turns(&mut self) {
+        self.ai_error = self.game.play_ai_turns().err().map(|e| e.to_string());
+    }
+
+    fn check_clock(&mut self) {
+        if self.game.check_clock() {
+            self.play_ai_turns();
+        }
+        if self.game.is_over() && self.finished.is_none() {
+            self.finished = Some(Instant::now());
+        }
+    }
+
+    fn is_expired(&self) -> bool {
+        self.finished
+            .is_some_and(|finished| finished.elapsed() > FINISHED_GAME_TTL)
+    }
+
+    // The player the token was handed out to, once the game is full
+    fn authorize(&self, player: &str, token: &Token) -> Result<PlayerId, Response> {
+        let player: PlayerId = player
+            .parse()
+            .map_err(|_| Response::from(GameError::UnknownPlayer))?;
+        if self.tokens.get(&player) != Some(token) {
+            return Err(Response::error(
+                403,
+                "invalid_token",
+                "The token doesn't belong to this player",
+            ));
+        }
+        if self.game.players().len() < self.game.max_players() {
+            return Err(Response::error(
+                409,
+                "waiting_for_players",
+                "Waiting for more players to join",
+            ));
+        }
+        Ok(player)
+    }
+}
+
+// Every game has a lock of its own, so a slow AI turn only holds up its
+// own game. The map is locked just long enough to find or add one
+struct Games {
+    games: Mutex<BTreeMap<u64, Arc<Mutex<HostedGame>>>>,
+    symbol_set: SymbolSet,
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// Serves games as JSON resources over HTTP:
+//   POST /games                  create a game
+//   GET  /games                  list games
+//   GET  /games/{id}             board, players, current player and status
+//   POST /games/{id}/players     join a game, humans get a move token
+//   POST /games/{id}/moves       play a move with the player's token
+//   POST /games/{id}/choices     answer the choice of a swap opening
+//   GET  /games/{id}/history     moves played so far
+pub struct ApiServer {
+    listener: TcpListener,
+    games: Arc<Games>,
+    connections: Arc<AtomicUsize>,
+}
+
+// Counts a connection as handled until it is dropped
+struct ConnectionSlot(Arc<AtomicUsize>);
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl ApiServer {
+    pub fn bind(addr: impl ToSocketAddrs, symbol_set: SymbolSet) -> io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr)?,
+            games: Arc::new(Games {
+                games: Mutex::new(BTreeMap::new()),
+                symbol_set,
+            }),
+            connections: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    // Handles each connection on its own thread, one request per connection
+    pub fn run(&self) -> io::Result<()> {
+        for stream in self.listener.incoming() {
+            let mut stream = stream?;
+            let slot = ConnectionSlot(Arc::clone(&self.connections));
+            if self.connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                drop(slot);
+                let _ = stream.set_write_timeout(Some(REQUEST_TIMEOUT));
+                let busy = Response::error(503, "busy", "Too many requests at once, try again");
+                let _ = write_response(&mut stream, &busy);
+                continue;
+            }
+            let games = Arc::clone(&self.games);
+            thread::spawn(move || {
+                handle_connection(&games, stream);
+                drop(slot);
+            });
+        }
+        Ok(())
+    }
+}
+
+fn handle_connection(games: &Games, mut stream: TcpStream) {
+    let response = match read_request(&stream) {
+        Ok(Some(request)) => games.route(&request).unwrap_or_else(|error| error),
+        Ok(None) => Response::error(400, "bad_request", "Malformed HTTP request"),
+        Err(_) => return,
+    };
+    let _ = stream.set_write_timeout(Some(REQUEST_TIMEOUT));
+    let _ = write_response(&mut stream, &response);
+}
+
+// Reads until the deadline, so a slow client can't keep its thread forever
+struct TimedStream<'a> {
+    stream: &'a TcpStream,
+    deadline: Instant,
+}
+
+impl Read for TimedStream<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let left = self.deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+        self.stream.set_read_timeout(Some(left))?;
+        (&mut &*self.stream).read(buf)
+    }
+}
+
+// None for malformed or oversized requests
+fn read_request(stream: &TcpStream) -> io::Result<Option<Request>> {
+    let timed = TimedStream {
+        stream,
+        deadline: Instant::now() + REQUEST_TIMEOUT,
+    };
+    let mut reader = BufReader::new(timed.take(MAX_HEAD));
+    // A line cut short ran into the size limit or the end of the stream
+    let mut read_head_line = |line: &mut String| -> io::Result<bool> {
+        reader.read_line(line)?;
+        Ok(line.ends_with('\n'))
+    };
+
+    let mut line = String::new();
+    if !read_head_line(&mut line)? {
+        return Ok(None);
+    }
+    let mut parts = line.split_whitespace();
+    let (method, target) = match (parts.next(), parts.next()) {
+        (Some(method), Some(target)) => (method.to_string(), target),
+        _ => return Ok(None),
+    };
+    let path = target.split('?').next().unwrap_or_default().to_string();
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        if !read_head_line(&mut header)? {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = match value.trim().parse() {
+                    Ok(length) if length <= MAX_BODY => length,
+                    _ => return Ok(None),
+                };
+            }
+        }
+    }
+
+    // The body has a limit of its own
+    reader.get_mut().set_limit(content_length as u64);
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    Ok(Some(Request { method, path, body }))
+}
+
+fn write_response(stream: &mut TcpStream, response: &Response) -> io::Result<()> {
+    let body = response
+        .body
+        .as_ref()
+        .map(|body| body.to_string())
+        .unwrap_or_default();
+    let reason = match response.status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\n\
+         Access-Control-Allow-Methods: GET, POST, OPTIONS\r\n\
+         Access-Control-Allow-Headers: Content-Type\r\n\
+         Connection: close\r\n\r\n{}",
+        response.status,
+        reason,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+fn parse_body<T: DeserializeOwned>(body: &[u8]) -> Result<T, Response> {
+    serde_json::from_slice(body).map_err(|e| Response::error(400, "invalid_request", e))
+}
+
+fn game_state(id: u64, game: &Game) -> Value {
+    let board = game.board();
+    let cells: Vec<Vec<Option<String>>> = (0..board.rows())
+        .map(|row| {
+            (0..board.cols())
+                .map(|col| board.get_cell((row, col)).map(|s| s.to_string()))
+                .collect()
+        })
+        .collect();
+    let players: Vec<Value> = game
+        .players()
+        .iter()
+        .map(|p| {
+            json!({
+                "id": p.id().to_string(),
+                "name": p.name(),
+                "symbol": p.symbol().to_string(),
+                "ai": p.is_ai(),
+                "time_left_ms": game.time_left(p.id()).map(|left| left.as_millis() as u64),
+            })
+        })
+        .collect();
+    let current_player = game
+        .current_player()
+        .filter(|_| !game.is_over())
+        .map(|p| p.id().to_string());
+    // Swap openings wait for this player to pick from the options
+    let choice = match game.opening_stage() {
+        OpeningStage::Choosing { player, options } if !game.is_over() => {
+            Some(json!({ "player": player.to_string(), "options": options }))
+        }
+        _ => None,
+    };
+
+    json!({
+        "id": id,
+        "board": cells,
+        "blocked": board.blocked_positions(),
+        "win_length": board.win_length(),
+        "max_players": game.max_players(),
+        "players": players,
+        "current_player": current_player,
+        "choice": choice,
+        "status": game.status(),
+        "status_text": game.status().to_string(),
+        "winner": game.winner().map(|w| w.id().to_string()),
+    })
+}
+
+impl Games {
+    fn route(&self, request: &Request) -> Handled {
+        let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+        match (request.method.as_str(), segments.as_slice()) {
+            // CORS preflight from browsers
+            ("OPTIONS", _) => Ok(Response {
+                status: 204,
+                body: None,
+            }),
+            ("GET", ["games"]) => Ok(self.list_games()),
+            ("POST", ["games"]) => self.create_game(parse_body(&request.body)?),
+            ("GET", ["games", id]) => {
+                let (id, hosted) = self.game(id)?;
+                let state = lock(&hosted).state(id);
+                Ok(Response::json(200, state))
+            }
+            ("POST", ["games", id, "players"]) => self.add_player(id, parse_body(&request.body)?),
+            ("POST", ["games", id, "moves"]) => self.play(id, parse_body(&request.body)?),
+            ("POST", ["games", id, "choices"]) => self.choose(id, parse_body(&request.body)?),
+            ("GET", ["games", id, "history"]) => {
+                let (_, hosted) = self.game(id)?;
+                let moves: Vec<Value> = lock(&hosted)
+                    .game
+                    .history()
+                    .iter()
+                    .map(|mv| {
+                        json!({
+                            "player": mv.player.to_string(),
+                            "symbol": mv.symbol.to_string(),
+                            "row": mv.position.0,
+                            "col": mv.position.1,
+                        })
+                    })
+                    .collect();
+                Ok(Response::json(200, json!(moves)))
+            }
+            (_, ["games"])
+            | (_, ["games", _])
+            | (_, ["games", _, "players" | "moves" | "choices" | "history"]) => Err(
+                Response::error(405, "method_not_allowed", "Method not allowed"),
+            ),
+            _ => Err(Response::error(404, "not_found", "No such resource")),
+        }
+    }
+
+    fn game(&self, id: &str) -> Result<(u64, Arc<Mutex<HostedGame>>), Response> {
+        let not_found = || Response::error(404, "game_not_found", format!("No game {}", id));
+        let id: u64 = id.parse().map_err(|_| not_found())?;
+        match lock(&self.games).get(&id) {
+            Some(hosted) => Ok((id, Arc::clone(hosted))),
+            None => Err(not_found()),
+        }
+    }
+
+    // Drops games that finished a while ago. Games busy with a request are
+    // left for a later one
+    fn expire_games(&self) {
+        lock(&self.games).retain(|_, hosted| match hosted.try_lock() {
+            Ok(mut hosted) => {
+                hosted.check_clock();
+                !hosted.is_expired()
+            }
+            Err(_) => true,
+        });
+    }
+
+    fn list_games(&self) -> Response {
+        self.expire_games();
+        let hosted: Vec<(u64, Arc<Mutex<HostedGame>>)> = lock(&self.games)
+            .iter()
+            .map(|(&id, hosted)| (id, Arc::clone(hosted)))
+            .collect();
+        let games: Vec<Value> = hosted
+            .iter()
+            .map(|(id, hosted)| lock(hosted).state(*id))
+            .collect();
+        Response::json(200, json!(games))
+    }
+
+    fn create_game(&self, request: NewGame) -> Handled {
+        let rules = request.rules.unwrap_or_default();
+        let rows = request.board_size;
+        let cols = request.board_cols.unwrap_or(rows);
+        if !(3..=10).contains(&rows)
+            || !(3..=10).contains(&cols)
+            || !(2..=4).contains(&request.max_players)
+            || !(3..=rows.max(cols)).contains(&rules.win_length)
+        {
+            return Err(Response::error(
+                400,
+                "invalid_rules",
+                "Rows and columns must be 3-10, players 2-4 and the win length 3 up to the longer side",
+            ));
+        }
+        if rules
+            .time_control
+            .as_ref()
+            .is_some_and(|tc| !tc.is_playable())
+        {
+            return Err(Response::error(
+                400,
+                "invalid_rules",
+                "The clock needs some time, an increment or a delay, and a move limit above zero",
+            ));
+        }
+        if rules.time_control.as_ref().is_some_and(|tc| {
+            [tc.initial, tc.increment, tc.delay]
+                .into_iter()
+                .chain(tc.per_move)
+                .any(|duration| duration > MAX_CLOCK)
+        }) {
+            return Err(Response::error(
+                400,
+                "invalid_rules",
+                "Clock times must be at most 24 hours",
+            ));
+        }
+        if !rules.cells_fit(rows, cols) {
+            return Err(Response::error(
+                400,
+                "invalid_rules",
+                "Blocked cells and opening stones must be on distinct cells of the board",
+            ));
+        }
+        let game = Game::with_dimensions(rows, cols, request.max_players, rules);
+        self.expire_games();
+        let mut games = lock(&self.games);
+        let id = games.keys().next_back().map_or(1, |last| last + 1);
+        let mut hosted = HostedGame {
+            game,
+            tokens: HashMap::new(),
+            ai_error: None,
+            finished: None,
+        };
+        let state = hosted.state(id);
+        games.insert(id, Arc::new(Mutex::new(hosted)));
+        Ok(Response::json(201, state))
+    }
+
+    fn add_player(&self, id: &str, request: NewPlayer) -> Handled {
+        let (id, hosted) = self.game(id)?;
+        let mut hosted = lock(&hosted);
+        let HostedGame { game, tokens, .. } = &mut *hosted;
+        let used: Vec<Symbol> = game.players().iter().map(|p| p.symbol()).collect();
+        let symbol = match &request.symbol {
+            Some(symbol) => Symbol::parse(symbol, &used)?,
+            None => self
+                .symbol_set
+                .pick_unique(&used)
+                .ok_or(GameError::MaxPlayersReached)?,
+        };
+        let is_human = request.difficulty.is_none();
+        let player = Player::new(request.name, symbol, is_human, request.difficulty);
+        game.add_player(&player)?;
+        // Only the player who joined learns the token, AI players need none
+        let token = is_human.then(Token::random);
+        if let Some(token) = &token {
+            tokens.insert(player.id(), token.clone());
+        }
+        if game.players().len() == game.max_players() {
+            hosted.play_ai_turns();
+        }
+        Ok(Response::json(
+            201,
+            json!({
+                "player": player.id().to_string(),
+                "token": token,
+                "symbol": symbol.to_string(),
+                "game": hosted.state(id),
+            }),
+        ))
+    }
+
+    fn play(&self, id: &str, request: NewMove) -> Handled {
+        let (id, hosted) = self.game(id)?;
+        let mut hosted = lock(&hosted);
+        let player = hosted.authorize(&request.player, &request.token)?;
+        let game = &mut hosted.game;
+        let pos = (request.row, request.col);
+        match request.symbol {
+            Some(symbol) => {
+                let symbol = Symbol::parse(&symbol, &[])?;
+                game.make_move_with_as(player, pos, symbol)?;
+            }
+            None => game.make_move_as(player, pos)?,
+        }
+        // The move is played, an AI that fails to answer it shows in the state
+        hosted.play_ai_turns();
+        Ok(Response::json(200, hosted.state(id)))
+    }
+
+    fn choose(&self, id: &str, request: NewChoice) -> Handled {
+        let (id, hosted) = self.game(id)?;
+        let mut hosted = lock(&hosted);
+        let player = hosted.authorize(&request.player, &request.token)?;
+        hosted.game.choose_opening(player, request.choice)?;
+        hosted.play_ai_turns();
+        Ok(Response::json(200, hosted.state(id)))
+    }
+}
@@ -1,7 +1,26 @@
 pub mod ai;
+pub mod client;
+pub mod command;
+pub mod cube;
+pub mod engine;
+pub mod events;
 pub mod game;
 pub mod game_board;
+pub mod gomoku;
+pub mod http;
+pub mod lobby;
 pub mod player;
+pub mod protocol;
+pub mod render;
+pub mod rules;
+pub mod runner;
 pub mod score_board;
+pub mod server;
+pub mod shape;
+pub mod snapshot;
+pub mod spectator;
+pub mod theme;
+pub mod tui;
 pub mod types;
 pub mod ui;
+pub mod ultimate;
@@ -0,0 +1,800 @@
+use crate::game::Game;
+use crate::player::Player;
+use crate::protocol::{read_message, ClientMessage, GameInfo, GameStage, ServerMessage};
+use crate::rules::GameRules;
+use crate::score_board::ScoreBoard;
+use crate::server::{pick_symbol, Outbox};
+use crate::spectator::evaluate;
+use crate::types::{GameError, PlayerId, Symbol, SymbolSet, Token};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, BufReader};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+// Matchmaking pairs players whose ratings are this close, and widens the
+// window by the growth for every second they have been waiting
+const MATCH_WINDOW: f64 = 100.0;
+const MATCH_WINDOW_GROWTH: f64 = 50.0;
+
+struct Member {
+    name: Option<String>,
+    player: Option<PlayerId>,
+    table: Option<u64>,
+    spectating: bool,
+    outbox: Outbox,
+}
+
+struct Table {
+    game: Game,
+    result_sent: bool,
+}
+
+impl Table {
+    fn stage(&self) -> GameStage {
+        if self.game.is_over() {
+            GameStage::Finished
+        } else if self.game.players().len() < self.game.max_players() {
+            GameStage::Open
+        } else {
+            GameStage::InProgress
+        }
+    }
+}
+
+struct Seeker {
+    member: usize,
+    player: PlayerId,
+    board_size: usize,
+    max_players: usize,
+    since: Instant,
+}
+
+impl Seeker {
+    fn window(&self, now: Instant) -> f64 {
+        MATCH_WINDOW + MATCH_WINDOW_GROWTH * now.duration_since(self.since).as_secs_f64()
+    }
+}
+
+struct LobbyState {
+    symbol_set: SymbolSet,
+    next_game: u64,
+    tables: BTreeMap<u64, Table>,
+    members: HashMap<usize, Member>,
+    queue: Vec<Seeker>,
+    scores: ScoreBoard,
+    // Handed out at the first login, needed to take a seat back
+    tokens: HashMap<PlayerId, Token>,
+    // The unfinished game each player has a seat in
+    seats: HashMap<PlayerId, u64>,
+}
+
+// Hosts any number of games over TCP. Players log in, then create, join or
+// watch games, or let matchmaking pair them with players of a similar rating
+pub struct LobbyServer {
+    listener: TcpListener,
+    state: Arc<Mutex<LobbyState>>,
+}
+
+impl LobbyServer {
+    pub fn bind(addr: impl ToSocketAddrs, symbol_set: SymbolSet) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            state: Arc::new(Mutex::new(LobbyState {
+                symbol_set,
+                next_game: 1,
+                tables: BTreeMap::new(),
+                members: HashMap::new(),
+                queue: Vec::new(),
+                scores: ScoreBoard::new(),
+                tokens: HashMap::new(),
+                seats: HashMap::new(),
+            })),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn run(&self) -> io::Result<()> {
+        let mut next_id = 0;
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    stream.set_nonblocking(false)?;
+                    let outbox = Outbox::new(&stream)?;
+                    self.lock().members.insert(
+                        next_id,
+                        Member {
+                            name: None,
+                            player: None,
+                            table: None,
+                            spectating: false,
+                            outbox,
+                        },
+                    );
+                    let state = Arc::clone(&self.state);
+                    let id = next_id;
+                    thread::spawn(move || handle_connection(state, id, stream));
+                    next_id += 1;
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(e) => return Err(e),
+            }
+
+            let mut state = self.lock();
+            let flagged: Vec<u64> = state
+                .tables
+                .iter_mut()
+                .filter_map(|(&id, table)| table.game.check_clock().then_some(id))
+                .collect();
+            for id in flagged {
+                state.after_change(id);
+            }
+            state.match_players();
+            drop(state);
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, LobbyState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn handle_connection(state: Arc<Mutex<LobbyState>>, id: usize, stream: TcpStream) {
+    let mut reader = BufReader::new(stream);
+    loop {
+        let message = read_message::<ClientMessage>(&mut reader);
+        let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+        match message {
+            Ok(Some(message)) => state.handle(id, message),
+            Ok(None) => {
+                state.disconnect(id);
+                return;
+            }
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                state.send(id, &error(format!("Invalid message: {}", e)));
+            }
+            Err(_) => {
+                state.disconnect(id);
+                return;
+            }
+        }
+    }
+}
+
+fn error(message: impl Into<String>) -> ServerMessage {
+    ServerMessage::Error {
+        message: message.into(),
+    }
+}
+
+fn notice(text: impl Into<String>) -> ServerMessage {
+    ServerMessage::Notice { text: text.into() }
+}
+
+fn validate(board_size: usize, max_players: usize, win_length: usize) -> Result<(), String> {
+    if !(3..=10).contains(&board_size)
+        || !(2..=4).contains(&max_players)
+        || !(3..=board_size).contains(&win_length)
+    {
+        return Err(
+            "Board size must be 3-10, players 2-4 and the win length 3 up to the board size"
+                .to_string(),
+        );
+    }
+    Ok(())
+}
+
+impl LobbyState {
+    fn handle(&mut self, id: usize, message: ClientMessage) {
+        let result = match message {
+            ClientMessage::Login {
+                name,
+                player,
+                token,
+            } => match (player, token) {
+                (Some(player), Some(token)) => self
+                    .check_token(player, &token)
+                    .and_then(|()| self.login(id, name, Some(player))),
+                (Some(_), None) => Err(GameError::UnknownPlayer.to_string()),
+                (None, _) => self.login(id, name, None),
+            },
+            ClientMessage::Rejoin { player, token } => self
+                .check_token(player, &token)
+                .and_then(|()| self.rejoin(id, player)),
+            // Clients made for a single game get a quick match instead
+            ClientMessage::Join { name, .. } => self
+                .login(id, name, None)
+                .and_then(|()| self.find_match(id, 3, 2)),
+            ClientMessage::ListGames => {
+                let games = self.games();
+                self.send(id, &ServerMessage::Games { games });
+                Ok(())
+            }
+            ClientMessage::CreateGame {
+                board_size,
+                max_players,
+                win_length,
+            } => self.create_game(id, board_size, max_players, win_length),
+            ClientMessage::JoinGame { game, symbol } => self.join_game(id, game, symbol),
+            ClientMessage::Spectate { game: Some(game) } => self.spectate(id, game),
+            ClientMessage::Spectate { game: None } => {
+                Err("Say which game to watch, list_games shows them".to_string())
+            }
+            ClientMessage::FindMatch {
+                board_size,
+                max_players,
+            } => self.find_match(id, board_size, max_players),
+            ClientMessage::Move { row, col, symbol } => {
+                self.play(id, |game, player| match symbol {
+                    Some(symbol) => game.make_move_with_as(player, (row, col), symbol),
+                    None => game.make_move_as(player, (row, col)),
+                })
+            }
+            ClientMessage::Choose { choice } => {
+                self.play(id, |game, player| game.choose_opening(player, choice))
+            }
+            ClientMessage::Leave => self.leave(id),
+            // Before the game starts there is nothing to lose, only a seat
+            ClientMessage::Resign if self.waiting_at_table(id) => self.leave(id),
+            ClientMessage::Resign => self.play(id, |game, player| game.resign_player(player)),
+            ClientMessage::Chat { text } => {
+                self.chat(id, text);
+                Ok(())
+            }
+        };
+        if let Err(message) = result {
+            self.send(id, &error(message));
+        }
+    }
+
+    // Known players have shown their token already
+    fn login(&mut self, id: usize, name: String, player: Option<PlayerId>) -> Result<(), String> {
+        if self.members.get(&id).is_some_and(|m| m.player.is_some()) {
+            return Err("You are already logged in".to_string());
+        }
+        if let Some(player) = player {
+            if self.seated_table(player).is_some() {
+                return self.rejoin(id, player);
+            }
+            self.drop_stale(id, player);
+        }
+        let player = player.unwrap_or_else(PlayerId::random);
+        self.tokens.entry(player).or_insert_with(Token::random);
+        if let Some(member) = self.members.get_mut(&id) {
+            member.name = Some(name);
+            member.player = Some(player);
+        }
+        self.logged_in_as(id, player);
+        Ok(())
+    }
+
+    fn check_token(&self, player: PlayerId, token: &Token) -> Result<(), String> {
+        if self.tokens.get(&player) == Some(token) {
+            Ok(())
+        } else {
+            Err(GameError::UnknownPlayer.to_string())
+        }
+    }
+
+    fn logged_in_as(&mut self, id: usize, player: PlayerId) {
+        if let Some(token) = self.tokens.get(&player).cloned() {
+            let rating = self.scores.rating(player);
+            self.send(
+                id,
+                &ServerMessage::LoggedIn {
+                    player,
+                    rating,
+                    token,
+                },
+            );
+        }
+    }
+
+    fn welcome(&mut self, id: usize, player: PlayerId, symbol: Symbol) {
+        if let Some(token) = self.tokens.get(&player).cloned() {
+            self.send(
+                id,
+                &ServerMessage::Welcome {
+                    player,
+                    symbol,
+                    token,
+                },
+            );
+        }
+    }
+
+    // Takes back a seat after a dropped connection
+    fn rejoin(&mut self, id: usize, player: PlayerId) -> Result<(), String> {
+        let table_id = self
+            .seated_table(player)
+            .ok_or_else(|| "You have no game to go back to".to_string())?;
+        let table = &self.tables[&table_id];
+        let seat = table
+            .game
+            .player(player)
+            .map(|p| (p.name().to_string(), p.symbol()));
+        let Some((name, symbol)) = seat else {
+            return Err("You have no game to go back to".to_string());
+        };
+
+        self.drop_stale(id, player);
+        if let Some(member) = self.members.get_mut(&id) {
+            member.name = Some(name.clone());
+            member.player = Some(player);
+        }
+        self.move_to(id, Some(table_id), false);
+        self.logged_in_as(id, player);
+        self.send(
+            id,
+            &ServerMessage::Joined {
+                game: table_id,
+                spectator: false,
+            },
+        );
+        self.welcome(id, player, symbol);
+        self.broadcast(table_id, &notice(format!("{} is back", name)));
+        let snapshot = Box::new(self.tables[&table_id].game.snapshot());
+        self.send(id, &ServerMessage::State { snapshot });
+        Ok(())
+    }
+
+    // The player's old connection may not have noticed it is gone yet
+    fn drop_stale(&mut self, id: usize, player: PlayerId) {
+        let stale: Vec<usize> = self
+            .members
+            .iter()
+            .filter(|(&other, m)| other != id && m.player == Some(player))
+            .map(|(&other, _)| other)
+            .collect();
+        for stale_id in stale {
+            self.queue.retain(|seeker| seeker.member != stale_id);
+            if let Some(member) = self.members.remove(&stale_id) {
+                member.outbox.close();
+            }
+        }
+    }
+
+    fn create_game(
+        &mut self,
+        id: usize,
+        board_size: usize,
+        max_players: usize,
+        win_length: Option<usize>,
+    ) -> Result<(), String> {
+        self.free_player(id)?;
+        let win_length = win_length.unwrap_or(3);
+        validate(board_size, max_players, win_length)?;
+        let table_id = self.open_table(board_size, max_players, win_length);
+        self.seat(id, table_id, None)
+    }
+
+    fn join_game(&mut self, id: usize, game: u64, symbol: Option<String>) -> Result<(), String> {
+        self.free_player(id)?;
+        if !self.tables.contains_key(&game) {
+            return Err(format!("There is no game #{}", game));
+        }
+        self.seat(id, game, symbol)
+    }
+
+    fn spectate(&mut self, id: usize, game: u64) -> Result<(), String> {
+        if let Some(player) = self.members.get(&id).and_then(|m| m.player) {
+            if self.seated_table(player).is_some() {
+                return Err("Finish your current game first".to_string());
+            }
+        }
+        let Some(table) = self.tables.get(&game) else {
+            return Err(format!("There is no game #{}", game));
+        };
+        let snapshot = Box::new(table.game.snapshot());
+        let result = table.result_sent.then(|| table.game.status());
+        let evaluations = (!table.game.is_over()).then(|| evaluate(&table.game));
+        self.move_to(id, Some(game), true);
+        self.send(
+            id,
+            &ServerMessage::Joined {
+                game,
+                spectator: true,
+            },
+        );
+        self.send(id, &ServerMessage::State { snapshot });
+        if let Some(evaluations) = evaluations {
+            self.send(id, &ServerMessage::Evaluation { evaluations });
+        }
+        if let Some(status) = result {
+            self.send(id, &ServerMessage::Result { status });
+        }
+        Ok(())
+    }
+
+    fn find_match(
+        &mut self,
+        id: usize,
+        board_size: usize,
+        max_players: usize,
+    ) -> Result<(), String> {
+        let (player, _) = self.free_player(id)?;
+        validate(board_size, max_players, 3)?;
+        self.queue.retain(|seeker| seeker.member != id);
+        self.queue.push(Seeker {
+            member: id,
+            player,
+            board_size,
+            max_players,
+            since: Instant::now(),
+        });
+        self.send(
+            id,
+            &notice(format!(
+                "Looking for a {}x{} game with {} players...",
+                board_size, board_size, max_players
+            )),
+        );
+        self.match_players();
+        Ok(())
+    }
+
+    // Starts a game for the longest waiting player as soon as enough players
+    // with a close enough rating are looking for the same kind of game
+    fn match_players(&mut self) {
+        let now = Instant::now();
+        let mut i = 0;
+        while i < self.queue.len() {
+            let seeker = &self.queue[i];
+            let rating = self.scores.rating(seeker.player);
+            let mut candidates: Vec<(i32, usize)> = self
+                .queue
+                .iter()
+                .enumerate()
+                .filter(|&(j, other)| {
+                    let difference = (self.scores.rating(other.player) - rating).abs() as f64;
+                    j != i
+                        && other.board_size == seeker.board_size
+                        && other.max_players == seeker.max_players
+                        && difference <= seeker.window(now)
+                        && difference <= other.window(now)
+                })
+                .map(|(j, other)| ((self.scores.rating(other.player) - rating).abs(), j))
+                .collect();
+            if candidates.len() + 1 < seeker.max_players {
+                i += 1;
+                continue;
+            }
+
+            candidates.sort();
+            let mut picked: Vec<usize> = candidates
+                .iter()
+                .take(seeker.max_players - 1)
+                .map(|&(_, j)| j)
+                .collect();
+            picked.push(i);
+            picked.sort_unstable_by(|a, b| b.cmp(a));
+            let (board_size, max_players) = (seeker.board_size, seeker.max_players);
+            let mut members: Vec<usize> = picked
+                .into_iter()
+                .map(|j| self.queue.remove(j).member)
+                .collect();
+            // The longest waiting player sits down first
+            members.reverse();
+
+            let table_id = self.open_table(board_size, max_players, 3);
+            for member in members {
+                if let Err(message) = self.seat(member, table_id, None) {
+                    self.send(member, &error(message));
+                }
+            }
+            i = 0;
+        }
+    }
+
+    fn open_table(&mut self, board_size: usize, max_players: usize, win_length: usize) -> u64 {
+        let id = self.next_game;
+        self.next_game += 1;
+        let game = Game::with_rules(
+            board_size,
+            max_players,
+            GameRules::with_win_length(win_length),
+        );
+        self.tables.insert(
+            id,
+            Table {
+                game,
+                result_sent: false,
+            },
+        );
+        id
+    }
+
+    fn seat(&mut self, id: usize, table_id: u64, symbol: Option<String>) -> Result<(), String> {
+        let (player_id, name) = self.logged_in(id)?;
+        let symbol_set = self.symbol_set;
+        let table = self
+            .tables
+            .get_mut(&table_id)
+            .ok_or_else(|| format!("There is no game #{}", table_id))?;
+        let symbol = pick_symbol(&table.game, symbol_set, symbol)?;
+        let player = Player::new(name, symbol, true, None).with_id(player_id);
+        table.game.add_player(&player).map_err(|e| e.to_string())?;
+        let full = table.game.players().len() == table.game.max_players();
+
+        self.queue.retain(|seeker| seeker.member != id);
+        self.seats.insert(player_id, table_id);
+        self.move_to(id, Some(table_id), false);
+        self.send(
+            id,
+            &ServerMessage::Joined {
+                game: table_id,
+                spectator: false,
+            },
+        );
+        self.welcome(id, player_id, symbol);
+        let text = format!("{} joined as {}", player.name(), symbol);
+        self.broadcast(table_id, &notice(text));
+        if full {
+            self.broadcast(table_id, &notice("All players are here, the game starts"));
+        }
+        self.after_change(table_id);
+        Ok(())
+    }
+
+    // Moves and resignations from a seated player of a game that has started
+    fn play(
+        &mut self,
+        id: usize,
+        action: impl FnOnce(&mut Game, PlayerId) -> Result<(), GameError>,
+    ) -> Result<(), String> {
+        let member = self.members.get(&id).ok_or("Unknown connection")?;
+        if member.spectating {
+            return Err("Spectators can't play".to_string());
+        }
+        let (Some(player), Some(table_id)) = (member.player, member.table) else {
+            return Err("Join a game first".to_string());
+        };
+        let table = self.tables.get_mut(&table_id).ok_or("Join a game first")?;
+        if table.game.players().len() < table.game.max_players() {
+            return Err("Waiting for more players to join".to_string());
+        }
+        action(&mut table.game, player).map_err(|e| e.to_string())?;
+        self.after_change(table_id);
+        Ok(())
+    }
+
+    fn waiting_at_table(&self, id: usize) -> bool {
+        let member = self.members.get(&id);
+        member.is_some_and(|m| !m.spectating)
+            && member
+                .and_then(|m| m.table)
+                .and_then(|table_id| self.tables.get(&table_id))
+                .is_some_and(|table| table.stage() == GameStage::Open)
+    }
+
+    // Gives up a seat before the game starts, or stops watching or waiting
+    // for a match. Games that have started are left by resigning
+    fn leave(&mut self, id: usize) -> Result<(), String> {
+        let member = self.members.get(&id).ok_or("Unknown connection")?;
+        let (player, name, table_id) = (member.player, member.name.clone(), member.table);
+        let queued = self.queue.iter().any(|seeker| seeker.member == id);
+        if queued {
+            self.queue.retain(|seeker| seeker.member != id);
+            self.send(id, &notice("Stopped looking for a match"));
+            return Ok(());
+        }
+        let Some(table_id) = table_id else {
+            return Err("You are not at a game".to_string());
+        };
+        let seated = player.filter(|&p| self.seats.get(&p) == Some(&table_id));
+        match seated {
+            Some(_) if !self.waiting_at_table(id) => {
+                return Err("The game has started, resign to leave it".to_string());
+            }
+            Some(player) => self.unseat(table_id, player),
+            None => {}
+        }
+        self.move_to(id, None, false);
+        self.send(id, &notice(format!("You left game #{}", table_id)));
+        if seated.is_some() && self.tables.contains_key(&table_id) {
+            let name = name.unwrap_or_else(|| "guest".to_string());
+            self.broadcast(table_id, &notice(format!("{} left", name)));
+            self.after_change(table_id);
+        }
+        Ok(())
+    }
+
+    // Takes the player out of a game that hasn't started. The game is set up
+    // again for the players left, or closed when there are none
+    fn unseat(&mut self, table_id: u64, player: PlayerId) {
+        self.seats.remove(&player);
+        let Some(table) = self.tables.get_mut(&table_id) else {
+            return;
+        };
+        let others: Vec<Player> = table
+            .game
+            .players()
+            .iter()
+            .filter(|p| p.id() != player)
+            .cloned()
+            .collect();
+        if others.is_empty() {
+            self.tables.remove(&table_id);
+            return;
+        }
+        let board = table.game.board();
+        let mut game = Game::with_dimensions(
+            board.rows(),
+            board.cols(),
+            table.game.max_players(),
+            table.game.rules().clone(),
+        );
+        for other in &others {
+            let _ = game.add_player(other);
+        }
+        table.game = game;
+    }
+
+    // Moves a member to another table, or none, and closes the game they
+    // leave behind if it is over and nobody is looking at it anymore
+    fn move_to(&mut self, id: usize, table: Option<u64>, spectating: bool) {
+        let Some(member) = self.members.get_mut(&id) else {
+            return;
+        };
+        let old = member.table;
+        member.table = table;
+        member.spectating = spectating;
+        if let Some(old) = old.filter(|&old| Some(old) != table) {
+            self.close_if_done(old);
+        }
+    }
+
+    fn close_if_done(&mut self, table_id: u64) {
+        let done = self.tables.get(&table_id).is_some_and(|t| t.game.is_over());
+        if done && !self.members.values().any(|m| m.table == Some(table_id)) {
+            self.tables.remove(&table_id);
+        }
+    }
+
+    // Chat goes to everyone at the same table, or everyone in the lobby
+    fn chat(&mut self, id: usize, text: String) {
+        let Some(member) = self.members.get(&id) else {
+            return;
+        };
+        let from = member.name.clone().unwrap_or_else(|| "guest".to_string());
+        let table = member.table;
+        let message = ServerMessage::Chat { from, text };
+        for member in self.members.values().filter(|m| m.table == table) {
+            member.outbox.send(&message);
+        }
+    }
+
+    fn disconnect(&mut self, id: usize) {
+        self.queue.retain(|seeker| seeker.member != id);
+        let Some(member) = self.members.remove(&id) else {
+            return;
+        };
+        if let (Some(name), Some(table_id), false) = (member.name, member.table, member.spectating)
+        {
+            self.broadcast(table_id, &notice(format!("{} lost the connection", name)));
+        }
+        if let Some(table_id) = member.table {
+            self.close_if_done(table_id);
+        }
+    }
+
+    fn after_change(&mut self, table_id: u64) {
+        let Some(table) = self.tables.get_mut(&table_id) else {
+            return;
+        };
+        let snapshot = Box::new(table.game.snapshot());
+        let finished = table.game.is_over() && !table.result_sent;
+        if finished {
+            table.result_sent = true;
+            self.scores.record_game(&table.game);
+            for player in table.game.players() {
+                self.seats.remove(&player.id());
+            }
+        }
+        let status = table.game.status();
+        let players: Vec<PlayerId> = table.game.players().iter().map(|p| p.id()).collect();
+        let watched = self
+            .members
+            .values()
+            .any(|m| m.table == Some(table_id) && m.spectating);
+        let evaluations = (watched && !table.game.is_over()).then(|| evaluate(&table.game));
+
+        self.broadcast(table_id, &ServerMessage::State { snapshot });
+        if let Some(evaluations) = evaluations {
+            let message = ServerMessage::Evaluation { evaluations };
+            for member in self
+                .members
+                .values()
+                .filter(|m| m.table == Some(table_id) && m.spectating)
+            {
+                member.outbox.send(&message);
+            }
+        }
+        if finished {
+            self.broadcast(table_id, &ServerMessage::Result { status });
+            self.close_if_done(table_id);
+            for player in players {
+                let text = format!("Your rating is now {}", self.scores.rating(player));
+                let seats: Vec<usize> = self
+                    .members
+                    .iter()
+                    .filter(|(_, m)| m.player == Some(player))
+                    .map(|(&id, _)| id)
+                    .collect();
+                for id in seats {
+                    self.send(id, &notice(text.clone()));
+                }
+            }
+        }
+    }
+
+    fn games(&self) -> Vec<GameInfo> {
+        self.tables
+            .iter()
+            .map(|(&id, table)| GameInfo {
+                id,
+                board_size: table.game.board().rows(),
+                board_cols: table.game.board().cols(),
+                win_length: table.game.board().win_length(),
+                max_players: table.game.max_players(),
+                players: table
+                    .game
+                    .players()
+                    .iter()
+                    .map(|p| p.name().to_string())
+                    .collect(),
+                spectators: self
+                    .members
+                    .values()
+                    .filter(|m| m.table == Some(id) && m.spectating)
+                    .count(),
+                stage: table.stage(),
+            })
+            .collect()
+    }
+
+    fn logged_in(&self, id: usize) -> Result<(PlayerId, String), String> {
+        match self.members.get(&id) {
+            Some(Member {
+                player: Some(player),
+                name: Some(name),
+                ..
+            }) => Ok((*player, name.clone())),
+            _ => Err("Log in first".to_string()),
+        }
+    }
+
+    // A logged in player who isn't playing a game right now
+    fn free_player(&self, id: usize) -> Result<(PlayerId, String), String> {
+        let (player, name) = self.logged_in(id)?;
+        if self.seated_table(player).is_some() {
+            return Err("Finish your current game first".to_string());
+        }
+        Ok((player, name))
+    }
+
+    // The unfinished game the player has a seat in
+    fn seated_table(&self, player: PlayerId) -> Option<u64> {
+        self.seats.get(&player).copied()
+    }
+
+    fn send(&mut self, id: usize, message: &ServerMessage) {
+        if let Some(member) = self.members.get(&id) {
+            member.outbox.send(message);
+        }
+    }
+
+    fn broadcast(&mut self, table_id: u64, message: &ServerMessage) {
+        for member in self.members.values().filter(|m| m.table == Some(table_id)) {
+            member.outbox.send(message);
+        }
+    }
+}
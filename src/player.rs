@@ -1,9 +1,10 @@
 use crate::ai::AI;
 use crate::game_board::GameBoard;
-use crate::types::{Difficulty, PlayerError, Symbol};
+use crate::types::{Difficulty, PlayerError, PlayerId, Symbol};
 
 #[derive(Debug, Clone)]
 pub struct Player {
+    id: PlayerId,
     pub name: String,
     symbol: Symbol,
     pub ai: Option<AI>,
@@ -25,7 +26,21 @@ impl Player {
             None
         };
 
-        Self { name, symbol, ai }
+        Self {
+            id: PlayerId::random(),
+            name,
+            symbol,
+            ai,
+        }
+    }
+
+    pub fn with_id(mut self, id: PlayerId) -> Self {
+        self.id = id;
+        self
+    }
+
+    pub fn id(&self) -> PlayerId {
+        self.id
     }
 
     pub fn load(_id: usize) -> Result<Self, PlayerError> {
@@ -41,6 +56,18 @@ impl Player {
         self.symbol
     }
 
+    // Players of swap openings change colors, their AI with them
+    pub fn set_symbol(&mut self, symbol: Symbol) {
+        self.symbol = symbol;
+        if let Some(ai) = &mut self.ai {
+            *ai = AI::new(ai.difficulty(), symbol);
+        }
+    }
+
+    pub fn difficulty(&self) -> Option<Difficulty> {
+        self.ai.as_ref().map(|ai| ai.difficulty())
+    }
+
     pub fn is_ai(&self) -> bool {
         self.ai.is_some()
     }
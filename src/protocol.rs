@@ -0,0 +1,172 @@
+use crate::snapshot::GameSnapshot;
+use crate::spectator::Evaluation;
+use crate::types::{GameStatus, OpeningChoice, PlayerId, Symbol, Token};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, Read, Write};
+
+// Longest line read as one message, longer ones end the connection
+pub const MAX_LINE: u64 = 1024 * 1024;
+
+// Messages are sent as one JSON object per line, tagged with "type"
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Join {
+        name: String,
+        #[serde(default)]
+        symbol: Option<String>,
+    },
+    // Take over a seat again after the connection dropped, with the token
+    // from the welcome
+    Rejoin {
+        player: PlayerId,
+        token: Token,
+    },
+    Move {
+        row: usize,
+        col: usize,
+        // Wild games only, the player's own symbol otherwise
+        #[serde(default)]
+        symbol: Option<Symbol>,
+    },
+    // Swap openings: answer the choice of colors when it is the player's
+    Choose {
+        choice: OpeningChoice,
+    },
+    Chat {
+        text: String,
+    },
+    Resign,
+    // Lobby only: pick a name, or come back as a known player to keep the
+    // rating, with the token from their first login
+    Login {
+        name: String,
+        #[serde(default)]
+        player: Option<PlayerId>,
+        #[serde(default)]
+        token: Option<Token>,
+    },
+    ListGames,
+    CreateGame {
+        #[serde(default = "default_board_size")]
+        board_size: usize,
+        #[serde(default = "default_max_players")]
+        max_players: usize,
+        #[serde(default)]
+        win_length: Option<usize>,
+    },
+    JoinGame {
+        game: u64,
+        #[serde(default)]
+        symbol: Option<String>,
+    },
+    // Watch a game without playing, the lobby needs to know which one
+    Spectate {
+        #[serde(default)]
+        game: Option<u64>,
+    },
+    // Give up a seat in a game that hasn't started, stop watching, or stop
+    // waiting for a match
+    Leave,
+    // Wait for opponents with a similar rating
+    FindMatch {
+        #[serde(default = "default_board_size")]
+        board_size: usize,
+        #[serde(default = "default_max_players")]
+        max_players: usize,
+    },
+}
+
+fn default_board_size() -> usize {
+    3
+}
+
+fn default_max_players() -> usize {
+    2
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GameStage {
+    Open,
+    InProgress,
+    Finished,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameInfo {
+    pub id: u64,
+    pub board_size: usize,
+    pub board_cols: usize,
+    pub win_length: usize,
+    pub max_players: usize,
+    pub players: Vec<String>,
+    pub spectators: usize,
+    pub stage: GameStage,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    // Only ever sent to the player it welcomes, the token is theirs alone
+    Welcome {
+        player: PlayerId,
+        symbol: Symbol,
+        token: Token,
+    },
+    State {
+        snapshot: Box<GameSnapshot>,
+    },
+    Chat {
+        from: String,
+        text: String,
+    },
+    Notice {
+        text: String,
+    },
+    Error {
+        message: String,
+    },
+    Result {
+        status: GameStatus,
+    },
+    // Like the welcome, only sent to the player the token belongs to
+    LoggedIn {
+        player: PlayerId,
+        rating: i32,
+        token: Token,
+    },
+    Games {
+        games: Vec<GameInfo>,
+    },
+    // Followed by a welcome for players, or the current state for spectators
+    Joined {
+        game: u64,
+        spectator: bool,
+    },
+    // Sent to spectators after every state
+    Evaluation {
+        evaluations: Vec<Evaluation>,
+    },
+}
+
+pub fn write_message<T: Serialize>(out: &mut impl Write, message: &T) -> io::Result<()> {
+    let line = serde_json::to_string(message)?;
+    writeln!(out, "{}", line)?;
+    out.flush()
+}
+
+// None once the other side has closed the connection
+pub fn read_message<T: DeserializeOwned>(input: &mut impl BufRead) -> io::Result<Option<T>> {
+    let mut line = String::new();
+    if input.take(MAX_LINE).read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    if line.len() as u64 == MAX_LINE && !line.ends_with('\n') {
+        return Err(io::Error::other("message too long"));
+    }
+    serde_json::from_str(&line)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
@@ -0,0 +1,373 @@
+use crate::cube::CubeGame;
+use crate::game::{Game, OpeningStage};
+use crate::game_board::GameBoard;
+use crate::theme::{CellEmphasis, Theme};
+use crate::types::{OpeningChoice, Symbol};
+use crate::ultimate::{UltimateBoard, UltimateGame, SIZE, SUB_SIZE};
+use std::time::Duration;
+use unicode_width::UnicodeWidthStr;
+
+pub fn display_width(text: &str) -> usize {
+    UnicodeWidthStr::width(text)
+}
+
+pub fn pad_center(text: &str, width: usize) -> String {
+    let padding = width.saturating_sub(display_width(text));
+    let left = padding / 2;
+    format!("{}{}{}", " ".repeat(left), text, " ".repeat(padding - left))
+}
+
+pub fn pad_left(text: &str, width: usize) -> String {
+    let padding = width.saturating_sub(display_width(text));
+    format!("{}{}", " ".repeat(padding), text)
+}
+
+// "m:ss", with tenths of a second once under ten seconds
+pub fn format_clock(time: Duration) -> String {
+    let secs = time.as_secs();
+    if secs < 10 {
+        format!("0:0{}.{}", secs, time.subsec_millis() / 100)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+// Remaining time of every player, None when the game is untimed
+pub fn clock_summary(game: &Game) -> Option<String> {
+    let clocks: Vec<String> = game
+        .players()
+        .iter()
+        .filter_map(|p| {
+            let left = game.time_left(p.id())?;
+            Some(format!("{} {}", p.name(), format_clock(left)))
+        })
+        .collect();
+    if clocks.is_empty() {
+        None
+    } else {
+        Some(clocks.join("  "))
+    }
+}
+
+// What the swap opening waits for, None once it is over
+pub fn opening_summary(game: &Game) -> Option<String> {
+    let name = |id| game.player(id).map_or("?", |p| p.name()).to_string();
+    match game.opening_stage() {
+        OpeningStage::Placing { player, symbol } => Some(format!(
+            "Opening: {} places a {} stone",
+            name(player),
+            symbol
+        )),
+        OpeningStage::Choosing { player, options } => {
+            let words: Vec<&str> = options
+                .iter()
+                .map(|option| match option {
+                    OpeningChoice::Black => "black",
+                    OpeningChoice::White => "white",
+                    OpeningChoice::PlaceTwo => "two",
+                    OpeningChoice::Swap => "swap",
+                    OpeningChoice::Keep => "keep",
+                })
+                .collect();
+            Some(format!(
+                "Opening: {} chooses {}",
+                name(player),
+                words.join(" / ")
+            ))
+        }
+        OpeningStage::Done => None,
+    }
+}
+
+// What to highlight when drawing a board in color
+#[derive(Debug, Clone, Default)]
+pub struct BoardMarks {
+    pub players: Vec<Symbol>,
+    pub last_move: Option<(usize, usize)>,
+    pub winning_line: Vec<(usize, usize)>,
+    // Disappearing pieces: the mark that goes with the current player's next move
+    pub vanishing: Option<(usize, usize)>,
+}
+
+impl BoardMarks {
+    pub fn for_game(game: &Game) -> Self {
+        Self {
+            players: game.players().iter().map(|p| p.symbol()).collect(),
+            last_move: game.last_move().map(|m| m.position),
+            // Under misère the completed line lost the game, nothing to show off
+            winning_line: game
+                .winning_line()
+                .filter(|_| !game.rules().misere)
+                .unwrap_or_default(),
+            vanishing: game.next_to_vanish(),
+        }
+    }
+
+    pub fn for_ultimate(game: &UltimateGame) -> Self {
+        Self {
+            players: game.players().iter().map(|p| p.symbol()).collect(),
+            last_move: game.last_move().map(|m| m.position),
+            winning_line: game.board().winning_cells(),
+            vanishing: None,
+        }
+    }
+
+    // Marks for one layer of a 3D game, in that layer's (row, col)
+    pub fn for_cube_layer(game: &CubeGame, layer: usize) -> Self {
+        let in_layer = |pos: (usize, usize, usize)| (pos.0 == layer).then_some((pos.1, pos.2));
+        Self {
+            players: game.players().iter().map(|p| p.symbol()).collect(),
+            last_move: game.last_move().and_then(|m| in_layer(m.position)),
+            winning_line: game
+                .board()
+                .winning_line()
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|&pos| in_layer(pos))
+                .collect(),
+            vanishing: None,
+        }
+    }
+
+    pub fn player_idx(&self, symbol: Symbol) -> Option<usize> {
+        self.players.iter().position(|&s| s == symbol)
+    }
+
+    pub fn emphasis(&self, pos: (usize, usize)) -> CellEmphasis {
+        if self.winning_line.contains(&pos) {
+            CellEmphasis::WinningLine
+        } else if self.last_move == Some(pos) {
+            CellEmphasis::LastMove
+        } else if self.vanishing == Some(pos) {
+            CellEmphasis::Vanishing
+        } else {
+            CellEmphasis::None
+        }
+    }
+}
+
+// Column widths for drawing a board as a grid:
+//
+//     0   1   2
+//   -------------
+// 0 | X |   | O |
+//   -------------
+#[derive(Debug, Clone)]
+pub struct BoardLayout {
+    rows: usize,
+    cols: usize,
+    label_width: usize,
+    symbol_width: usize,
+}
+
+impl BoardLayout {
+    pub fn new(size: usize, symbol_width: usize) -> Self {
+        Self::with_dimensions(size, size, symbol_width)
+    }
+
+    pub fn with_dimensions(rows: usize, cols: usize, symbol_width: usize) -> Self {
+        let label_width = rows.max(cols).saturating_sub(1).to_string().len();
+        Self {
+            rows,
+            cols,
+            label_width,
+            symbol_width: symbol_width.max(label_width).max(1),
+        }
+    }
+
+    // Widest of the symbols already on the board and the given minimum
+    pub fn for_board(board: &GameBoard, min_symbol_width: usize) -> Self {
+        let symbol_width = board
+            .get_all_symbols()
+            .iter()
+            .map(Symbol::display_width)
+            .fold(min_symbol_width, usize::max);
+        Self::with_dimensions(board.rows(), board.cols(), symbol_width)
+    }
+
+    pub fn cell_width(&self) -> usize {
+        self.symbol_width + 2
+    }
+
+    pub fn column_header(&self) -> String {
+        let mut header = " ".repeat(self.label_width + 2);
+        for col in 0..self.cols {
+            header.push_str(&pad_center(&col.to_string(), self.cell_width()));
+            header.push(' ');
+        }
+        header.trim_end().to_string()
+    }
+
+    pub fn border(&self) -> String {
+        format!(
+            "{}{}",
+            " ".repeat(self.label_width + 1),
+            "-".repeat((self.cell_width() + 1) * self.cols + 1)
+        )
+    }
+
+    pub fn row_label(&self, row: usize) -> String {
+        format!("{}|", self.row_number(row))
+    }
+
+    // Row label without the leading grid separator
+    pub fn row_number(&self, row: usize) -> String {
+        format!("{} ", pad_left(&row.to_string(), self.label_width))
+    }
+
+    // Cell content without the separators, always cell_width() columns wide
+    pub fn cell(&self, symbol: Option<Symbol>) -> String {
+        match symbol {
+            Some(symbol) => pad_center(&symbol.to_string(), self.cell_width()),
+            None => " ".repeat(self.cell_width()),
+        }
+    }
+
+    // A cell of `board`, blocked ones filled with '#'
+    pub fn board_cell(&self, board: &GameBoard, pos: (usize, usize)) -> String {
+        if board.is_blocked(pos) {
+            "#".repeat(self.cell_width())
+        } else {
+            self.cell(board.get_cell(pos))
+        }
+    }
+
+    pub fn styled_cell(
+        &self,
+        board: &GameBoard,
+        pos: (usize, usize),
+        theme: &Theme,
+        marks: &BoardMarks,
+    ) -> String {
+        if board.is_blocked(pos) {
+            return theme.paint_grid(&self.board_cell(board, pos));
+        }
+        let symbol = board.get_cell(pos);
+        theme.paint_cell(
+            &self.cell(symbol),
+            symbol.and_then(|s| marks.player_idx(s)),
+            marks.emphasis(pos),
+        )
+    }
+
+    pub fn lines(&self, board: &GameBoard) -> Vec<String> {
+        self.styled_lines(board, &Theme::plain(), &BoardMarks::default())
+    }
+
+    pub fn styled_lines(
+        &self,
+        board: &GameBoard,
+        theme: &Theme,
+        marks: &BoardMarks,
+    ) -> Vec<String> {
+        let border = theme.paint_grid(&self.border());
+        let separator = theme.paint_grid("|");
+
+        let mut lines = vec![self.column_header(), border.clone()];
+        for row in 0..self.rows {
+            let mut line = format!("{}{}", self.row_number(row), separator);
+            for col in 0..self.cols {
+                line.push_str(&self.styled_cell(board, (row, col), theme, marks));
+                line.push_str(&separator);
+            }
+            lines.push(line);
+            lines.push(border.clone());
+        }
+        lines
+    }
+}
+
+// Ultimate tic-tac-toe as one 9x9 grid. '#' and '=' mark the sub-board
+// borders, '|' and '-' the cells inside, and '.' the cells the next move
+// may go to:
+//
+//     0   1   2   3 ...
+//   #===========#====
+// 0 # X | . | . # ...
+//   #---+---+---#
+pub fn ultimate_lines(
+    board: &UltimateBoard,
+    symbol_width: usize,
+    theme: &Theme,
+    marks: &BoardMarks,
+) -> Vec<String> {
+    let symbol_width = board
+        .get_all_symbols()
+        .iter()
+        .map(Symbol::display_width)
+        .fold(symbol_width.max(1), usize::max);
+    let cell_width = symbol_width + 2;
+    let label_width = (SIZE - 1).to_string().len();
+    let indent = " ".repeat(label_width + 1);
+
+    let mut header = " ".repeat(label_width + 2);
+    for col in 0..SIZE {
+        header.push_str(&pad_center(&col.to_string(), cell_width));
+        header.push(' ');
+    }
+    let band = |fill: &str, joint: &str| {
+        let cells = vec![fill.repeat(cell_width); SUB_SIZE].join(joint);
+        format!("{}#{}#", indent, vec![cells; SUB_SIZE].join("#"))
+    };
+    let outer = theme.paint_grid(&band("=", "="));
+    let inner = theme.paint_grid(&band("-", "+"));
+
+    let legal = board.legal_moves();
+    let mut lines = vec![header.trim_end().to_string(), outer.clone()];
+    for row in 0..SIZE {
+        let mut line = format!("{} ", pad_left(&row.to_string(), label_width));
+        for col in 0..SIZE {
+            let separator = if col % SUB_SIZE == 0 { "#" } else { "|" };
+            line.push_str(&theme.paint_grid(separator));
+            let symbol = board.get_cell((row, col));
+            let text = match symbol {
+                Some(symbol) => pad_center(&symbol.to_string(), cell_width),
+                None if legal.contains(&(row, col)) => pad_center(".", cell_width),
+                None => " ".repeat(cell_width),
+            };
+            line.push_str(&theme.paint_cell(
+                &text,
+                symbol.and_then(|s| marks.player_idx(s)),
+                marks.emphasis((row, col)),
+            ));
+        }
+        line.push_str(&theme.paint_grid("#"));
+        lines.push(line);
+        if row % SUB_SIZE == SUB_SIZE - 1 {
+            lines.push(outer.clone());
+        } else {
+            lines.push(inner.clone());
+        }
+    }
+    lines
+}
+
+// A 3D board drawn one layer after the other, each as a regular grid under
+// a "Layer N" heading
+pub fn cube_lines(game: &CubeGame, symbol_width: usize, theme: &Theme) -> Vec<String> {
+    let board = game.board();
+    let symbol_width = board
+        .get_all_symbols()
+        .iter()
+        .map(Symbol::display_width)
+        .fold(symbol_width, usize::max);
+    let layout = BoardLayout::new(board.size(), symbol_width);
+
+    let mut lines = Vec::new();
+    for layer in 0..board.size() {
+        let mut plane = GameBoard::new(board.size());
+        for row in 0..board.size() {
+            for col in 0..board.size() {
+                if let Some(symbol) = board.get_cell((layer, row, col)) {
+                    plane.apply_move((row, col), symbol).unwrap();
+                }
+            }
+        }
+        if layer > 0 {
+            lines.push(String::new());
+        }
+        lines.push(format!("Layer {}", layer));
+        lines.extend(layout.styled_lines(&plane, theme, &BoardMarks::for_cube_layer(game, layer)));
+    }
+    lines
+}
@@ -0,0 +1,191 @@
+use crate::types::Symbol;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum DeadPositionCheck {
+    // Play until a line is completed or the board is full
+    #[default]
+    Off,
+    // Draw once no remaining player has a line free of other symbols
+    OpenLines,
+    // Also require that a player has enough turns left to fill such a line
+    MovesLeft,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum FlagFall {
+    // The player is out of the game, as if they had resigned
+    #[default]
+    Lose,
+    // The player loses the move and plays on with whatever time is left
+    SkipTurn,
+}
+
+// How a line of the win length counts, for five in a row
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum GomokuRule {
+    // The win length or longer wins
+    #[default]
+    Freestyle,
+    // Exactly the win length, longer lines don't win
+    Standard,
+    // Exactly for the first player, who may not make overlines, two fours
+    // or two open threes at once. The second player wins with five or more
+    Renju,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum Opening {
+    #[default]
+    Free,
+    // The opener places two black stones and a white one, then the other
+    // player picks a color
+    Swap,
+    // Like swap, but the other player may instead place a white and a black
+    // stone and leave the choice to the opener
+    Swap2,
+    // Pie rule: after the first move the other player may take it over
+    Pie,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimeControl {
+    pub initial: Duration,
+    #[serde(default)]
+    pub increment: Duration,
+    // Time at the start of each move that is not taken off the clock
+    #[serde(default)]
+    pub delay: Duration,
+    #[serde(default)]
+    pub per_move: Option<Duration>,
+    #[serde(default)]
+    pub on_flag: FlagFall,
+}
+
+impl TimeControl {
+    pub fn new(initial: Duration, increment: Duration) -> Self {
+        Self {
+            initial,
+            increment,
+            delay: Duration::ZERO,
+            per_move: None,
+            on_flag: FlagFall::default(),
+        }
+    }
+
+    // Whether a turn can ever have time on the clock. Otherwise every flag
+    // falls the moment the turn starts, and skipped turns go round forever
+    pub fn is_playable(&self) -> bool {
+        let gains_time = !(self.initial.is_zero() && self.increment.is_zero());
+        let has_delay = !self.delay.is_zero();
+        let move_limit = self.per_move.is_none_or(|limit| !limit.is_zero());
+        (gains_time || has_delay) && move_limit
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameRules {
+    pub win_length: usize,
+    #[serde(default)]
+    pub dead_position: DeadPositionCheck,
+    #[serde(default)]
+    pub time_control: Option<TimeControl>,
+    // Completing a line loses: the player is out and the others play on
+    #[serde(default)]
+    pub misere: bool,
+    // Wild tic-tac-toe: symbols every player may place, and whoever completes
+    // a line wins whatever its symbol. Empty for one symbol per player
+    #[serde(default)]
+    pub wild: Vec<Symbol>,
+    // Disappearing pieces: each player keeps at most this many marks on the
+    // board, placing another one removes their oldest
+    #[serde(default)]
+    pub max_marks: Option<usize>,
+    // Connect6: stones each turn places after the single first stone of the
+    // game, None for one
+    #[serde(default)]
+    pub stones_per_turn: Option<usize>,
+    #[serde(default)]
+    pub gomoku: GomokuRule,
+    // Swap openings need two players
+    #[serde(default)]
+    pub opening: Opening,
+    // Stones already on the board before the first move, one list of cells
+    // for each player in the order they join
+    #[serde(default)]
+    pub opening_stones: Vec<Vec<(usize, usize)>>,
+    // Cells outside the board's shape, which no one may play and which
+    // break every line through them
+    #[serde(default)]
+    pub blocked: Vec<(usize, usize)>,
+}
+
+impl Default for GameRules {
+    fn default() -> Self {
+        Self {
+            win_length: 3,
+            dead_position: DeadPositionCheck::default(),
+            time_control: None,
+            misere: false,
+            wild: Vec::new(),
+            max_marks: None,
+            stones_per_turn: None,
+            gomoku: GomokuRule::default(),
+            opening: Opening::default(),
+            opening_stones: Vec::new(),
+            blocked: Vec::new(),
+        }
+    }
+}
+
+impl GameRules {
+    pub fn is_wild(&self) -> bool {
+        !self.wild.is_empty()
+    }
+
+    pub fn with_win_length(win_length: usize) -> Self {
+        Self {
+            win_length,
+            ..Self::default()
+        }
+    }
+
+    // Whether every blocked cell and opening stone is on the board and on a
+    // cell of its own
+    pub fn cells_fit(&self, rows: usize, cols: usize) -> bool {
+        let cells: Vec<(usize, usize)> = self
+            .blocked
+            .iter()
+            .chain(self.opening_stones.iter().flatten())
+            .copied()
+            .collect();
+        cells
+            .iter()
+            .enumerate()
+            .all(|(i, &(row, col))| row < rows && col < cols && !cells[..i].contains(&(row, col)))
+    }
+}
+
+// A balanced opening for two players: `pairs` random cells for the first
+// player and their mirror images through the center for the second, none
+// of them on a blocked cell
+pub fn mirrored_stones(
+    rows: usize,
+    cols: usize,
+    pairs: usize,
+    blocked: &[(usize, usize)],
+) -> Vec<Vec<(usize, usize)>> {
+    let (last_row, last_col) = (rows.saturating_sub(1), cols.saturating_sub(1));
+    let mirror = move |(row, col): (usize, usize)| (last_row - row, last_col - col);
+    let mut cells: Vec<(usize, usize)> = (0..rows)
+        .flat_map(|row| (0..cols).map(move |col| (row, col)))
+        .filter(|&cell| cell < mirror(cell))
+        .filter(|&cell| !blocked.contains(&cell) && !blocked.contains(&mirror(cell)))
+        .collect();
+    cells.shuffle(&mut rand::thread_rng());
+    cells.truncate(pairs);
+    let mirrored = cells.iter().map(|&cell| mirror(cell)).collect();
+    vec![cells, mirrored]
+}
@@ -0,0 +1,406 @@
+use crate::ai::AI;
+use crate::game::Game;
+use crate::score_board::ScoreBoard;
+use crate::types::{GameError, GameStatus, Move, OpeningChoice, PlayerId, Symbol};
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+// Actions the game refuses in a row before the provider forfeits
+const MAX_REJECTED_ACTIONS: usize = 3;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlayerAction {
+    Move((usize, usize)),
+    // Wild games: a cell and the symbol to put there
+    Place((usize, usize), Symbol),
+    // Swap openings: answer the choice of colors
+    ChooseOpening(OpeningChoice),
+    Undo,
+    Redo,
+    OfferDraw,
+    Resign,
+    Quit,
+    // The player's clock ran out while waiting for their input
+    TimeUp,
+}
+
+pub struct TurnContext<'a> {
+    pub player: PlayerId,
+    pub time_limit: Option<Duration>,
+    // The player's clock, when the game has a time control
+    pub time_left: Option<Duration>,
+    pub scores: &'a ScoreBoard,
+}
+
+// Source of moves for one or more players: a human at the keyboard, the AI,
+// a network connection, or a script
+pub trait MoveProvider {
+    fn next_action(&mut self, game: &Game, ctx: &TurnContext) -> PlayerAction;
+
+    // `ctx.player` is the player asked to accept the current offer
+    fn respond_to_draw(&mut self, _game: &Game, _ctx: &TurnContext) -> bool {
+        false
+    }
+
+    // Interactive providers get the turn back after an undo
+    fn is_interactive(&self) -> bool {
+        false
+    }
+}
+
+// Lets several players share one provider, e.g. two humans on one terminal
+impl<P: MoveProvider> MoveProvider for Rc<RefCell<P>> {
+    fn next_action(&mut self, game: &Game, ctx: &TurnContext) -> PlayerAction {
+        self.borrow_mut().next_action(game, ctx)
+    }
+
+    fn respond_to_draw(&mut self, game: &Game, ctx: &TurnContext) -> bool {
+        self.borrow_mut().respond_to_draw(game, ctx)
+    }
+
+    fn is_interactive(&self) -> bool {
+        self.borrow().is_interactive()
+    }
+}
+
+pub struct AiProvider {
+    ai: AI,
+    // Stones left of the turn it planned, and the history length at which the
+    // next one is due
+    plan: VecDeque<(usize, usize)>,
+    plan_at: usize,
+}
+
+impl AiProvider {
+    pub fn new(ai: AI) -> Self {
+        Self {
+            ai,
+            plan: VecDeque::new(),
+            plan_at: 0,
+        }
+    }
+
+    fn planned_stone(&mut self, game: &Game) -> Option<(usize, usize)> {
+        if self.plan_at != game.history().len() {
+            self.plan.clear();
+        }
+        if self.plan.is_empty() {
+            self.plan = game.ai_stones(&self.ai)?.into();
+        }
+        let pos = self.plan.pop_front()?;
+        self.plan_at = game.history().len() + 1;
+        Some(pos)
+    }
+}
+
+impl MoveProvider for AiProvider {
+    fn next_action(&mut self, game: &Game, ctx: &TurnContext) -> PlayerAction {
+        if let Some(choice) = game.ai_opening_choice(&self.ai) {
+            return PlayerAction::ChooseOpening(choice);
+        }
+        if let Some(pos) = self.planned_stone(game) {
+            return PlayerAction::Move(pos);
+        }
+        match game.ai_choice(&self.ai, ctx.time_left) {
+            Some((pos, symbol)) if game.rules().is_wild() => PlayerAction::Place(pos, symbol),
+            Some((pos, _)) => PlayerAction::Move(pos),
+            None => PlayerAction::Quit,
+        }
+    }
+
+    fn respond_to_draw(&mut self, game: &Game, _ctx: &TurnContext) -> bool {
+        self.ai.accepts_draw(game.board())
+    }
+}
+
+// Plays a fixed list of actions, then quits
+#[derive(Debug, Clone, Default)]
+pub struct ScriptedProvider {
+    actions: VecDeque<PlayerAction>,
+    accept_draws: bool,
+}
+
+impl ScriptedProvider {
+    pub fn new(moves: &[(usize, usize)]) -> Self {
+        Self::from_actions(moves.iter().map(|&pos| PlayerAction::Move(pos)).collect())
+    }
+
+    pub fn from_actions(actions: Vec<PlayerAction>) -> Self {
+        Self {
+            actions: actions.into(),
+            accept_draws: false,
+        }
+    }
+
+    pub fn accepting_draws(mut self) -> Self {
+        self.accept_draws = true;
+        self
+    }
+}
+
+impl MoveProvider for ScriptedProvider {
+    fn next_action(&mut self, _game: &Game, _ctx: &TurnContext) -> PlayerAction {
+        self.actions.pop_front().unwrap_or(PlayerAction::Quit)
+    }
+
+    fn respond_to_draw(&mut self, _game: &Game, _ctx: &TurnContext) -> bool {
+        self.accept_draws
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StepOutcome {
+    Played(Move),
+    OpeningChosen {
+        player: PlayerId,
+        choice: OpeningChoice,
+    },
+    Rejected {
+        player: PlayerId,
+        error: GameError,
+    },
+    Undone(usize),
+    Redone(usize),
+    DrawOffered {
+        player: PlayerId,
+        accepted: bool,
+    },
+    Resigned(PlayerId),
+    TimedOut(PlayerId),
+    Quit(PlayerId),
+}
+
+#[derive(Debug, Clone)]
+pub struct RunResult {
+    pub status: GameStatus,
+    pub winner: Option<PlayerId>,
+    pub moves: Vec<Move>,
+    pub timed_out: Vec<PlayerId>,
+    pub quit: bool,
+    pub duration: Duration,
+}
+
+// Drives a game by asking each player's provider for actions
+#[derive(Default)]
+pub struct GameRunner {
+    providers: HashMap<PlayerId, Box<dyn MoveProvider>>,
+    move_time_limit: Option<Duration>,
+    scores: ScoreBoard,
+    // Refused moves in a row, per player
+    rejected: HashMap<PlayerId, usize>,
+}
+
+impl GameRunner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // AI players get an AiProvider, everyone else a clone of `humans`
+    pub fn for_game<P>(game: &Game, humans: P) -> Self
+    where
+        P: MoveProvider + Clone + 'static,
+    {
+        let mut runner = Self::new();
+        for player in game.players() {
+            match &player.ai {
+                Some(ai) => runner.set_provider(player.id(), AiProvider::new(ai.clone())),
+                None => runner.set_provider(player.id(), humans.clone()),
+            }
+        }
+        runner
+    }
+
+    pub fn set_provider(&mut self, player: PlayerId, provider: impl MoveProvider + 'static) {
+        self.providers.insert(player, Box::new(provider));
+    }
+
+    // Moves that take longer forfeit the game for that player. Providers get
+    // the limit to budget their time, it cannot interrupt a blocking provider
+    pub fn set_move_time_limit(&mut self, limit: Option<Duration>) {
+        self.move_time_limit = limit;
+    }
+
+    pub fn scores(&self) -> &ScoreBoard {
+        &self.scores
+    }
+
+    pub fn step(&mut self, game: &mut Game) -> Result<StepOutcome, GameError> {
+        if game.is_over() {
+            return Err(GameError::GameOver);
+        }
+        let player = game.current_player().ok_or(GameError::InvalidMove)?.id();
+        let provider = self
+            .providers
+            .get_mut(&player)
+            .ok_or(GameError::UnknownPlayer)?;
+
+        let ctx = TurnContext {
+            player,
+            time_limit: self.move_time_limit,
+            time_left: game.time_left(player),
+            scores: &self.scores,
+        };
+        let started = Instant::now();
+        let action = provider.next_action(game, &ctx);
+
+        if self
+            .move_time_limit
+            .is_some_and(|limit| started.elapsed() > limit)
+            && action != PlayerAction::Quit
+        {
+            game.resign_player(player)?;
+            return Ok(self.finish_step(game, StepOutcome::TimedOut(player)));
+        }
+        // The game's own clock, which may only cost the player this turn
+        if action != PlayerAction::Quit && game.check_clock() {
+            return Ok(self.finish_step(game, StepOutcome::TimedOut(player)));
+        }
+
+        let outcome = match action {
+            PlayerAction::Move(pos) => game
+                .make_move_as(player, pos)
+                .map(|()| StepOutcome::Played(game.last_move().expect("move was just played"))),
+            PlayerAction::Place(pos, symbol) => game
+                .make_move_with_as(player, pos, symbol)
+                .map(|()| StepOutcome::Played(game.last_move().expect("move was just played"))),
+            PlayerAction::ChooseOpening(choice) => game
+                .choose_opening(player, choice)
+                .map(|()| StepOutcome::OpeningChosen { player, choice }),
+            PlayerAction::Undo => self.undo_own_move(game, player).map(StepOutcome::Undone),
+            PlayerAction::Redo => self.redo_to_interactive(game).map(StepOutcome::Redone),
+            PlayerAction::OfferDraw => game.offer_draw_as(player).map(|()| {
+                let accepted = self.resolve_draw_offer(game);
+                StepOutcome::DrawOffered { player, accepted }
+            }),
+            PlayerAction::Resign => game
+                .resign_player(player)
+                .map(|()| StepOutcome::Resigned(player)),
+            PlayerAction::Quit => Ok(StepOutcome::Quit(player)),
+            // Only reached if the clock hasn't quite run out after all
+            PlayerAction::TimeUp => Err(GameError::OutOfTime),
+        };
+        let outcome = self.count_rejections(game, player, outcome)?;
+        Ok(self.finish_step(game, outcome))
+    }
+
+    // A refused action leaves the turn with the same player. Providers that
+    // keep sending actions the game refuses forfeit
+    fn count_rejections(
+        &mut self,
+        game: &mut Game,
+        player: PlayerId,
+        outcome: Result<StepOutcome, GameError>,
+    ) -> Result<StepOutcome, GameError> {
+        match outcome {
+            Ok(outcome) => {
+                self.rejected.remove(&player);
+                Ok(outcome)
+            }
+            Err(error) => {
+                let rejected = self.rejected.entry(player).or_default();
+                *rejected += 1;
+                if *rejected < MAX_REJECTED_ACTIONS {
+                    Ok(StepOutcome::Rejected { player, error })
+                } else {
+                    self.rejected.remove(&player);
+                    game.resign_player(player)
+                        .map(|()| StepOutcome::Resigned(player))
+                }
+            }
+        }
+    }
+
+    pub fn run(&mut self, game: &mut Game) -> Result<RunResult, GameError> {
+        let started = Instant::now();
+        let mut timed_out = Vec::new();
+        let mut quit = false;
+
+        while !game.is_over() {
+            match self.step(game)? {
+                StepOutcome::TimedOut(player) => timed_out.push(player),
+                StepOutcome::Quit(_) => {
+                    quit = true;
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        Ok(RunResult {
+            status: game.status(),
+            winner: game.winner().map(|w| w.id()),
+            moves: game.history().to_vec(),
+            timed_out,
+            quit,
+            duration: started.elapsed(),
+        })
+    }
+
+    fn finish_step(&mut self, game: &Game, outcome: StepOutcome) -> StepOutcome {
+        if game.is_over() {
+            self.scores.record_game(game);
+        }
+        outcome
+    }
+
+    fn current_is_interactive(&self, game: &Game) -> bool {
+        game.current_player()
+            .and_then(|p| self.providers.get(&p.id()))
+            .is_some_and(|provider| provider.is_interactive())
+    }
+
+    // Players take back their own last move, along with the moves of
+    // non-interactive players since. Moves of the other people at the
+    // keyboard are theirs to take back
+    fn undo_own_move(&self, game: &mut Game, player: PlayerId) -> Result<usize, GameError> {
+        let is_interactive = |id| self.providers.get(&id).is_some_and(|p| p.is_interactive());
+        let history = game.history();
+        let count = match history
+            .iter()
+            .rposition(|mv| mv.player == player || is_interactive(mv.player))
+        {
+            Some(idx) if history[idx].player == player => history.len() - idx,
+            _ => return Err(GameError::NothingToUndo),
+        };
+        for _ in 0..count {
+            game.undo()?;
+        }
+        Ok(count)
+    }
+
+    fn redo_to_interactive(&mut self, game: &mut Game) -> Result<usize, GameError> {
+        game.redo()?;
+        let mut count = 1;
+        while !self.current_is_interactive(game) && game.can_redo() {
+            game.redo()?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    fn resolve_draw_offer(&mut self, game: &mut Game) -> bool {
+        for player in game.draw_offer_pending_for() {
+            let ctx = TurnContext {
+                player,
+                time_limit: self.move_time_limit,
+                time_left: game.time_left(player),
+                scores: &self.scores,
+            };
+            let accepted = match self.providers.get_mut(&player) {
+                Some(provider) => provider.respond_to_draw(game, &ctx),
+                None => false,
+            };
+            let answer = if accepted {
+                game.accept_draw(player)
+            } else {
+                game.decline_draw(player)
+            };
+            if answer.is_err() || !accepted {
+                return false;
+            }
+        }
+        game.is_draw_agreed()
+    }
+}
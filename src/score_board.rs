@@ -1,12 +1,30 @@
+use crate::game::Game;
 use crate::player::Player;
-use crate::types::GameResult;
+use crate::types::{GameResult, GameStatus, PlayerId};
+use std::cmp::Ordering;
 use std::collections::HashMap;
 
-#[derive(Debug, Default)]
+// Elo ratings, starting where chess clubs usually do
+pub const INITIAL_RATING: f64 = 1200.0;
+const RATING_K: f64 = 32.0;
+
+#[derive(Debug)]
 pub struct PlayerStats {
     wins: u32,
     losses: u32,
     draws: u32,
+    rating: f64,
+}
+
+impl Default for PlayerStats {
+    fn default() -> Self {
+        Self {
+            wins: 0,
+            losses: 0,
+            draws: 0,
+            rating: INITIAL_RATING,
+        }
+    }
 }
 
 impl PlayerStats {
@@ -31,14 +49,38 @@ impl PlayerStats {
         }
     }
 
+    pub fn wins(&self) -> u32 {
+        self.wins
+    }
+
+    pub fn losses(&self) -> u32 {
+        self.losses
+    }
+
+    pub fn draws(&self) -> u32 {
+        self.draws
+    }
+
     pub fn total_games(&self) -> u32 {
         self.wins + self.losses + self.draws
     }
+
+    pub fn rating(&self) -> i32 {
+        self.rating.round() as i32
+    }
+}
+
+fn rank(result: GameResult) -> u8 {
+    match result {
+        GameResult::Win => 2,
+        GameResult::Draw => 1,
+        GameResult::Loss => 0,
+    }
 }
 
 #[derive(Debug, Default)]
 pub struct ScoreBoard {
-    stats: HashMap<String, PlayerStats>,
+    stats: HashMap<PlayerId, PlayerStats>,
 }
 
 impl ScoreBoard {
@@ -48,12 +90,80 @@ impl ScoreBoard {
 
     pub fn record_result(&mut self, player: &Player, result: GameResult) {
         self.stats
-            .entry(player.name().to_string())
+            .entry(player.id())
             .or_default()
             .add_result(result);
     }
 
+    // Resigned players lose even when the remaining players draw
+    pub fn record_game(&mut self, game: &Game) {
+        let status = game.status();
+        let winner = game.winner().map(|w| w.id());
+        let mut results = Vec::new();
+        for player in game.players() {
+            let result = match &status {
+                GameStatus::InProgress => return,
+                GameStatus::Win(..) if winner == Some(player.id()) => GameResult::Win,
+                GameStatus::Win(..) => GameResult::Loss,
+                GameStatus::Draw(_) if game.has_resigned(player.id()) => GameResult::Loss,
+                GameStatus::Draw(_) => GameResult::Draw,
+            };
+            self.record_result(player, result);
+            results.push((player.id(), result));
+        }
+        self.update_ratings(&results);
+    }
+
+    // Multiplayer games count as a match between every pair of players,
+    // each pair weighted so a game moves a rating as much as a duel does
+    fn update_ratings(&mut self, results: &[(PlayerId, GameResult)]) {
+        if results.len() < 2 {
+            return;
+        }
+        let k = RATING_K / (results.len() - 1) as f64;
+        let ratings: Vec<f64> = results.iter().map(|(id, _)| self.rating_of(*id)).collect();
+        for (i, &(id, result)) in results.iter().enumerate() {
+            let mut change = 0.0;
+            for (j, &(_, other)) in results.iter().enumerate() {
+                if i == j {
+                    continue;
+                }
+                let score = match rank(result).cmp(&rank(other)) {
+                    Ordering::Greater => 1.0,
+                    Ordering::Equal => 0.5,
+                    Ordering::Less => 0.0,
+                };
+                let expected = 1.0 / (1.0 + 10f64.powf((ratings[j] - ratings[i]) / 400.0));
+                change += k * (score - expected);
+            }
+            self.stats.entry(id).or_default().rating += change;
+        }
+    }
+
+    fn rating_of(&self, player: PlayerId) -> f64 {
+        self.stats
+            .get(&player)
+            .map_or(INITIAL_RATING, |stats| stats.rating)
+    }
+
+    pub fn rating(&self, player: PlayerId) -> i32 {
+        self.rating_of(player).round() as i32
+    }
+
+    pub fn summary(&self, players: &[Player]) -> Vec<String> {
+        players
+            .iter()
+            .map(|player| {
+                let (wins, losses, draws) = match self.get_stats(player) {
+                    Some(stats) => (stats.wins, stats.losses, stats.draws),
+                    None => (0, 0, 0),
+                };
+                format!("{} {}W/{}L/{}D", player.name(), wins, losses, draws)
+            })
+            .collect()
+    }
+
     pub fn get_stats(&self, player: &Player) -> Option<&PlayerStats> {
-        self.stats.get(player.name())
+        self.stats.get(&player.id())
     }
 }
@@ -0,0 +1,474 @@
+use crate::game::Game;
+use crate::player::Player;
+use crate::protocol::{read_message, ClientMessage, ServerMessage};
+use crate::spectator::evaluate;
+use crate::types::{GameError, GameStatus, PlayerId, Symbol, SymbolSet, Token};
+use std::collections::HashMap;
+use std::io::{self, BufReader, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+// Clients that send nothing for this long are dropped, players can rejoin
+pub(crate) const IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+// Messages waiting for a client before it counts as stalled
+const OUTBOX_SIZE: usize = 256;
+
+// Messages on their way to one client. A thread of its own writes them, so
+// a client that stops reading never holds up the server, and is dropped
+// once too many messages pile up
+pub(crate) struct Outbox {
+    sender: SyncSender<String>,
+    stream: TcpStream,
+    writer: JoinHandle<()>,
+}
+
+impl Outbox {
+    pub(crate) fn new(stream: &TcpStream) -> io::Result<Self> {
+        stream.set_read_timeout(Some(IDLE_TIMEOUT))?;
+        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+        let (sender, lines) = mpsc::sync_channel::<String>(OUTBOX_SIZE);
+        let mut out = stream.try_clone()?;
+        // Once the outbox is gone and its messages are out, so is the client
+        let writer = thread::spawn(move || {
+            for line in lines {
+                if writeln!(out, "{}", line)
+                    .and_then(|()| out.flush())
+                    .is_err()
+                {
+                    break;
+                }
+            }
+            let _ = out.shutdown(Shutdown::Both);
+        });
+        Ok(Self {
+            sender,
+            stream: stream.try_clone()?,
+            writer,
+        })
+    }
+
+    pub(crate) fn send(&self, message: &ServerMessage) {
+        let Ok(line) = serde_json::to_string(message) else {
+            return;
+        };
+        if self.sender.try_send(line).is_err() {
+            self.close();
+        }
+    }
+
+    // The reading side notices and disconnects the client
+    pub(crate) fn close(&self) {
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+
+    // Waits for the messages already queued to be written, then disconnects
+    pub(crate) fn finish(self) {
+        drop(self.sender);
+        let _ = self.writer.join();
+    }
+}
+
+struct Connection {
+    player: Option<PlayerId>,
+    spectating: bool,
+    outbox: Outbox,
+}
+
+struct ServerState {
+    game: Game,
+    symbol_set: SymbolSet,
+    connections: HashMap<usize, Connection>,
+    // Rejoin tokens of the remote players
+    tokens: HashMap<PlayerId, Token>,
+    result_sent: bool,
+    // Counts changes to the game, so an AI move worked out meanwhile can
+    // tell it is out of date
+    version: u64,
+    ai_thinking: bool,
+    // Set when the AI player to move had no answer, until the game changes
+    ai_stuck: bool,
+}
+
+// Hosts one game over TCP. Remote players join until the game is full, AI
+// players already in the game are played by the server
+pub struct GameServer {
+    listener: TcpListener,
+    state: Arc<Mutex<ServerState>>,
+}
+
+impl GameServer {
+    pub fn bind(addr: impl ToSocketAddrs, game: Game, symbol_set: SymbolSet) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            state: Arc::new(Mutex::new(ServerState {
+                game,
+                symbol_set,
+                connections: HashMap::new(),
+                tokens: HashMap::new(),
+                result_sent: false,
+                version: 0,
+                ai_thinking: false,
+                ai_stuck: false,
+            })),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    // Serves clients until the game is over and returns how it ended
+    pub fn run(&self) -> io::Result<GameStatus> {
+        let mut next_id = 0;
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    stream.set_nonblocking(false)?;
+                    let outbox = Outbox::new(&stream)?;
+                    lock(&self.state).connections.insert(
+                        next_id,
+                        Connection {
+                            player: None,
+                            spectating: false,
+                            outbox,
+                        },
+                    );
+                    let state = Arc::clone(&self.state);
+                    let id = next_id;
+                    thread::spawn(move || handle_connection(state, id, stream));
+                    next_id += 1;
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(e) => return Err(e),
+            }
+
+            let mut state = lock(&self.state);
+            if state.game.check_clock() {
+                state.after_change();
+            }
+            if state.result_sent {
+                // Everyone gets the last state and the result before the
+                // server goes away
+                let status = state.game.status();
+                let connections: Vec<Connection> =
+                    state.connections.drain().map(|(_, c)| c).collect();
+                drop(state);
+                for connection in connections {
+                    connection.outbox.finish();
+                }
+                return Ok(status);
+            }
+            drop(state);
+            play_ai_turns(&self.state);
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+fn lock(state: &Mutex<ServerState>) -> MutexGuard<'_, ServerState> {
+    state.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// The AI players think on a copy of the game, so the other clients are
+// served meanwhile. Their moves only count if the game hasn't changed since
+fn play_ai_turns(state: &Mutex<ServerState>) {
+    loop {
+        let (mut copy, version) = {
+            let mut state = lock(state);
+            if state.ai_thinking || !state.ai_to_move() {
+                return;
+            }
+            let Ok(copy) = Game::from_snapshot(&state.game.snapshot()) else {
+                return;
+            };
+            state.ai_thinking = true;
+            (copy, state.version)
+        };
+        let _ = copy.play_ai_turns();
+        let mut state = lock(state);
+        state.ai_thinking = false;
+        if state.version == version {
+            let played = copy.history().len() + copy.opening_decisions().len();
+            if played == state.game.history().len() + state.game.opening_decisions().len() {
+                state.ai_stuck = true;
+                return;
+            }
+            let _ = state.catch_up(&copy);
+            state.after_change();
+            return;
+        }
+    }
+}
+
+fn handle_connection(state: Arc<Mutex<ServerState>>, id: usize, stream: TcpStream) {
+    let mut reader = BufReader::new(stream);
+    loop {
+        let message = read_message::<ClientMessage>(&mut reader);
+        let mut guard = lock(&state);
+        match message {
+            Ok(Some(message)) => {
+                guard.handle(id, message);
+                drop(guard);
+                play_ai_turns(&state);
+            }
+            Ok(None) => {
+                guard.disconnect(id);
+                return;
+            }
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                guard.send(id, &error(format!("Invalid message: {}", e)));
+            }
+            Err(_) => {
+                guard.disconnect(id);
+                return;
+            }
+        }
+    }
+}
+
+fn error(message: impl Into<String>) -> ServerMessage {
+    ServerMessage::Error {
+        message: message.into(),
+    }
+}
+
+// The requested symbol if it is free, otherwise the next one from the set
+pub(crate) fn pick_symbol(
+    game: &Game,
+    symbol_set: SymbolSet,
+    requested: Option<String>,
+) -> Result<Symbol, String> {
+    let used: Vec<Symbol> = game.players().iter().map(|p| p.symbol()).collect();
+    match requested {
+        Some(symbol) => Symbol::parse(&symbol, &used).map_err(|e| e.to_string()),
+        None => symbol_set
+            .pick_unique(&used)
+            .ok_or_else(|| GameError::MaxPlayersReached.to_string()),
+    }
+}
+
+impl ServerState {
+    fn handle(&mut self, id: usize, message: ClientMessage) {
+        let player = self.connections.get(&id).and_then(|c| c.player);
+        let spectating = self.connections.get(&id).is_some_and(|c| c.spectating);
+        let result = match (message, player) {
+            (ClientMessage::Join { name, symbol }, None) => self.join(id, name, symbol),
+            (ClientMessage::Rejoin { player, token }, None) => self.rejoin(id, player, token),
+            (ClientMessage::Join { .. } | ClientMessage::Rejoin { .. }, Some(_)) => {
+                Err("You already joined the game".to_string())
+            }
+            (ClientMessage::Chat { text }, player) => {
+                let from = player
+                    .and_then(|id| self.game.player(id))
+                    .map_or("guest".to_string(), |p| p.name().to_string());
+                self.broadcast(&ServerMessage::Chat { from, text });
+                return;
+            }
+            (ClientMessage::Spectate { .. }, None) => {
+                self.spectate(id);
+                return;
+            }
+            (ClientMessage::Spectate { .. }, Some(_)) => {
+                Err("Players can't watch their own game".to_string())
+            }
+            (
+                ClientMessage::Login { .. }
+                | ClientMessage::ListGames
+                | ClientMessage::CreateGame { .. }
+                | ClientMessage::JoinGame { .. }
+                | ClientMessage::Leave
+                | ClientMessage::FindMatch { .. },
+                _,
+            ) => Err("This server hosts a single game, there is no lobby".to_string()),
+            (_, None) if spectating => Err("Spectators can't play".to_string()),
+            (_, None) => Err("Join the game first".to_string()),
+            (ClientMessage::Move { row, col, symbol }, Some(player)) => {
+                if self.game.players().len() < self.game.max_players() {
+                    Err("Waiting for more players to join".to_string())
+                } else {
+                    let moved = match symbol {
+                        Some(symbol) => self.game.make_move_with_as(player, (row, col), symbol),
+                        None => self.game.make_move_as(player, (row, col)),
+                    };
+                    moved.map_err(|e| e.to_string())
+                }
+            }
+            (ClientMessage::Choose { choice }, Some(player)) => {
+                if self.game.players().len() < self.game.max_players() {
+                    Err("Waiting for more players to join".to_string())
+                } else {
+                    self.game
+                        .choose_opening(player, choice)
+                        .map_err(|e| e.to_string())
+                }
+            }
+            (ClientMessage::Resign, Some(player)) => {
+                self.game.resign_player(player).map_err(|e| e.to_string())
+            }
+        };
+
+        match result {
+            Ok(()) => self.after_change(),
+            Err(message) => self.send(id, &error(message)),
+        }
+    }
+
+    fn join(&mut self, id: usize, name: String, symbol: Option<String>) -> Result<(), String> {
+        let symbol = pick_symbol(&self.game, self.symbol_set, symbol)?;
+        let player = Player::new(name, symbol, true, None);
+        self.game.add_player(&player).map_err(|e| e.to_string())?;
+        self.tokens.insert(player.id(), Token::random());
+        self.seat(id, player.id(), symbol);
+        let text = format!("{} joined as {}", player.name(), symbol);
+        self.broadcast(&ServerMessage::Notice { text });
+        Ok(())
+    }
+
+    fn rejoin(&mut self, id: usize, player: PlayerId, token: Token) -> Result<(), String> {
+        // Wrong tokens get the same answer as unknown players
+        let (name, symbol) = match self.game.player(player) {
+            Some(p) if self.tokens.get(&player) == Some(&token) => {
+                (p.name().to_string(), p.symbol())
+            }
+            _ => return Err(GameError::UnknownPlayer.to_string()),
+        };
+        // The old connection may not have noticed it is gone yet
+        let stale: Vec<usize> = self
+            .connections
+            .iter()
+            .filter(|(_, c)| c.player == Some(player))
+            .map(|(&id, _)| id)
+            .collect();
+        for stale_id in stale {
+            if let Some(connection) = self.connections.remove(&stale_id) {
+                connection.outbox.close();
+            }
+        }
+        self.seat(id, player, symbol);
+        self.broadcast(&ServerMessage::Notice {
+            text: format!("{} is back", name),
+        });
+        Ok(())
+    }
+
+    fn seat(&mut self, id: usize, player: PlayerId, symbol: Symbol) {
+        if let Some(connection) = self.connections.get_mut(&id) {
+            connection.player = Some(player);
+            connection.spectating = false;
+        }
+        if let Some(token) = self.tokens.get(&player).cloned() {
+            self.send(
+                id,
+                &ServerMessage::Welcome {
+                    player,
+                    symbol,
+                    token,
+                },
+            );
+        }
+    }
+
+    // Spectators joining midway get the whole game so far. With only one game
+    // on the server, it is game 0
+    fn spectate(&mut self, id: usize) {
+        if let Some(connection) = self.connections.get_mut(&id) {
+            connection.spectating = true;
+        }
+        self.send(
+            id,
+            &ServerMessage::Joined {
+                game: 0,
+                spectator: true,
+            },
+        );
+        let snapshot = Box::new(self.game.snapshot());
+        self.send(id, &ServerMessage::State { snapshot });
+        if self.game.is_over() {
+            let status = self.game.status();
+            self.send(id, &ServerMessage::Result { status });
+        } else {
+            let evaluations = evaluate(&self.game);
+            self.send(id, &ServerMessage::Evaluation { evaluations });
+        }
+    }
+
+    // Players keep their seat when the connection drops, so they can rejoin
+    fn disconnect(&mut self, id: usize) {
+        let player = match self.connections.remove(&id) {
+            Some(Connection {
+                player: Some(player),
+                ..
+            }) => player,
+            _ => return,
+        };
+        if let Some(name) = self.game.player(player).map(|p| p.name().to_string()) {
+            self.broadcast(&ServerMessage::Notice {
+                text: format!("{} lost the connection", name),
+            });
+        }
+    }
+
+    fn ai_to_move(&self) -> bool {
+        self.game.players().len() == self.game.max_players()
+            && !self.game.is_over()
+            && !self.ai_stuck
+            && self.game.current_player().is_some_and(|p| p.is_ai())
+    }
+
+    // Plays the moves and opening choices the copy has made beyond this game
+    fn catch_up(&mut self, copy: &Game) -> Result<(), GameError> {
+        let decisions = copy.opening_decisions();
+        let mut next_decision = self.game.opening_decisions().len();
+        for stones in self.game.history().len()..=copy.history().len() {
+            while let Some(decision) = decisions.get(next_decision).filter(|d| d.stones == stones) {
+                self.game.choose_opening(decision.player, decision.choice)?;
+                next_decision += 1;
+            }
+            if let Some(mv) = copy.history().get(stones) {
+                self.game.make_move_with(mv.position, mv.symbol)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Tells everyone about the new state
+    fn after_change(&mut self) {
+        self.version += 1;
+        self.ai_stuck = false;
+        let snapshot = Box::new(self.game.snapshot());
+        self.broadcast(&ServerMessage::State { snapshot });
+        if !self.game.is_over() && self.connections.values().any(|c| c.spectating) {
+            let message = ServerMessage::Evaluation {
+                evaluations: evaluate(&self.game),
+            };
+            for connection in self.connections.values().filter(|c| c.spectating) {
+                connection.outbox.send(&message);
+            }
+        }
+        if self.game.is_over() && !self.result_sent {
+            self.result_sent = true;
+            self.broadcast(&ServerMessage::Result {
+                status: self.game.status(),
+            });
+        }
+    }
+
+    fn send(&mut self, id: usize, message: &ServerMessage) {
+        if let Some(connection) = self.connections.get(&id) {
+            connection.outbox.send(message);
+        }
+    }
+
+    fn broadcast(&mut self, message: &ServerMessage) {
+        for connection in self.connections.values() {
+            connection.outbox.send(message);
+        }
+    }
+}
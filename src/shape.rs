@@ -0,0 +1,85 @@
+use crate::types::ShapeError;
+
+// Which cells of a board can be played. Written as rows of '.' for
+// playable and '#' for blocked cells, spaces between them optional:
+//
+//   # . #
+//   . . .
+//   # . #
+//
+// Rows shorter than the widest one are blocked to the right
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoardShape {
+    pub rows: usize,
+    pub cols: usize,
+    pub blocked: Vec<(usize, usize)>,
+}
+
+impl BoardShape {
+    pub const NAMES: [&'static str; 3] = ["cross", "diamond", "holes"];
+
+    pub fn parse(text: &str) -> Result<Self, ShapeError> {
+        let cells: Vec<Vec<bool>> = text
+            .lines()
+            .map(|line| line.split_whitespace().collect::<String>())
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                line.chars()
+                    .map(|c| match c {
+                        '.' => Ok(false),
+                        '#' => Ok(true),
+                        other => Err(ShapeError::UnknownCell(other)),
+                    })
+                    .collect()
+            })
+            .collect::<Result<_, _>>()?;
+        let rows = cells.len();
+        let cols = cells.iter().map(Vec::len).max().unwrap_or(0);
+        if rows == 0 {
+            return Err(ShapeError::Empty);
+        }
+        if rows < 3 || cols < 3 {
+            return Err(ShapeError::TooSmall(rows, cols));
+        }
+        let blocked = (0..rows)
+            .flat_map(|row| (0..cols).map(move |col| (row, col)))
+            .filter(|&(row, col)| cells[row].get(col).copied().unwrap_or(true))
+            .collect();
+        Ok(Self {
+            rows,
+            cols,
+            blocked,
+        })
+    }
+
+    // One of NAMES drawn on a board of `rows` by `cols`: a cross without the
+    // corner rectangles, a diamond touching the middle of each edge, or a
+    // hole in the middle of every 3x3 block
+    pub fn named(name: &str, rows: usize, cols: usize) -> Option<Self> {
+        if !Self::NAMES.contains(&name) {
+            return None;
+        }
+        let (last_row, last_col) = (rows.saturating_sub(1), cols.saturating_sub(1));
+        let outside = |index: usize, last: usize| {
+            let corner = (last + 1) / 3;
+            index < corner || index > last - corner
+        };
+        let blocked = (0..rows)
+            .flat_map(|row| (0..cols).map(move |col| (row, col)))
+            .filter(|&(row, col)| match name {
+                "cross" => outside(row, last_row) && outside(col, last_col),
+                "diamond" => {
+                    (2 * row).abs_diff(last_row) * last_col
+                        + (2 * col).abs_diff(last_col) * last_row
+                        > last_row * last_col
+                }
+                _ => row % 3 == 1 && col % 3 == 1,
+            })
+            .collect();
+        Some(Self {
+            rows,
+            cols,
+            blocked,
+        })
+    }
+}
@@ -0,0 +1,100 @@
+use crate::game::Game;
+use crate::player::Player;
+use crate::rules::GameRules;
+use crate::types::{Difficulty, Move, OpeningDecision, PlayerId, Symbol};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerSnapshot {
+    pub id: PlayerId,
+    pub name: String,
+    pub symbol: Symbol,
+    pub difficulty: Option<Difficulty>,
+}
+
+impl PlayerSnapshot {
+    pub fn from_player(player: &Player) -> Self {
+        Self {
+            id: player.id(),
+            name: player.name().to_string(),
+            symbol: player.symbol(),
+            difficulty: player.difficulty(),
+        }
+    }
+
+    pub fn to_player(&self) -> Player {
+        Player::new(
+            self.name.clone(),
+            self.symbol,
+            self.difficulty.is_none(),
+            self.difficulty,
+        )
+        .with_id(self.id)
+    }
+}
+
+// Everything needed to rebuild a game: the board is replayed from the moves
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameSnapshot {
+    // Rows of the board, and also its columns unless `board_cols` says
+    // otherwise, as in saves from before rectangular boards
+    pub board_size: usize,
+    #[serde(default)]
+    pub board_cols: Option<usize>,
+    pub max_players: usize,
+    #[serde(default)]
+    pub rules: GameRules,
+    pub players: Vec<PlayerSnapshot>,
+    pub moves: Vec<Move>,
+    #[serde(default)]
+    pub undone: Vec<Move>,
+    #[serde(default)]
+    pub resigned: Vec<PlayerId>,
+    #[serde(default)]
+    pub timed_out: Vec<PlayerId>,
+    #[serde(default)]
+    pub eliminated: Vec<PlayerId>,
+    #[serde(default)]
+    pub opening: Vec<OpeningDecision>,
+    // Time left per player, empty without a time control
+    #[serde(default)]
+    pub clocks: Vec<Duration>,
+    pub current_player: usize,
+    // Rounds played in this match, counting the current one
+    #[serde(default)]
+    pub round: usize,
+    // Stones the current player has already placed this turn
+    #[serde(default)]
+    pub stones_placed: usize,
+}
+
+impl GameSnapshot {
+    pub fn from_game(game: &Game) -> Self {
+        Self {
+            board_size: game.board().rows(),
+            board_cols: Some(game.board().cols()),
+            max_players: game.max_players(),
+            rules: game.rules().clone(),
+            players: game
+                .players()
+                .iter()
+                .map(PlayerSnapshot::from_player)
+                .collect(),
+            moves: game.history().to_vec(),
+            undone: game.undone().to_vec(),
+            resigned: game.resigned().to_vec(),
+            timed_out: game.timed_out().to_vec(),
+            eliminated: game.eliminated().to_vec(),
+            opening: game.opening_decisions().to_vec(),
+            clocks: game
+                .players()
+                .iter()
+                .filter_map(|p| game.time_left(p.id()))
+                .collect(),
+            current_player: game.current_player_idx(),
+            round: game.round(),
+            stones_placed: game.turn_progress().0,
+        }
+    }
+}
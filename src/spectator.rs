@@ -0,0 +1,175 @@
+use crate::ai::{AI, WIN_SCORE};
+use crate::events::{GameEvent, GameObserver};
+use crate::game::Game;
+use crate::types::{Difficulty, PlayerId};
+use crate::ui::UI;
+use serde::{Deserialize, Serialize};
+
+// How the AI sees the position for one player. Only spectators get these,
+// players would be reading hints
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Evaluation {
+    pub player: PlayerId,
+    pub score: i32,
+    // Only for the player to move
+    #[serde(default)]
+    pub best_move: Option<(usize, usize)>,
+}
+
+// Evaluations for every player still in the game
+pub fn evaluate(game: &Game) -> Vec<Evaluation> {
+    let current = game.current_player().map(|p| p.id());
+    game.players()
+        .iter()
+        .filter(|p| !game.has_resigned(p.id()))
+        .map(|player| {
+            let ai = AI::new(Difficulty::Hard, player.symbol());
+            let to_move = Some(player.id()) == current && !game.is_over();
+            Evaluation {
+                player: player.id(),
+                score: ai.evaluate(game.board()),
+                best_move: to_move.then(|| ai.decide_move(game.board())),
+            }
+        })
+        .collect()
+}
+
+pub fn describe_evaluation(game: &Game, evaluation: &Evaluation) -> String {
+    let name = game
+        .player(evaluation.player)
+        .map(|p| format!("{} ({})", p.name(), p.symbol()))
+        .unwrap_or_default();
+    let score = match evaluation.score {
+        WIN_SCORE => "won".to_string(),
+        score if score == -WIN_SCORE => "lost".to_string(),
+        score => format!("{:+}", score),
+    };
+    match evaluation.best_move {
+        Some((row, col)) => format!("{}: {}, best move {} {}", name, score, row, col),
+        None => format!("{}: {}", name, score),
+    }
+}
+
+// One line per move, e.g. "3. Ann (X) 1 1"
+pub fn history(game: &Game) -> Vec<String> {
+    game.history()
+        .iter()
+        .enumerate()
+        .map(|(i, mv)| {
+            let name = game.player(mv.player).map_or("?", |p| p.name());
+            let (row, col) = mv.position;
+            format!("{}. {} ({}) {} {}", i + 1, name, mv.symbol, row, col)
+        })
+        .collect()
+}
+
+pub fn describe_event(game: &Game, event: &GameEvent) -> Option<String> {
+    let name = |id| game.player(id).map_or("?", |p| p.name()).to_string();
+    match event {
+        GameEvent::PlayerJoined { name, symbol, .. } => {
+            Some(format!("{} joined as {}", name, symbol))
+        }
+        GameEvent::RoundStarted { round } => Some(format!("Round {} starts", round)),
+        GameEvent::MovePlayed(mv) => Some(format!(
+            "{} played {} {}",
+            name(mv.player),
+            mv.position.0,
+            mv.position.1
+        )),
+        GameEvent::MoveUndone(mv) => Some(format!(
+            "{}'s move {} {} was taken back",
+            name(mv.player),
+            mv.position.0,
+            mv.position.1
+        )),
+        GameEvent::MarkRemoved { player, position } => Some(format!(
+            "{}'s mark at {} {} vanished",
+            name(*player),
+            position.0,
+            position.1
+        )),
+        GameEvent::OpeningChosen { player, choice } => {
+            Some(format!("{} chose {}", name(*player), choice))
+        }
+        GameEvent::TurnChanged { .. } => None,
+        GameEvent::ThreatCreated { player, positions } => Some(format!(
+            "{} threatens to win at {}",
+            name(*player),
+            positions
+                .iter()
+                .map(|(row, col)| format!("{} {}", row, col))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+        GameEvent::PlayerResigned { player } => Some(format!("{} resigned", name(*player))),
+        GameEvent::PlayerEliminated { player } => {
+            Some(format!("{} completed a line and is out", name(*player)))
+        }
+        GameEvent::FlagFell { player } => Some(format!("{} ran out of time", name(*player))),
+        GameEvent::GameWon { .. } | GameEvent::GameDrawn { .. } => Some(game.status().to_string()),
+    }
+}
+
+// A read-only view of a game. It only ever sees the game through a shared
+// reference, so watching can't change it. Add it as an observer to follow a
+// local game, or feed it snapshots from a server
+pub struct Spectator {
+    ui: UI,
+}
+
+impl Spectator {
+    pub fn new(ui: UI) -> Self {
+        Self { ui }
+    }
+
+    // Everything someone joining midway needs to catch up
+    pub fn show_game(&self, game: &Game) {
+        let players: Vec<String> = game
+            .players()
+            .iter()
+            .map(|p| format!("{} ({})", p.name(), p.symbol()))
+            .collect();
+        println!("Players: {}", players.join(", "));
+        let moves = history(game);
+        if !moves.is_empty() {
+            println!("Moves so far:");
+            for line in moves {
+                println!("  {}", line);
+            }
+        }
+        self.show_position(game);
+    }
+
+    pub fn show_position(&self, game: &Game) {
+        self.ui.display_board(game.board());
+        if game.is_over() {
+            println!("{}", game.status());
+        } else if let Some(current) = game.current_player() {
+            println!("{} ({}) to move", current.name(), current.symbol());
+        }
+    }
+
+    pub fn show_evaluations(&self, game: &Game, evaluations: &[Evaluation]) {
+        for evaluation in evaluations {
+            println!("  {}", describe_evaluation(game, evaluation));
+        }
+    }
+}
+
+impl GameObserver for Spectator {
+    fn on_event(&mut self, game: &Game, event: &GameEvent) {
+        if let Some(text) = describe_event(game, event) {
+            println!("* {}", text);
+        }
+        match event {
+            GameEvent::MovePlayed(_) | GameEvent::MoveUndone(_) if !game.is_over() => {
+                self.show_position(game);
+                self.show_evaluations(game, &evaluate(game));
+            }
+            GameEvent::GameWon { .. } | GameEvent::GameDrawn { .. } => {
+                self.ui.display_board(game.board())
+            }
+            _ => {}
+        }
+    }
+}
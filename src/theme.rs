@@ -0,0 +1,170 @@
+use crossterm::style::{Attribute, Color, ContentStyle};
+use std::env;
+use std::io::{self, IsTerminal};
+
+fn rgb(r: u8, g: u8, b: u8) -> Color {
+    Color::Rgb { r, g, b }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CellEmphasis {
+    None,
+    LastMove,
+    WinningLine,
+    // A mark about to disappear
+    Vanishing,
+}
+
+#[derive(Debug, Clone)]
+pub struct Theme {
+    name: &'static str,
+    enabled: bool,
+    player_colors: Vec<Color>,
+    grid_color: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::classic()
+    }
+}
+
+impl Theme {
+    pub const NAMES: [&'static str; 3] = ["classic", "colorblind", "mono"];
+
+    pub fn classic() -> Self {
+        Self {
+            name: "classic",
+            enabled: true,
+            player_colors: vec![Color::Red, Color::Blue, Color::Green, Color::Magenta],
+            grid_color: Color::DarkGrey,
+        }
+    }
+
+    // Okabe-Ito palette, distinguishable with the common forms of color blindness
+    pub fn colorblind() -> Self {
+        Self {
+            name: "colorblind",
+            enabled: true,
+            player_colors: vec![
+                rgb(0, 114, 178),
+                rgb(230, 159, 0),
+                rgb(204, 121, 167),
+                rgb(0, 158, 115),
+            ],
+            grid_color: Color::DarkGrey,
+        }
+    }
+
+    // Emphasis through bold/underline/reverse only, no colors
+    pub fn mono() -> Self {
+        Self {
+            name: "mono",
+            enabled: true,
+            player_colors: Vec::new(),
+            grid_color: Color::Reset,
+        }
+    }
+
+    pub fn plain() -> Self {
+        Self {
+            name: "plain",
+            enabled: false,
+            player_colors: Vec::new(),
+            grid_color: Color::Reset,
+        }
+    }
+
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "classic" => Some(Self::classic()),
+            "colorblind" => Some(Self::colorblind()),
+            "mono" => Some(Self::mono()),
+            "plain" => Some(Self::plain()),
+            _ => None,
+        }
+    }
+
+    // Falls back to plain output when NO_COLOR is set or stdout is not a terminal
+    pub fn for_stdout(theme: Theme) -> Self {
+        if Self::color_allowed() {
+            theme
+        } else {
+            Self::plain()
+        }
+    }
+
+    pub fn color_allowed() -> bool {
+        let no_color = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        !no_color && io::stdout().is_terminal()
+    }
+
+    pub fn name(&self) -> &str {
+        self.name
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn paint_grid(&self, text: &str) -> String {
+        if !self.enabled || self.grid_color == Color::Reset {
+            return text.to_string();
+        }
+        let mut style = ContentStyle::new();
+        style.foreground_color = Some(self.grid_color);
+        style.apply(text).to_string()
+    }
+
+    // `player_idx` selects the player's color, `None` for empty cells
+    pub fn paint_cell(
+        &self,
+        text: &str,
+        player_idx: Option<usize>,
+        emphasis: CellEmphasis,
+    ) -> String {
+        if !self.enabled {
+            return text.to_string();
+        }
+
+        let color = player_idx.and_then(|idx| {
+            if self.player_colors.is_empty() {
+                None
+            } else {
+                Some(self.player_colors[idx % self.player_colors.len()])
+            }
+        });
+
+        let mut style = ContentStyle::new();
+        match emphasis {
+            CellEmphasis::None => style.foreground_color = color,
+            CellEmphasis::LastMove => {
+                style.foreground_color = color;
+                style.attributes.set(Attribute::Bold);
+                style.attributes.set(Attribute::Underlined);
+            }
+            CellEmphasis::Vanishing => {
+                style.foreground_color = color;
+                style.attributes.set(Attribute::Dim);
+                style.attributes.set(Attribute::CrossedOut);
+            }
+            CellEmphasis::WinningLine => {
+                // Background so the line also stands out for emoji symbols
+                match color {
+                    Some(color) => {
+                        style.background_color = Some(color);
+                        style.foreground_color = Some(Color::White);
+                    }
+                    None => style.attributes.set(Attribute::Reverse),
+                }
+                style.attributes.set(Attribute::Bold);
+            }
+        }
+
+        if style == ContentStyle::new() {
+            text.to_string()
+        } else {
+            style.apply(text).to_string()
+        }
+    }
+}
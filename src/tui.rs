@@ -0,0 +1,434 @@
+use crate::command::Command;
+use crate::game::Game;
+use crate::render::{clock_summary, opening_summary, BoardLayout, BoardMarks};
+use crate::runner::{MoveProvider, PlayerAction, TurnContext};
+use crate::score_board::ScoreBoard;
+use crate::theme::Theme;
+use crate::types::SymbolSet;
+use crossterm::cursor::{self, MoveTo};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use std::io::{self, IsTerminal, Stdout, Write};
+use std::time::{Duration, Instant};
+
+const CLOCK_REFRESH: Duration = Duration::from_millis(200);
+
+// What a key does on the board screen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Up,
+    Down,
+    Left,
+    Right,
+    Place,
+    Undo,
+    Redo,
+    Hint,
+    Console,
+    Quit,
+}
+
+pub struct TerminalUI {
+    out: Stdout,
+    symbol_set: SymbolSet,
+    theme: Theme,
+    cursor: (usize, usize),
+    message: Option<String>,
+    console: Option<String>,
+}
+
+impl TerminalUI {
+    pub fn is_supported() -> bool {
+        io::stdout().is_terminal() && io::stdin().is_terminal()
+    }
+
+    pub fn new(symbol_set: SymbolSet, theme: Theme) -> io::Result<Self> {
+        let mut out = io::stdout();
+        terminal::enable_raw_mode()?;
+        // Without the screen there is no UI to leave raw mode again later
+        if let Err(e) = execute!(out, EnterAlternateScreen, cursor::Hide) {
+            let _ = terminal::disable_raw_mode();
+            return Err(e);
+        }
+        Ok(Self {
+            out,
+            symbol_set,
+            theme: Theme::for_stdout(theme),
+            cursor: (0, 0),
+            message: None,
+            console: None,
+        })
+    }
+
+    pub fn set_message(&mut self, message: impl Into<String>) {
+        self.message = Some(message.into());
+    }
+
+    pub fn display(&mut self, game: &Game, scores: &ScoreBoard) -> io::Result<()> {
+        self.draw(game, scores, false)
+    }
+
+    // Cursor keys pick a cell, shortcuts and ':' give access to the console
+    // commands. None once the player's clock has run out
+    pub fn read_command(
+        &mut self,
+        game: &Game,
+        scores: &ScoreBoard,
+    ) -> io::Result<Option<Command>> {
+        let (rows, cols) = (game.board().rows(), game.board().cols());
+        self.cursor = clamp_cursor(self.cursor, rows, cols);
+
+        loop {
+            let Some(event) = self.next_event(game, scores)? else {
+                return Ok(None);
+            };
+            let action = match event {
+                Event::Key(key) => match key_action(key) {
+                    Some(action) => action,
+                    None => continue,
+                },
+                _ => continue,
+            };
+
+            let (row, col) = self.cursor;
+            match action {
+                Action::Up | Action::Down | Action::Left | Action::Right => {
+                    self.cursor = move_cursor(self.cursor, action, rows, cols);
+                }
+                Action::Place => {
+                    if game.is_move_valid(self.cursor) {
+                        self.message = None;
+                        return Ok(Some(Command::Move(self.cursor)));
+                    }
+                    self.message = Some(format!("Cell {} {} is taken!", row, col));
+                }
+                Action::Undo => return Ok(Some(Command::Undo)),
+                Action::Redo => return Ok(Some(Command::Redo)),
+                Action::Hint => return Ok(Some(Command::Hint)),
+                Action::Console => {
+                    if let Some(command) = self.read_console(game, scores)? {
+                        return Ok(Some(command));
+                    }
+                }
+                Action::Quit => return Ok(Some(Command::Quit)),
+            }
+        }
+    }
+
+    // The next terminal event, None if the player's clock runs out first.
+    // Timed games redraw a few times a second so the clocks count down
+    fn next_event(&mut self, game: &Game, scores: &ScoreBoard) -> io::Result<Option<Event>> {
+        loop {
+            self.draw(game, scores, true)?;
+            match game.flag_deadline() {
+                Some(deadline) if Instant::now() >= deadline => return Ok(None),
+                Some(_) if !event::poll(CLOCK_REFRESH)? => continue,
+                _ => return event::read().map(Some),
+            }
+        }
+    }
+
+    pub fn ask_yes_no(
+        &mut self,
+        game: &Game,
+        scores: &ScoreBoard,
+        prompt: &str,
+    ) -> io::Result<bool> {
+        self.message = Some(format!("{} (y/N)", prompt));
+        self.draw(game, scores, false)?;
+        self.message = None;
+        loop {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    return Ok(matches!(key.code, KeyCode::Char('y') | KeyCode::Char('Y')));
+                }
+            }
+        }
+    }
+
+    // Line editor at the bottom of the screen, None when cancelled with Esc
+    fn read_console(&mut self, game: &Game, scores: &ScoreBoard) -> io::Result<Option<Command>> {
+        self.console = Some(String::new());
+        loop {
+            let key = match self.next_event(game, scores)? {
+                Some(Event::Key(key)) if key.kind == KeyEventKind::Press => key,
+                Some(_) => continue,
+                None => {
+                    self.console = None;
+                    return Ok(None);
+                }
+            };
+            let input = self.console.get_or_insert_with(String::new);
+            match key.code {
+                KeyCode::Esc => {
+                    self.console = None;
+                    return Ok(None);
+                }
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char(c) => input.push(c),
+                KeyCode::Enter => {
+                    let line = self.console.take().unwrap_or_default();
+                    match Command::parse(&line) {
+                        Ok(command) => {
+                            self.message = None;
+                            return Ok(Some(command));
+                        }
+                        Err(e) => {
+                            self.message = Some(e.to_string());
+                            return Ok(None);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn ask_play_again(&mut self, game: &Game, scores: &ScoreBoard) -> io::Result<bool> {
+        let prompt = "Play another round? (Enter/y: yes, any other key: quit)";
+        self.message = match self.message.take() {
+            Some(message) => Some(format!("{}  {}", message, prompt)),
+            None => Some(prompt.to_string()),
+        };
+        self.draw(game, scores, false)?;
+        self.message = None;
+        loop {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    return Ok(matches!(
+                        key.code,
+                        KeyCode::Enter | KeyCode::Char('y') | KeyCode::Char('Y')
+                    ));
+                }
+            }
+        }
+    }
+
+    fn draw(&mut self, game: &Game, scores: &ScoreBoard, show_cursor: bool) -> io::Result<()> {
+        let board = game.board();
+        let layout = BoardLayout::for_board(board, self.symbol_set.cell_width());
+        let marks = BoardMarks::for_game(game);
+        let border = self.theme.paint_grid(&layout.border());
+        let separator = self.theme.paint_grid("|");
+        let mut line = 0;
+
+        queue!(self.out, Clear(ClearType::All), MoveTo(0, line))?;
+        queue!(self.out, Print("Tic Tac Toe"))?;
+        line += 2;
+
+        queue!(self.out, MoveTo(0, line), Print(layout.column_header()))?;
+        line += 1;
+        queue!(self.out, MoveTo(0, line), Print(&border))?;
+        line += 1;
+
+        for row in 0..board.rows() {
+            queue!(
+                self.out,
+                MoveTo(0, line),
+                Print(layout.row_number(row)),
+                Print(&separator)
+            )?;
+            for col in 0..board.cols() {
+                if show_cursor && self.cursor == (row, col) {
+                    queue!(
+                        self.out,
+                        SetAttribute(Attribute::Reverse),
+                        Print(layout.board_cell(board, (row, col))),
+                        SetAttribute(Attribute::Reset)
+                    )?;
+                } else {
+                    let content = layout.styled_cell(board, (row, col), &self.theme, &marks);
+                    queue!(self.out, Print(content))?;
+                }
+                queue!(self.out, Print(&separator))?;
+            }
+            line += 1;
+            queue!(self.out, MoveTo(0, line), Print(&border))?;
+            line += 1;
+        }
+
+        line += 1;
+        for status in Self::status_lines(game, scores) {
+            queue!(self.out, MoveTo(0, line), Print(status))?;
+            line += 1;
+        }
+
+        if let Some(message) = &self.message {
+            for text in message.lines() {
+                line += 1;
+                queue!(self.out, MoveTo(0, line), Print(text))?;
+            }
+        }
+
+        line += 2;
+        match &self.console {
+            Some(input) => queue!(
+                self.out,
+                MoveTo(0, line),
+                Print(format!(":{}", input)),
+                cursor::Show
+            )?,
+            None => queue!(
+                self.out,
+                MoveTo(0, line),
+                Print("Arrows/WASD/hjkl: move  Enter: place  u/r: undo/redo  ?: hint  :: command  q: quit"),
+                cursor::Hide
+            )?,
+        }
+        self.out.flush()
+    }
+
+    fn status_lines(game: &Game, scores: &ScoreBoard) -> Vec<String> {
+        let mut lines = Vec::new();
+
+        match game.current_player() {
+            Some(current) if !game.is_over() => lines.push(format!(
+                "Current player: {} ({})",
+                current.name(),
+                current.symbol()
+            )),
+            _ => lines.push(game.status().to_string()),
+        }
+
+        let (placed, total) = game.turn_progress();
+        if total > 1 && !game.is_over() {
+            lines.push(format!("Stone {} of {} this turn", placed + 1, total));
+        }
+        if let Some(opening) = opening_summary(game).filter(|_| !game.is_over()) {
+            lines.push(opening);
+        }
+
+        match game.last_move() {
+            Some(last) => lines.push(format!(
+                "Last move: {} at {} {}",
+                last.symbol, last.position.0, last.position.1
+            )),
+            None => lines.push("Last move: -".to_string()),
+        }
+
+        if let Some((row, col)) = game.next_to_vanish() {
+            lines.push(format!("Next to vanish: {} {}", row, col));
+        }
+        lines.push(format!(
+            "Scores: {}",
+            scores.summary(game.players()).join("  ")
+        ));
+        if let Some(clocks) = clock_summary(game) {
+            lines.push(format!("Time left: {}", clocks));
+        }
+
+        lines
+    }
+}
+
+// Terminal errors end the game the same way as quitting
+impl MoveProvider for TerminalUI {
+    fn next_action(&mut self, game: &Game, ctx: &TurnContext) -> PlayerAction {
+        loop {
+            let command = match self.read_command(game, ctx.scores) {
+                Ok(Some(command)) => command,
+                Ok(None) => return PlayerAction::TimeUp,
+                Err(_) => return PlayerAction::Quit,
+            };
+            match command {
+                Command::Move(pos) => return PlayerAction::Move(pos),
+                Command::Place(pos, symbol) => return PlayerAction::Place(pos, symbol),
+                Command::Choose(choice) => return PlayerAction::ChooseOpening(choice),
+                Command::Undo => return PlayerAction::Undo,
+                Command::Redo => return PlayerAction::Redo,
+                Command::OfferDraw => return PlayerAction::OfferDraw,
+                Command::Resign => return PlayerAction::Resign,
+                Command::Quit => return PlayerAction::Quit,
+                Command::Hint => match game.hint_with_symbol() {
+                    Some(((row, col), symbol)) if game.rules().is_wild() => {
+                        self.set_message(format!("Hint: try {} {} {}", row, col, symbol))
+                    }
+                    Some(((row, col), _)) => self.set_message(format!("Hint: try {} {}", row, col)),
+                    None => self.set_message("No hint available."),
+                },
+                Command::Save(path) => match game.save(&path) {
+                    Ok(()) => self.set_message(format!(
+                        "Game saved to {}, resume with --load {}",
+                        path, path
+                    )),
+                    Err(e) => self.set_message(e.to_string()),
+                },
+                Command::Board | Command::Score => {}
+                Command::Help => self.set_message(Command::HELP),
+            }
+        }
+    }
+
+    fn respond_to_draw(&mut self, game: &Game, ctx: &TurnContext) -> bool {
+        let prompt = match (game.player(ctx.player), game.draw_offered_by()) {
+            (Some(player), Some(offered_by)) => format!(
+                "{}, {} offers a draw. Accept?",
+                player.name(),
+                offered_by.name()
+            ),
+            _ => return false,
+        };
+        self.ask_yes_no(game, ctx.scores, &prompt).unwrap_or(false)
+    }
+
+    fn is_interactive(&self) -> bool {
+        true
+    }
+}
+
+// The action for a key press, None for keys without one
+pub fn key_action(key: KeyEvent) -> Option<Action> {
+    if key.kind != KeyEventKind::Press {
+        return None;
+    }
+    if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+        return Some(Action::Quit);
+    }
+    match key.code {
+        KeyCode::Up | KeyCode::Char('w') | KeyCode::Char('k') => Some(Action::Up),
+        KeyCode::Down | KeyCode::Char('s') | KeyCode::Char('j') => Some(Action::Down),
+        KeyCode::Left | KeyCode::Char('a') | KeyCode::Char('h') => Some(Action::Left),
+        KeyCode::Right | KeyCode::Char('d') | KeyCode::Char('l') => Some(Action::Right),
+        KeyCode::Enter | KeyCode::Char(' ') => Some(Action::Place),
+        KeyCode::Char('u') => Some(Action::Undo),
+        KeyCode::Char('r') => Some(Action::Redo),
+        KeyCode::Char('?') => Some(Action::Hint),
+        KeyCode::Char(':') => Some(Action::Console),
+        KeyCode::Esc | KeyCode::Char('q') => Some(Action::Quit),
+        _ => None,
+    }
+}
+
+// Arrow keys wrap around the edges of a `rows` x `cols` board, other
+// actions leave the cursor where it is
+pub fn move_cursor(
+    cursor: (usize, usize),
+    action: Action,
+    rows: usize,
+    cols: usize,
+) -> (usize, usize) {
+    let (row, col) = cursor;
+    match action {
+        Action::Up => (row.checked_sub(1).unwrap_or(rows - 1), col),
+        Action::Down => ((row + 1) % rows, col),
+        Action::Left => (row, col.checked_sub(1).unwrap_or(cols - 1)),
+        Action::Right => (row, (col + 1) % cols),
+        _ => cursor,
+    }
+}
+
+// Keeps the cursor on the board when the next game's board is smaller
+pub fn clamp_cursor(cursor: (usize, usize), rows: usize, cols: usize) -> (usize, usize) {
+    (cursor.0.min(rows - 1), cursor.1.min(cols - 1))
+}
+
+impl Drop for TerminalUI {
+    fn drop(&mut self) {
+        let _ = execute!(self.out, cursor::Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
@@ -1,13 +1,18 @@
 use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::num::ParseIntError;
+use std::str::FromStr;
+use unicode_width::UnicodeWidthChar;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Symbol(pub char);
 
 impl Symbol {
     const SYMBOLS: [char; 12] = [
         '🐱', '🐶', '🦊', '🐰', '🐼', '🐨', '🦁', '🐯', '🐸', '🦉', '🦄', '🐙',
     ];
+    const ASCII_SYMBOLS: [char; 12] = ['X', 'O', 'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'K', 'M'];
 
     pub fn random_unique(used_symbols: &[Symbol]) -> Option<Symbol> {
         let available_symbols: Vec<_> = Self::SYMBOLS
@@ -20,6 +25,27 @@ impl Symbol {
             .choose(&mut rand::thread_rng())
             .map(|&c| Symbol(c))
     }
+
+    pub fn parse(input: &str, used_symbols: &[Symbol]) -> Result<Symbol, PlayerError> {
+        let mut chars = input.trim().chars();
+        let c = match (chars.next(), chars.next()) {
+            (Some(c), None) => c,
+            _ => return Err(PlayerError::InvalidSymbol),
+        };
+        if c.is_whitespace() || c.is_control() || c.width().unwrap_or(0) == 0 {
+            return Err(PlayerError::InvalidSymbol);
+        }
+
+        let symbol = Symbol(c);
+        if used_symbols.contains(&symbol) {
+            return Err(PlayerError::SymbolTaken);
+        }
+        Ok(symbol)
+    }
+
+    pub fn display_width(&self) -> usize {
+        self.0.width().unwrap_or(1)
+    }
 }
 
 impl fmt::Display for Symbol {
@@ -28,32 +54,207 @@ impl fmt::Display for Symbol {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SymbolSet {
+    #[default]
+    Emoji,
+    Ascii,
+}
+
+impl SymbolSet {
+    pub fn symbols(&self) -> &'static [char] {
+        match self {
+            SymbolSet::Emoji => &Symbol::SYMBOLS,
+            SymbolSet::Ascii => &Symbol::ASCII_SYMBOLS,
+        }
+    }
+
+    // Emoji are handed out at random, ASCII letters in order (X, O, A, B, ...)
+    pub fn pick_unique(&self, used_symbols: &[Symbol]) -> Option<Symbol> {
+        match self {
+            SymbolSet::Emoji => Symbol::random_unique(used_symbols),
+            SymbolSet::Ascii => self
+                .symbols()
+                .iter()
+                .map(|&c| Symbol(c))
+                .find(|symbol| !used_symbols.contains(symbol)),
+        }
+    }
+
+    pub fn cell_width(&self) -> usize {
+        self.symbols()
+            .iter()
+            .map(|&c| Symbol(c).display_width())
+            .max()
+            .unwrap_or(1)
+    }
+}
+
+// Stable identity of a player, independent of their name and symbol
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PlayerId(pub u64);
+
+impl PlayerId {
+    pub fn random() -> Self {
+        PlayerId(rand::random())
+    }
+}
+
+impl fmt::Display for PlayerId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+// Parses the hex form written by Display
+impl FromStr for PlayerId {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        u64::from_str_radix(s, 16).map(PlayerId)
+    }
+}
+
+// Secret that proves a connection speaks for a player. Player ids are
+// public, every state update shows them, so servers hand a token only to
+// the player it belongs to and ask for it whenever they come back
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Token(pub String);
+
+impl Token {
+    pub fn random() -> Self {
+        Token(format!("{:032x}", rand::random::<u128>()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Move {
+    pub position: (usize, usize),
+    pub symbol: Symbol,
+    pub player: PlayerId,
+}
+
+// Swap openings: the color a player takes, or in swap2 placing two more
+// stones and leaving the choice to the opener. Under the pie rule the
+// second player takes over the first move or keeps their own side
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum OpeningChoice {
+    Black,
+    White,
+    PlaceTwo,
+    Swap,
+    Keep,
+}
+
+impl fmt::Display for OpeningChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OpeningChoice::Black => write!(f, "black"),
+            OpeningChoice::White => write!(f, "white"),
+            OpeningChoice::PlaceTwo => write!(f, "two more stones"),
+            OpeningChoice::Swap => write!(f, "to swap sides"),
+            OpeningChoice::Keep => write!(f, "to keep their side"),
+        }
+    }
+}
+
+// A choice made during the opening, after `stones` stones were placed.
+// `swapped` when the two players exchanged symbols for it
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct OpeningDecision {
+    pub player: PlayerId,
+    pub choice: OpeningChoice,
+    pub stones: usize,
+    pub swapped: bool,
+}
+
+// Renju moves the first player may not make
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ForbiddenMove {
+    DoubleThree,
+    DoubleFour,
+    Overline,
+}
+
+impl fmt::Display for ForbiddenMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ForbiddenMove::DoubleThree => write!(f, "two open threes at once"),
+            ForbiddenMove::DoubleFour => write!(f, "two fours at once"),
+            ForbiddenMove::Overline => write!(f, "more than five in a row"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum GameStatus {
     InProgress,
-    Win(String), // Player name
-    Draw,
+    Win(String, WinReason), // Player name
+    Draw(DrawReason),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum WinReason {
+    Streak,
+    Resignation,
+    Timeout,
+    // Misère: everyone else completed a line
+    Elimination,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DrawReason {
+    BoardFull,
+    Agreement,
+    NoWinPossible,
+}
+
+impl fmt::Display for GameStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameStatus::InProgress => write!(f, "Game in progress"),
+            GameStatus::Win(name, WinReason::Streak) => write!(f, "{} wins!", name),
+            GameStatus::Win(name, WinReason::Resignation) => {
+                write!(f, "{} wins, everyone else resigned!", name)
+            }
+            GameStatus::Win(name, WinReason::Timeout) => {
+                write!(f, "{} wins, everyone else ran out of time!", name)
+            }
+            GameStatus::Win(name, WinReason::Elimination) => {
+                write!(f, "{} wins, everyone else completed a line!", name)
+            }
+            GameStatus::Draw(DrawReason::BoardFull) => write!(f, "Draw, the board is full!"),
+            GameStatus::Draw(DrawReason::Agreement) => write!(f, "Draw by agreement!"),
+            GameStatus::Draw(DrawReason::NoWinPossible) => {
+                write!(f, "Draw, nobody can complete a line anymore!")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum GameResult {
     Win,
     Loss,
     Draw,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum Difficulty {
     Easy,
     Medium,
     Hard,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum BoardError {
     InvalidPosition,
     CellOccupied,
     OutOfBounds,
+    // Ultimate tic-tac-toe: the move has to go to another sub-board
+    InactiveSubBoard,
+    // The cell is not part of the board's shape
+    CellBlocked,
 }
 
 impl fmt::Display for BoardError {
@@ -62,16 +263,32 @@ impl fmt::Display for BoardError {
             BoardError::InvalidPosition => write!(f, "Invalid position"),
             BoardError::CellOccupied => write!(f, "Cell already occupied"),
             BoardError::OutOfBounds => write!(f, "Position is out of bounds"),
+            BoardError::InactiveSubBoard => write!(f, "That sub-board is not in play"),
+            BoardError::CellBlocked => write!(f, "That cell is blocked"),
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum GameError {
     InvalidMove,
     MaxPlayersReached,
     GameOver,
     OutOfTurn,
+    UnknownPlayer,
+    PlayerAlreadyJoined,
+    NothingToUndo,
+    NothingToRedo,
+    NoDrawOffer,
+    OutOfTime,
+    // Wild tic-tac-toe: the symbol isn't one the player may place
+    SymbolNotAllowed,
+    // Renju: the first player may not play there
+    ForbiddenMove(ForbiddenMove),
+    // Swap openings: a color has to be chosen before play goes on
+    ChoicePending,
+    SaveFailed(String),
+    LoadFailed(String),
     BoardError(BoardError),
 }
 
@@ -88,6 +305,17 @@ impl fmt::Display for GameError {
             GameError::MaxPlayersReached => write!(f, "Maximum number of players reached"),
             GameError::GameOver => write!(f, "Game is over"),
             GameError::OutOfTurn => write!(f, "Not your turn"),
+            GameError::UnknownPlayer => write!(f, "Player is not part of this game"),
+            GameError::PlayerAlreadyJoined => write!(f, "Player has already joined"),
+            GameError::NothingToUndo => write!(f, "Nothing to undo"),
+            GameError::NothingToRedo => write!(f, "Nothing to redo"),
+            GameError::NoDrawOffer => write!(f, "No draw offer to answer"),
+            GameError::OutOfTime => write!(f, "Out of time"),
+            GameError::SymbolNotAllowed => write!(f, "That symbol can't be placed in this game"),
+            GameError::ForbiddenMove(reason) => write!(f, "Forbidden move: {}", reason),
+            GameError::ChoicePending => write!(f, "A color has to be chosen first"),
+            GameError::SaveFailed(err) => write!(f, "Could not save game: {}", err),
+            GameError::LoadFailed(err) => write!(f, "Could not load game: {}", err),
             GameError::BoardError(err) => write!(f, "Board error: {}", err),
         }
     }
@@ -97,4 +325,88 @@ impl fmt::Display for GameError {
 pub enum PlayerError {
     NotFound,
     InvalidData,
+    InvalidSymbol,
+    SymbolTaken,
+}
+
+impl fmt::Display for PlayerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlayerError::NotFound => write!(f, "Player not found"),
+            PlayerError::InvalidData => write!(f, "Invalid player data"),
+            PlayerError::InvalidSymbol => write!(f, "Symbol must be a single visible character"),
+            PlayerError::SymbolTaken => write!(f, "Symbol is already used by another player"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CommandError {
+    Empty,
+    Unknown(String),
+    MissingArgument(&'static str),
+    InvalidCoordinates,
+    InvalidSymbol(String),
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::Empty => write!(f, "Please enter a move or a command"),
+            CommandError::Unknown(cmd) => {
+                write!(f, "Unknown command '{}', type 'help' for a list", cmd)
+            }
+            CommandError::MissingArgument(arg) => write!(f, "Missing argument: {}", arg),
+            CommandError::InvalidCoordinates => {
+                write!(f, "Moves are two numbers: row and column, e.g. '1 1'")
+            }
+            CommandError::InvalidSymbol(symbol) => write!(f, "'{}' is not a symbol", symbol),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShapeError {
+    Empty,
+    UnknownCell(char),
+    TooSmall(usize, usize),
+}
+
+impl fmt::Display for ShapeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShapeError::Empty => write!(f, "The shape has no cells"),
+            ShapeError::UnknownCell(c) => {
+                write!(
+                    f,
+                    "Unknown cell '{}', use '.' for playable and '#' for blocked",
+                    c
+                )
+            }
+            ShapeError::TooSmall(rows, cols) => write!(
+                f,
+                "Boards need at least 3 rows and 3 columns, the shape is {}x{}",
+                rows, cols
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EngineError {
+    UnknownCommand(String),
+    InvalidArgument(&'static str),
+    IllegalMove((usize, usize)),
+    NoLegalMoves,
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EngineError::UnknownCommand(cmd) => write!(f, "unknown command '{}'", cmd),
+            EngineError::InvalidArgument(arg) => write!(f, "invalid or missing {}", arg),
+            EngineError::IllegalMove((row, col)) => write!(f, "illegal move {},{}", row, col),
+            EngineError::NoLegalMoves => write!(f, "no legal moves"),
+        }
+    }
 }
@@ -1,11 +1,27 @@
-use crate::game::Game;
+use crate::command::Command;
+use crate::cube::CubeGame;
+use crate::game::{Game, OpeningStage};
 use crate::game_board::GameBoard;
-use crate::types::Difficulty;
+use crate::player::Player;
+use crate::render::{
+    clock_summary, cube_lines, opening_summary, ultimate_lines, BoardLayout, BoardMarks,
+};
+use crate::runner::{MoveProvider, PlayerAction, TurnContext};
+use crate::score_board::ScoreBoard;
+use crate::theme::Theme;
+use crate::types::{Difficulty, GameError, Symbol, SymbolSet};
+use crate::ultimate::UltimateGame;
 use std::io::{self, Write};
 use std::ops::RangeInclusive;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::Instant;
 
+#[derive(Clone)]
 pub struct UI {
-    // Add fields as necessary
+    symbol_set: SymbolSet,
+    theme: Theme,
 }
 
 impl Default for UI {
@@ -16,20 +32,84 @@ impl Default for UI {
 
 impl UI {
     pub fn new() -> Self {
+        Self::with_symbol_set(SymbolSet::default())
+    }
+
+    pub fn with_symbol_set(symbol_set: SymbolSet) -> Self {
         Self {
-            // Initialize fields as necessary
+            symbol_set,
+            theme: Theme::for_stdout(Theme::default()),
         }
     }
 
-    pub fn get_game_settings(&self) -> (usize, usize) {
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = Theme::for_stdout(theme);
+    }
+
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    pub fn symbol_set(&self) -> SymbolSet {
+        self.symbol_set
+    }
+
+    // Rows and columns of the board, and the number of players
+    pub fn get_game_settings(&self) -> ((usize, usize), usize) {
         println!("\nWelcome to Tic Tac Toe!");
         println!("------------------------");
 
-        let board_size = self.get_number_input("Enter board size (default: 3): ", 3, 3..=10);
+        let dimensions = self.get_dimensions_input(
+            "Enter board size, e.g. '3' or '4 5' for rows and columns (3-10, default: 3): ",
+            3,
+            3..=10,
+        );
+
+        (dimensions, self.get_player_count())
+    }
 
-        let num_players = self.get_number_input("Enter number of players (default: 2): ", 2, 2..=4);
+    pub fn get_player_count(&self) -> usize {
+        self.get_number_input("Enter number of players (default: 2): ", 2, 2..=4)
+    }
 
-        (board_size, num_players)
+    // Up to the longer side of the board
+    pub fn get_win_length(&self, (rows, cols): (usize, usize)) -> usize {
+        let longest = rows.max(cols);
+        let default = 3.min(longest);
+        let prompt = format!(
+            "Enter how many in a row win (3-{}, default: {}): ",
+            longest, default
+        );
+        self.get_number_input(&prompt, default, 3..=longest)
+    }
+
+    // Board size, players and win length for five in a row and Connect6,
+    // on a large board by default
+    pub fn get_connect_settings(&self, default_win: usize) -> ((usize, usize), usize, usize) {
+        let dimensions = self.get_dimensions_input(
+            "Enter board size, e.g. '15' or '15 19' for rows and columns (6-19, default: 15): ",
+            15,
+            6..=19,
+        );
+        let num_players = self.get_player_count();
+        let longest = dimensions.0.max(dimensions.1);
+        let prompt = format!(
+            "Enter how many in a row win (3-{}, default: {}): ",
+            longest, default_win
+        );
+        let win_length = self.get_number_input(&prompt, default_win, 3..=longest);
+        (dimensions, num_players, win_length)
+    }
+
+    // Size and win length of a 3D board, 4x4x4 with four in a row by default
+    pub fn get_cube_settings(&self) -> (usize, usize) {
+        let size = self.get_number_input("Enter cube size (default: 4): ", 4, 3..=6);
+        let prompt = format!(
+            "Enter how many in a row win (3-{}, default: {}): ",
+            size, size
+        );
+        let win_length = self.get_number_input(&prompt, size, 3..=size);
+        (size, win_length)
     }
 
     pub fn get_player_type(&self, player_num: usize) -> bool {
@@ -40,8 +120,7 @@ impl UI {
             );
             io::stdout().flush().unwrap();
 
-            let mut input = String::new();
-            io::stdin().read_line(&mut input).unwrap();
+            let input = read_input().unwrap_or_default();
             let input = input.trim();
 
             if input.is_empty() {
@@ -68,8 +147,7 @@ impl UI {
             );
             io::stdout().flush().unwrap();
 
-            let mut input = String::new();
-            io::stdin().read_line(&mut input).unwrap();
+            let input = read_input().unwrap_or_default();
             let input = input.trim();
 
             if input.is_empty() {
@@ -82,6 +160,33 @@ impl UI {
         }
     }
 
+    pub fn get_player_symbol(&self, player_num: usize, used_symbols: &[Symbol]) -> Symbol {
+        let default = self
+            .symbol_set
+            .pick_unique(used_symbols)
+            .expect("ran out of symbols");
+
+        loop {
+            print!(
+                "Enter symbol for Player {} [default: {}]: ",
+                player_num, default
+            );
+            io::stdout().flush().unwrap();
+
+            let input = read_input().unwrap_or_default();
+            let input = input.trim();
+
+            if input.is_empty() {
+                return default;
+            }
+
+            match Symbol::parse(input, used_symbols) {
+                Ok(symbol) => return symbol,
+                Err(e) => println!("Invalid symbol! {}.", e),
+            }
+        }
+    }
+
     pub fn get_ai_difficulty(&self) -> Difficulty {
         loop {
             println!("Select AI difficulty:");
@@ -91,8 +196,7 @@ impl UI {
             print!("Enter choice [1-3]: ");
             io::stdout().flush().unwrap();
 
-            let mut input = String::new();
-            io::stdin().read_line(&mut input).unwrap();
+            let input = read_input().unwrap_or_default();
             let input = input.trim();
 
             if input.is_empty() {
@@ -108,6 +212,74 @@ impl UI {
         }
     }
 
+    pub fn ask_draw_response(&self, player: &Player, offered_by: &Player) -> bool {
+        print!(
+            "{}, {} offers a draw. Accept? (y/N): ",
+            player.name(),
+            offered_by.name()
+        );
+        io::stdout().flush().unwrap();
+
+        let input = read_input().unwrap_or_default();
+        matches!(input.trim(), "y" | "Y" | "yes")
+    }
+
+    pub fn display_scores(&self, game: &Game, scores: &ScoreBoard) {
+        println!("Scores:");
+        for line in scores.summary(game.players()) {
+            println!("  {}", line);
+        }
+    }
+
+    pub fn get_pie_rule(&self) -> bool {
+        print!("Pie rule, the second player may take over the first move? (y/N): ");
+        io::stdout().flush().unwrap();
+
+        let input = read_input().unwrap_or_default();
+        matches!(input.trim(), "y" | "Y" | "yes")
+    }
+
+    pub fn ask_play_again(&self) -> bool {
+        print!("Play another round? (y/N): ");
+        io::stdout().flush().unwrap();
+
+        let input = read_input().unwrap_or_default();
+        matches!(input.trim(), "y" | "Y" | "yes")
+    }
+
+    // One number for a square board, or rows and columns as '4 5' or '4x5'
+    fn get_dimensions_input(
+        &self,
+        prompt: &str,
+        default: usize,
+        range: RangeInclusive<usize>,
+    ) -> (usize, usize) {
+        loop {
+            print!("{}", prompt);
+            io::stdout().flush().unwrap();
+
+            let input = read_input().unwrap_or_default();
+            let numbers: Result<Vec<usize>, _> = input
+                .split(|c: char| c.is_whitespace() || c == 'x' || c == 'X')
+                .filter(|part| !part.is_empty())
+                .map(str::parse)
+                .collect();
+
+            match numbers.as_deref() {
+                Ok([]) => return (default, default),
+                Ok(&[size]) if range.contains(&size) => return (size, size),
+                Ok(&[rows, cols]) if range.contains(&rows) && range.contains(&cols) => {
+                    return (rows, cols)
+                }
+                _ => println!(
+                    "Invalid input! Please enter one or two numbers between {} and {}.",
+                    range.start(),
+                    range.end()
+                ),
+            }
+        }
+    }
+
     fn get_number_input(
         &self,
         prompt: &str,
@@ -118,8 +290,7 @@ impl UI {
             print!("{}", prompt);
             io::stdout().flush().unwrap();
 
-            let mut input = String::new();
-            io::stdin().read_line(&mut input).unwrap();
+            let input = read_input().unwrap_or_default();
             let input = input.trim();
 
             if input.is_empty() {
@@ -138,7 +309,11 @@ impl UI {
     }
 
     pub fn display(&self, game: &Game) {
-        self.display_board(game.board());
+        let board = game.board();
+        let layout = BoardLayout::for_board(board, self.symbol_set.cell_width());
+        for line in layout.styled_lines(board, &self.theme, &BoardMarks::for_game(game)) {
+            println!("{}", line);
+        }
         if let Some(current) = game.current_player() {
             println!(
                 "\nCurrent player: {} ({})",
@@ -148,64 +323,214 @@ impl UI {
         } else {
             println!("\nNo current player");
         }
+        let (placed, total) = game.turn_progress();
+        if total > 1 && !game.is_over() {
+            println!("Stone {} of {} this turn", placed + 1, total);
+        }
+        if let Some(opening) = opening_summary(game).filter(|_| !game.is_over()) {
+            println!("{}", opening);
+        }
+        if game.rules().is_wild() {
+            let symbols: Vec<String> = game.rules().wild.iter().map(|s| s.to_string()).collect();
+            println!("Any player may place: {}", symbols.join(" "));
+        }
+        if let Some((row, col)) = game.next_to_vanish() {
+            println!("Next to vanish: {} {}", row, col);
+        }
+        if let Some(clocks) = clock_summary(game) {
+            println!("Time left: {}", clocks);
+        }
     }
 
     pub fn display_board(&self, board: &GameBoard) {
-        let size = board.size();
-        let border = "-".repeat(4 * size + 1);
+        let layout = BoardLayout::for_board(board, self.symbol_set.cell_width());
+        for line in layout.styled_lines(board, &self.theme, &BoardMarks::default()) {
+            println!("{}", line);
+        }
+    }
 
-        // Print column numbers
-        print!("   ");
-        for col in 0..size {
-            print!(" {}  ", col);
+    pub fn display_ultimate(&self, game: &UltimateGame) {
+        let marks = BoardMarks::for_ultimate(game);
+        for line in ultimate_lines(
+            game.board(),
+            self.symbol_set.cell_width(),
+            &self.theme,
+            &marks,
+        ) {
+            println!("{}", line);
+        }
+        if game.is_over() {
+            return;
+        }
+        if let [index] = game.board().active_boards()[..] {
+            println!("\nPlay in sub-board {} (marked with dots)", index);
+        } else {
+            println!("\nPlay in any open sub-board (marked with dots)");
+        }
+        if let Some(current) = game.current_player() {
+            println!("Current player: {} ({})", current.name(), current.symbol());
+        }
+    }
+
+    pub fn display_cube(&self, game: &CubeGame) {
+        for line in cube_lines(game, self.symbol_set.cell_width(), &self.theme) {
+            println!("{}", line);
+        }
+        if game.is_over() {
+            return;
+        }
+        if let Some(current) = game.current_player() {
+            println!(
+                "\nCurrent player: {} ({})",
+                current.name(),
+                current.symbol()
+            );
         }
-        println!();
+    }
 
-        println!("{}", border);
+    // One line of input, None once stdin is closed
+    pub fn read_line(&self, prompt: &str) -> Option<String> {
+        print!("{}", prompt);
+        io::stdout().flush().unwrap();
+        read_input()
+    }
 
-        for row in 0..size {
-            // Print row number
-            print!("{} |", row);
+    // Reads a move or a console command such as `undo` or `save <file>`
+    pub fn read_command(&self) -> Command {
+        self.read_command_until(None).unwrap_or(Command::Quit)
+    }
 
-            // Print cells
-            for col in 0..size {
-                match board.get_cell((row, col)) {
-                    Some(symbol) => print!(" {} |", symbol),
-                    None => print!("   |"),
-                }
+    // Gives up with None once `deadline` has passed
+    pub fn read_command_until(&self, deadline: Option<Instant>) -> Option<Command> {
+        loop {
+            print!("Enter your move (row col) or a command ('help' for a list): ");
+            io::stdout().flush().unwrap();
+
+            let input = match deadline {
+                Some(deadline) => match read_input_until(deadline) {
+                    Ok(input) => Some(input),
+                    Err(RecvTimeoutError::Timeout) => {
+                        println!();
+                        return None;
+                    }
+                    Err(RecvTimeoutError::Disconnected) => None,
+                },
+                None => read_input(),
+            };
+            let Some(input) = input else {
+                return Some(Command::Quit);
+            };
+
+            match Command::parse(&input) {
+                Ok(command) => return Some(command),
+                Err(e) => println!("{}", e),
             }
-            println!();
-            println!("{}", border);
         }
     }
+}
 
-    pub fn get_player_move(&self, game: &mut Game) -> (usize, usize) {
-        loop {
-            print!("Enter your move (row col): ");
-            io::stdout().flush().unwrap();
+// Lines from stdin, once a timed move has needed them read on a thread of
+// their own. From then on every prompt takes its input from there
+static INPUT: OnceLock<Mutex<Receiver<String>>> = OnceLock::new();
 
-            let mut input = String::new();
-            io::stdin().read_line(&mut input).unwrap();
+fn read_input() -> Option<String> {
+    if let Some(lines) = INPUT.get() {
+        return lines.lock().unwrap().recv().ok();
+    }
+    let mut input = String::new();
+    match io::stdin().read_line(&mut input) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(input),
+    }
+}
 
-            if let Some((row, col)) = self.parse_move(&input) {
-                if game.is_move_valid((row, col)) {
-                    return (row, col);
+fn read_input_until(deadline: Instant) -> Result<String, RecvTimeoutError> {
+    let lines = INPUT.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for line in io::stdin().lines() {
+                let Ok(line) = line else { break };
+                if sender.send(line).is_err() {
+                    break;
                 }
             }
+        });
+        Mutex::new(receiver)
+    });
+    lines
+        .lock()
+        .unwrap()
+        .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+}
 
-            println!("Invalid move! Please try again.");
+// Reads moves from stdin and answers the informational commands itself. A
+// clock that runs out while waiting ends the turn
+impl MoveProvider for UI {
+    fn next_action(&mut self, game: &Game, ctx: &TurnContext) -> PlayerAction {
+        loop {
+            let Some(command) = self.read_command_until(game.flag_deadline()) else {
+                return PlayerAction::TimeUp;
+            };
+            match command {
+                Command::Move(_) if game.rules().is_wild() => {
+                    println!(
+                        "Add the symbol to place, e.g. '1 1 {}'",
+                        game.rules().wild[0]
+                    );
+                }
+                Command::Move(pos) => {
+                    if game.is_move_valid(pos) {
+                        return PlayerAction::Move(pos);
+                    }
+                    match (game.opening_stage(), game.forbidden_move(pos)) {
+                        (OpeningStage::Choosing { .. }, _) => {
+                            println!("{}", GameError::ChoicePending)
+                        }
+                        (_, Some(reason)) => println!("{}", GameError::ForbiddenMove(reason)),
+                        _ => println!("Invalid move! Please try again."),
+                    }
+                }
+                Command::Choose(choice) => return PlayerAction::ChooseOpening(choice),
+                Command::Place(pos, symbol) => {
+                    if !game.placeable_symbols().contains(&symbol) {
+                        println!("{}", GameError::SymbolNotAllowed);
+                    } else if game.is_move_valid(pos) {
+                        return PlayerAction::Place(pos, symbol);
+                    } else {
+                        println!("Invalid move! Please try again.");
+                    }
+                }
+                Command::Undo => return PlayerAction::Undo,
+                Command::Redo => return PlayerAction::Redo,
+                Command::OfferDraw => return PlayerAction::OfferDraw,
+                Command::Resign => return PlayerAction::Resign,
+                Command::Quit => return PlayerAction::Quit,
+                Command::Hint => match game.hint_with_symbol() {
+                    Some(((row, col), symbol)) if game.rules().is_wild() => {
+                        println!("Hint: try {} {} {}", row, col, symbol)
+                    }
+                    Some(((row, col), _)) => println!("Hint: try {} {}", row, col),
+                    None => println!("No hint available."),
+                },
+                Command::Save(path) => match game.save(&path) {
+                    Ok(()) => println!("Game saved to {}, resume with --load {}", path, path),
+                    Err(e) => println!("{}", e),
+                },
+                Command::Board => self.display(game),
+                Command::Score => self.display_scores(game, ctx.scores),
+                Command::Help => println!("{}", Command::HELP),
+            }
         }
     }
 
-    fn parse_move(&self, input: &str) -> Option<(usize, usize)> {
-        let coords: Vec<&str> = input.split_whitespace().collect();
-        if coords.len() != 2 {
-            return None;
+    fn respond_to_draw(&mut self, game: &Game, ctx: &TurnContext) -> bool {
+        match (game.player(ctx.player), game.draw_offered_by()) {
+            (Some(player), Some(offered_by)) => self.ask_draw_response(player, offered_by),
+            _ => false,
         }
+    }
 
-        let row = coords[0].parse::<usize>().ok()?;
-        let col = coords[1].parse::<usize>().ok()?;
-
-        Some((row, col))
+    fn is_interactive(&self) -> bool {
+        true
     }
 }
@@ -0,0 +1,268 @@
+use crate::game_board::GameBoard;
+use crate::player::Player;
+use crate::types::{BoardError, DrawReason, GameError, GameStatus, Move, Symbol, WinReason};
+
+// Sub-boards per side, and cells per side of each sub-board
+pub const SUB_SIZE: usize = 3;
+// Cells per side of the whole grid
+pub const SIZE: usize = SUB_SIZE * SUB_SIZE;
+
+// Nine tic-tac-toe boards in a 3x3 grid. Positions are (row, col) on the
+// whole 9x9 grid; where a move lands inside its sub-board decides which
+// sub-board the next player has to play in. Won sub-boards become the
+// winner's cell on the meta-board, and three of those in a row win
+#[derive(Debug, Clone)]
+pub struct UltimateBoard {
+    boards: Vec<GameBoard>,
+    meta: GameBoard,
+    // None when the next player may pick any open sub-board
+    next_board: Option<usize>,
+}
+
+impl Default for UltimateBoard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UltimateBoard {
+    pub fn new() -> Self {
+        Self {
+            boards: vec![GameBoard::new(SUB_SIZE); SUB_SIZE * SUB_SIZE],
+            meta: GameBoard::new(SUB_SIZE),
+            next_board: None,
+        }
+    }
+
+    // Sub-boards are numbered row by row, 0 to 8
+    pub fn sub_board_of(pos: (usize, usize)) -> usize {
+        (pos.0 / SUB_SIZE) * SUB_SIZE + pos.1 / SUB_SIZE
+    }
+
+    fn local(pos: (usize, usize)) -> (usize, usize) {
+        (pos.0 % SUB_SIZE, pos.1 % SUB_SIZE)
+    }
+
+    fn meta_cell(index: usize) -> (usize, usize) {
+        (index / SUB_SIZE, index % SUB_SIZE)
+    }
+
+    // Position on the whole grid of a cell in a sub-board
+    pub fn global(index: usize, local: (usize, usize)) -> (usize, usize) {
+        let (meta_row, meta_col) = Self::meta_cell(index);
+        (meta_row * SUB_SIZE + local.0, meta_col * SUB_SIZE + local.1)
+    }
+
+    pub fn sub_board(&self, index: usize) -> &GameBoard {
+        &self.boards[index]
+    }
+
+    pub fn meta(&self) -> &GameBoard {
+        &self.meta
+    }
+
+    pub fn get_cell(&self, pos: (usize, usize)) -> Option<Symbol> {
+        if pos.0 >= SIZE || pos.1 >= SIZE {
+            return None;
+        }
+        self.boards[Self::sub_board_of(pos)].get_cell(Self::local(pos))
+    }
+
+    // Who won a sub-board, if anyone
+    pub fn sub_board_winner(&self, index: usize) -> Option<Symbol> {
+        self.meta.get_cell(Self::meta_cell(index))
+    }
+
+    // Won or full, either way nobody can play there anymore
+    pub fn is_decided(&self, index: usize) -> bool {
+        self.sub_board_winner(index).is_some() || self.boards[index].is_full()
+    }
+
+    // Sub-boards the next move may go to
+    pub fn active_boards(&self) -> Vec<usize> {
+        if self.winner_symbol().is_some() {
+            return Vec::new();
+        }
+        match self.next_board {
+            Some(index) if !self.is_decided(index) => vec![index],
+            _ => (0..self.boards.len())
+                .filter(|&index| !self.is_decided(index))
+                .collect(),
+        }
+    }
+
+    pub fn legal_moves(&self) -> Vec<(usize, usize)> {
+        self.active_boards()
+            .into_iter()
+            .flat_map(|index| {
+                self.boards[index]
+                    .empty_positions()
+                    .into_iter()
+                    .map(move |local| Self::global(index, local))
+            })
+            .collect()
+    }
+
+    pub fn check_move(&self, pos: (usize, usize)) -> Result<(), BoardError> {
+        if pos.0 >= SIZE || pos.1 >= SIZE {
+            return Err(BoardError::OutOfBounds);
+        }
+        if self.get_cell(pos).is_some() {
+            return Err(BoardError::CellOccupied);
+        }
+        if !self.active_boards().contains(&Self::sub_board_of(pos)) {
+            return Err(BoardError::InactiveSubBoard);
+        }
+        Ok(())
+    }
+
+    pub fn is_move_valid(&self, pos: (usize, usize)) -> bool {
+        self.check_move(pos).is_ok()
+    }
+
+    pub fn apply_move(&mut self, pos: (usize, usize), symbol: Symbol) -> Result<(), BoardError> {
+        self.check_move(pos)?;
+        let index = Self::sub_board_of(pos);
+        let local = Self::local(pos);
+        self.boards[index].apply_move(local, symbol)?;
+        if self.boards[index].winner_symbol() == Some(symbol) {
+            self.meta.apply_move(Self::meta_cell(index), symbol)?;
+        } else if self.boards[index].is_full() {
+            // A drawn sub-board counts for nobody on the meta-board
+            self.meta.block(Self::meta_cell(index))?;
+        }
+        self.next_board = Some(local.0 * SUB_SIZE + local.1);
+        Ok(())
+    }
+
+    pub fn winner_symbol(&self) -> Option<Symbol> {
+        self.meta.winner_symbol()
+    }
+
+    // Winning lines of the sub-boards and the meta-board, on the whole grid
+    pub fn winning_cells(&self) -> Vec<(usize, usize)> {
+        let mut cells = Vec::new();
+        for (index, board) in self.boards.iter().enumerate() {
+            if let Some(line) = board.winning_line(SUB_SIZE) {
+                cells.extend(line.into_iter().map(|local| Self::global(index, local)));
+            }
+        }
+        cells
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.winner_symbol().is_some() || self.legal_moves().is_empty()
+    }
+
+    // Symbols of everyone who has played so far
+    pub fn get_all_symbols(&self) -> Vec<Symbol> {
+        let mut symbols = Vec::new();
+        for board in &self.boards {
+            for symbol in board.get_all_symbols() {
+                if !symbols.contains(&symbol) {
+                    symbols.push(symbol);
+                }
+            }
+        }
+        symbols
+    }
+}
+
+// Two players taking turns on an `UltimateBoard`
+#[derive(Debug, Clone, Default)]
+pub struct UltimateGame {
+    board: UltimateBoard,
+    players: Vec<Player>,
+    current_player_idx: usize,
+    history: Vec<Move>,
+}
+
+impl UltimateGame {
+    pub const MAX_PLAYERS: usize = 2;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_player(&mut self, player: &Player) -> Result<(), GameError> {
+        if self.players.len() >= Self::MAX_PLAYERS {
+            return Err(GameError::MaxPlayersReached);
+        }
+        if self.players.iter().any(|p| p.id() == player.id()) {
+            return Err(GameError::PlayerAlreadyJoined);
+        }
+        self.players.push(player.clone());
+        Ok(())
+    }
+
+    pub fn board(&self) -> &UltimateBoard {
+        &self.board
+    }
+
+    pub fn players(&self) -> &[Player] {
+        &self.players
+    }
+
+    pub fn current_player(&self) -> Option<&Player> {
+        self.players.get(self.current_player_idx)
+    }
+
+    pub fn history(&self) -> &[Move] {
+        &self.history
+    }
+
+    pub fn last_move(&self) -> Option<Move> {
+        self.history.last().cloned()
+    }
+
+    pub fn is_move_valid(&self, pos: (usize, usize)) -> bool {
+        !self.is_over() && self.board.is_move_valid(pos)
+    }
+
+    pub fn make_move(&mut self, pos: (usize, usize)) -> Result<(), GameError> {
+        if self.players.len() < Self::MAX_PLAYERS {
+            return Err(GameError::InvalidMove);
+        }
+        if self.is_over() {
+            return Err(GameError::GameOver);
+        }
+        let player = &self.players[self.current_player_idx];
+        let (symbol, id) = (player.symbol(), player.id());
+        self.board.apply_move(pos, symbol)?;
+        self.history.push(Move {
+            position: pos,
+            symbol,
+            player: id,
+        });
+        self.current_player_idx = (self.current_player_idx + 1) % self.players.len();
+        Ok(())
+    }
+
+    // The move the current player's AI would play, None for humans
+    pub fn ai_move(&self) -> Option<(usize, usize)> {
+        let ai = self.current_player()?.ai.as_ref()?;
+        if self.is_over() {
+            return None;
+        }
+        Some(ai.decide_ultimate_move(&self.board))
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.board.is_over()
+    }
+
+    pub fn winner(&self) -> Option<&Player> {
+        let symbol = self.board.winner_symbol()?;
+        self.players.iter().find(|p| p.symbol() == symbol)
+    }
+
+    pub fn status(&self) -> GameStatus {
+        if let Some(winner) = self.winner() {
+            GameStatus::Win(winner.name().to_string(), WinReason::Streak)
+        } else if self.is_over() {
+            GameStatus::Draw(DrawReason::BoardFull)
+        } else {
+            GameStatus::InProgress
+        }
+    }
+}
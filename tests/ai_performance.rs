@@ -1,5 +1,6 @@
 use rust_tic_tac_toe::game::Game;
 use rust_tic_tac_toe::player::Player;
+use rust_tic_tac_toe::runner::{GameRunner, ScriptedProvider};
 use rust_tic_tac_toe::types::{Difficulty, Symbol};
 use std::collections::HashMap;
 
@@ -99,27 +100,21 @@ fn test_three_player_ai_performance() {
 
             // Play the game
             game.randomize_turn();
-            while !game.is_over() {
-                if let Some(current_player) = game.current_player() {
-                    if let Some(ref ai) = current_player.ai {
-                        let mv = ai.decide_move(game.board());
-                        if let Err(e) = game.make_move(mv) {
-                            println!("Error making move: {}", e);
-                            break;
-                        }
-                    }
-                } else {
-                    break;
-                }
-            }
+            let result = GameRunner::for_game(&game, ScriptedProvider::default())
+                .run(&mut game)
+                .unwrap();
+            assert!(!result.quit);
 
             // Record results
-            if let Some(winner) = game.winner() {
-                combination_stats.record_win(&winner.name);
-                stats.record_win(&winner.name);
-            } else {
-                combination_stats.record_draw();
-                stats.record_draw();
+            match result.winner.and_then(|id| game.player(id)) {
+                Some(winner) => {
+                    combination_stats.record_win(&winner.name);
+                    stats.record_win(&winner.name);
+                }
+                None => {
+                    combination_stats.record_draw();
+                    stats.record_draw();
+                }
             }
         }
 
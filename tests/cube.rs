@@ -0,0 +1,106 @@
+use rust_tic_tac_toe::ai::AI;
+use rust_tic_tac_toe::cube::{CubeBoard, CubeGame};
+use rust_tic_tac_toe::player::Player;
+use rust_tic_tac_toe::render::{cube_lines, display_width};
+use rust_tic_tac_toe::theme::Theme;
+use rust_tic_tac_toe::types::{BoardError, Difficulty, GameError, GameStatus, Symbol, WinReason};
+
+fn x() -> Symbol {
+    Symbol::parse("X", &[]).unwrap()
+}
+
+fn o() -> Symbol {
+    Symbol::parse("O", &[]).unwrap()
+}
+
+#[test]
+fn test_cube_lines() {
+    assert_eq!(CubeBoard::new(4, 4).lines().len(), 76);
+    assert_eq!(CubeBoard::new(3, 3).lines().len(), 49);
+    // Shorter lines fit in more places: 2 per row and pillar, and more
+    // diagonals
+    assert!(CubeBoard::new(4, 3).lines().len() > 76);
+
+    // A space diagonal from one corner of the cube to the other
+    let mut board = CubeBoard::new(4, 4);
+    for i in 0..4 {
+        assert_eq!(board.winner_symbol(), None);
+        board.apply_move((i, i, 3 - i), x()).unwrap();
+    }
+    assert_eq!(board.winner_symbol(), Some(x()));
+    assert_eq!(board.winning_line().unwrap().len(), 4);
+
+    assert_eq!(CubeBoard::parse_position(" 1 2 3\n"), Some((1, 2, 3)));
+    assert_eq!(CubeBoard::parse_position("1 2"), None);
+    assert_eq!(board.check_move((4, 0, 0)), Err(BoardError::OutOfBounds));
+    assert_eq!(board.check_move((0, 0, 3)), Err(BoardError::CellOccupied));
+}
+
+#[test]
+fn test_qubic_game_and_ai() {
+    let mut game = CubeGame::qubic();
+    game.add_player(&Player::new("Ann".to_string(), x(), true, None))
+        .unwrap();
+    assert!(matches!(
+        game.make_move((0, 0, 0)),
+        Err(GameError::InvalidMove)
+    ));
+    game.add_player(&Player::new("Bob".to_string(), o(), true, None))
+        .unwrap();
+
+    // Ann fills a pillar while Bob plays elsewhere
+    for (layer, reply) in [(0, (0, 3, 0)), (1, (2, 0, 3)), (2, (1, 0, 0))] {
+        game.make_move((layer, 1, 2)).unwrap();
+        game.make_move(reply).unwrap();
+    }
+    // The AI finishes the pillar for Ann, or blocks it for Bob
+    for symbol in [x(), o()] {
+        let ai = AI::new(Difficulty::Hard, symbol);
+        assert_eq!(ai.decide_cube_move(game.board()), (3, 1, 2));
+    }
+    game.make_move((3, 1, 2)).unwrap();
+    assert_eq!(
+        game.status(),
+        GameStatus::Win("Ann".to_string(), WinReason::Streak)
+    );
+    assert!(matches!(
+        game.make_move((3, 3, 3)),
+        Err(GameError::GameOver)
+    ));
+
+    let lines = cube_lines(&game, 1, &Theme::plain());
+    assert_eq!(
+        lines
+            .iter()
+            .filter(|line| line.starts_with("Layer"))
+            .count(),
+        4
+    );
+    let grid: Vec<&String> = lines.iter().filter(|line| line.contains('|')).collect();
+    assert_eq!(grid.len(), 16);
+    assert!(grid
+        .iter()
+        .all(|line| display_width(line) == display_width(grid[0])));
+
+    for difficulty in [Difficulty::Easy, Difficulty::Hard] {
+        let mut game = CubeGame::new(3, 3);
+        game.add_player(&Player::new(
+            "One".to_string(),
+            x(),
+            false,
+            Some(difficulty),
+        ))
+        .unwrap();
+        game.add_player(&Player::new(
+            "Two".to_string(),
+            o(),
+            false,
+            Some(Difficulty::Medium),
+        ))
+        .unwrap();
+        while let Some(pos) = game.ai_move() {
+            game.make_move(pos).unwrap();
+        }
+        assert!(game.is_over());
+    }
+}
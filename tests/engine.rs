@@ -0,0 +1,139 @@
+use rust_tic_tac_toe::engine::{Engine, EngineCommand, EngineProvider, Position};
+use rust_tic_tac_toe::game::Game;
+use rust_tic_tac_toe::player::Player;
+use rust_tic_tac_toe::rules::GameRules;
+use rust_tic_tac_toe::runner::{GameRunner, ScriptedProvider};
+use rust_tic_tac_toe::types::{EngineError, Symbol};
+use std::io::ErrorKind;
+use std::time::{Duration, Instant};
+
+fn session(input: &str) -> Vec<String> {
+    let mut output = Vec::new();
+    Engine::new().run(input.as_bytes(), &mut output).unwrap();
+    String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn test_engine_protocol() {
+    assert_eq!(
+        EngineCommand::parse("go movetime 250"),
+        Ok(EngineCommand::Go {
+            movetime: Some(Duration::from_millis(250))
+        })
+    );
+    assert_eq!(
+        EngineCommand::parse("go fast"),
+        Err(EngineError::UnknownCommand("go fast".to_string()))
+    );
+
+    // Player 1 has to block the top row
+    let replies = session(
+        "isready\n\
+         setoption name Difficulty value hard\n\
+         position size 3 win 3 turn 1 moves 0:0,0 1:1,1 0:0,1\n\
+         go movetime 200\n\
+         position size 3 cols 5 win 3 turn 1 moves 0:0,0 1:1,2 0:1,0\n\
+         go movetime 200\n\
+         position size 3 moves 0:1,1 1:1,1\n\
+         setoption name speed value 11\n\
+         quit\n\
+         isready\n",
+    );
+    assert_eq!(
+        replies,
+        [
+            "readyok",
+            "bestmove 0,2",
+            "bestmove 2,0",
+            "error illegal move 1,1",
+            "error invalid or missing option name",
+        ]
+    );
+
+    // Positions are replayed moves, which rules like vanishing marks or
+    // blocked cells don't fit
+    assert!(Position::supports(&GameRules::default()));
+    let vanishing = GameRules {
+        max_marks: Some(3),
+        ..GameRules::default()
+    };
+    assert!(!Position::supports(&vanishing));
+    let blocked = GameRules {
+        blocked: vec![(1, 1)],
+        ..GameRules::default()
+    };
+    assert!(!Position::supports(&blocked));
+}
+
+#[test]
+fn test_external_engine_plays_a_game() {
+    let mut game = Game::new(3, 2);
+    game.add_player(&Player::new("Ann".to_string(), Symbol('X'), true, None))
+        .unwrap();
+    game.add_player(&Player::new("Bot".to_string(), Symbol('O'), true, None))
+        .unwrap();
+    let (ann, bot) = (game.players()[0].id(), game.players()[1].id());
+
+    let mut engine = EngineProvider::spawn(env!("CARGO_BIN_EXE_tic-tac-toe"), &["engine"]).unwrap();
+    engine.set_option("difficulty", "hard").unwrap();
+    assert!(engine.set_option("difficulty", "godlike").is_err());
+    engine.set_move_time(Duration::from_millis(200));
+
+    game.make_move((0, 0)).unwrap();
+    game.make_move((1, 1)).unwrap();
+    game.make_move((0, 1)).unwrap();
+    assert_eq!(
+        Position::of(&game).to_string(),
+        "position size 3 win 3 turn 1 moves 0:0,0 1:1,1 0:0,1"
+    );
+    assert_eq!(
+        engine.best_move(&game, Duration::from_millis(200)).unwrap(),
+        (0, 2)
+    );
+
+    // Driven by the runner, the engine blocks every line Ann tries
+    let mut runner = GameRunner::new();
+    runner.set_provider(
+        ann,
+        ScriptedProvider::new(&[(2, 0), (1, 0), (2, 2), (2, 1)]),
+    );
+    runner.set_provider(bot, engine);
+    let result = runner.run(&mut game).unwrap();
+    assert_ne!(result.winner, Some(ann));
+    assert!(game.is_over() || result.quit);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_silent_engine_forfeits() {
+    let mut game = Game::new(3, 2);
+    game.add_player(&Player::new("Ann".to_string(), Symbol('X'), true, None))
+        .unwrap();
+    game.add_player(&Player::new("Bot".to_string(), Symbol('O'), true, None))
+        .unwrap();
+    let (ann, bot) = (game.players()[0].id(), game.players()[1].id());
+
+    // Ready, but never a move
+    let script = r#"while read line; do [ "$line" = isready ] && echo readyok; done"#;
+    let mut engine = EngineProvider::spawn("sh", &["-c", script]).unwrap();
+    engine.set_move_time(Duration::from_millis(10));
+    let started = Instant::now();
+    assert_eq!(
+        engine
+            .best_move(&game, Duration::from_millis(10))
+            .unwrap_err()
+            .kind(),
+        ErrorKind::TimedOut
+    );
+    assert!(started.elapsed() < Duration::from_secs(5));
+
+    let mut runner = GameRunner::new();
+    runner.set_provider(ann, ScriptedProvider::new(&[(0, 0)]));
+    runner.set_provider(bot, engine);
+    let result = runner.run(&mut game).unwrap();
+    assert_eq!(result.winner, Some(ann));
+}
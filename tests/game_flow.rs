@@ -0,0 +1,1283 @@
+use rust_tic_tac_toe::ai::AI;
+use rust_tic_tac_toe::command::Command;
+use rust_tic_tac_toe::events::GameEvent;
+use rust_tic_tac_toe::game::{Game, OpeningStage};
+use rust_tic_tac_toe::game_board::GameBoard;
+use rust_tic_tac_toe::gomoku;
+use rust_tic_tac_toe::player::Player;
+use rust_tic_tac_toe::render::BoardMarks;
+use rust_tic_tac_toe::rules::{
+    mirrored_stones, DeadPositionCheck, FlagFall, GameRules, GomokuRule, Opening, TimeControl,
+};
+use rust_tic_tac_toe::runner::{
+    GameRunner, MoveProvider, PlayerAction, ScriptedProvider, StepOutcome, TurnContext,
+};
+use rust_tic_tac_toe::score_board::ScoreBoard;
+use rust_tic_tac_toe::shape::BoardShape;
+use rust_tic_tac_toe::spectator::{describe_event, evaluate, history};
+use rust_tic_tac_toe::types::{
+    BoardError, CommandError, Difficulty, DrawReason, ForbiddenMove, GameError, GameStatus,
+    OpeningChoice, ShapeError, Symbol, WinReason,
+};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn two_player_game() -> Game {
+    let mut game = Game::new(3, 2);
+    game.add_player(&Player::new("Ann".to_string(), Symbol('X'), true, None))
+        .unwrap();
+    game.add_player(&Player::new("Bob".to_string(), Symbol('O'), true, None))
+        .unwrap();
+    game
+}
+
+#[test]
+fn test_command_parsing() {
+    assert_eq!(Command::parse(" 1 2 "), Ok(Command::Move((1, 2))));
+    assert_eq!(Command::parse("UNDO"), Ok(Command::Undo));
+    assert_eq!(Command::parse("offer draw"), Ok(Command::OfferDraw));
+    assert_eq!(
+        Command::parse("save my game.json"),
+        Ok(Command::Save("my game.json".to_string()))
+    );
+    assert_eq!(
+        Command::parse("save"),
+        Err(CommandError::MissingArgument("file name"))
+    );
+    assert_eq!(Command::parse("1"), Err(CommandError::InvalidCoordinates));
+    assert_eq!(
+        Command::parse("dance"),
+        Err(CommandError::Unknown("dance".to_string()))
+    );
+    assert_eq!(Command::parse("   "), Err(CommandError::Empty));
+}
+
+#[test]
+fn test_undo_redo_restores_turns() {
+    let mut game = two_player_game();
+    assert!(matches!(game.undo(), Err(GameError::NothingToUndo)));
+
+    game.make_move((0, 0)).unwrap();
+    game.make_move((1, 1)).unwrap();
+    assert_eq!(game.undo().unwrap().position, (1, 1));
+    assert_eq!(game.current_player().unwrap().name(), "Bob");
+    assert!(game.board().get_cell((1, 1)).is_none());
+
+    assert_eq!(game.redo().unwrap().position, (1, 1));
+    assert_eq!(game.current_player().unwrap().name(), "Ann");
+    assert!(matches!(game.redo(), Err(GameError::NothingToRedo)));
+
+    // A new move discards the redo history
+    game.undo().unwrap();
+    game.make_move((2, 2)).unwrap();
+    assert!(!game.can_redo());
+}
+
+#[test]
+fn test_save_and_load_round_trip() {
+    let mut game = two_player_game();
+    game.make_move((0, 0)).unwrap();
+    game.make_move((0, 1)).unwrap();
+    game.make_move((1, 1)).unwrap();
+
+    // A directory of its own so parallel test runs don't share the file
+    let dir = std::env::temp_dir().join(format!(
+        "rust_tic_tac_toe_{}_{}",
+        std::process::id(),
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos()
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("round_trip.json");
+    game.save(&path).unwrap();
+    let loaded = Game::load(&path).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(loaded.history(), game.history());
+    assert_eq!(loaded.current_player().unwrap().name(), "Bob");
+    assert_eq!(loaded.board().get_cell((1, 1)), Some(Symbol('X')));
+    assert!(matches!(Game::load(&path), Err(GameError::LoadFailed(_))));
+}
+
+#[test]
+fn test_resign_and_draw_agreement() {
+    let mut game = two_player_game();
+    let bob = game.players()[1].id();
+    game.offer_draw().unwrap();
+    game.decline_draw(bob).unwrap();
+    assert!(!game.is_over());
+    assert!(matches!(game.accept_draw(bob), Err(GameError::NoDrawOffer)));
+
+    game.offer_draw().unwrap();
+    game.accept_draw(bob).unwrap();
+    assert!(game.is_over());
+    assert!(game.winner().is_none());
+
+    let mut game = two_player_game();
+    game.resign().unwrap();
+    assert!(game.is_over());
+    assert_eq!(game.winner().unwrap().name(), "Bob");
+}
+
+#[test]
+fn test_multi_player_resignation_and_scores() {
+    let mut game = Game::new(5, 3);
+    for (name, symbol) in [("Ann", 'X'), ("Bob", 'O'), ("Cid", 'A')] {
+        game.add_player(&Player::new(name.to_string(), Symbol(symbol), true, None))
+            .unwrap();
+    }
+
+    game.make_move((0, 0)).unwrap();
+    game.resign().unwrap(); // Bob
+    assert_eq!(game.status(), GameStatus::InProgress);
+    assert_eq!(game.current_player().unwrap().name(), "Cid");
+    assert_eq!(game.board().get_cell((0, 0)), Some(Symbol('X')));
+
+    // Bob is skipped from now on
+    game.make_move((4, 4)).unwrap();
+    assert_eq!(game.current_player().unwrap().name(), "Ann");
+    game.make_move((0, 1)).unwrap();
+    assert_eq!(game.current_player().unwrap().name(), "Cid");
+
+    // Draw between the remaining players, Bob still loses
+    game.offer_draw().unwrap();
+    game.accept_draw(game.players()[0].id()).unwrap();
+    assert_eq!(game.status(), GameStatus::Draw(DrawReason::Agreement));
+
+    let mut scores = ScoreBoard::new();
+    scores.record_game(&game);
+    let stats = |idx: usize| scores.get_stats(&game.players()[idx]).unwrap();
+    assert_eq!(
+        (stats(0).draws(), stats(1).losses(), stats(2).draws()),
+        (1, 1, 1)
+    );
+
+    let ids: Vec<_> = game.players().iter().map(|p| p.id()).collect();
+    game.prepare_next_round();
+    game.resign_player(ids[0]).unwrap();
+    game.resign_player(ids[1]).unwrap();
+    assert_eq!(
+        game.status(),
+        GameStatus::Win("Cid".to_string(), WinReason::Resignation)
+    );
+    assert!(matches!(
+        game.resign_player(ids[2]),
+        Err(GameError::GameOver)
+    ));
+}
+
+#[test]
+fn test_dead_position_ends_game_early() {
+    // X O X
+    // X O O
+    // O X .   <- nobody can complete a line through the last cell
+    let rules = GameRules {
+        dead_position: DeadPositionCheck::OpenLines,
+        ..GameRules::default()
+    };
+    let mut game = Game::with_rules(3, 2, rules);
+    game.add_player(&Player::new("Ann".to_string(), Symbol('X'), true, None))
+        .unwrap();
+    game.add_player(&Player::new("Bob".to_string(), Symbol('O'), true, None))
+        .unwrap();
+    // By default the game goes on until the board is full
+    let mut default_rules = two_player_game();
+    for pos in [
+        (0, 0),
+        (0, 1),
+        (0, 2),
+        (1, 1),
+        (1, 0),
+        (1, 2),
+        (2, 1),
+        (2, 0),
+    ] {
+        assert!(!game.is_over(), "ended before {:?}", pos);
+        game.make_move(pos).unwrap();
+        default_rules.make_move(pos).unwrap();
+    }
+    assert_eq!(game.status(), GameStatus::Draw(DrawReason::NoWinPossible));
+    assert!(!game.board().is_full());
+    assert_eq!(default_rules.status(), GameStatus::InProgress);
+
+    // Counting turns ends games sooner: X gets two of the last four cells,
+    // too few to fill the open bottom row
+    let rules = GameRules {
+        dead_position: DeadPositionCheck::MovesLeft,
+        ..GameRules::with_win_length(4)
+    };
+    let mut game = Game::with_rules(4, 2, rules.clone());
+    let mut open_lines = Game::with_rules(
+        4,
+        2,
+        GameRules {
+            dead_position: DeadPositionCheck::OpenLines,
+            ..rules
+        },
+    );
+    for game in [&mut game, &mut open_lines] {
+        game.add_player(&Player::new("Ann".to_string(), Symbol('X'), true, None))
+            .unwrap();
+        game.add_player(&Player::new("Bob".to_string(), Symbol('O'), true, None))
+            .unwrap();
+        // X O X O
+        // O X O X
+        // O X O X
+        // . . . .   <- row 3 still open for X, but X needs four more
+        for pos in COUNTED_MOVES {
+            game.make_move(pos).unwrap();
+        }
+    }
+    assert_eq!(game.moves_left(game.players()[0].id()), 2);
+    assert_eq!(game.status(), GameStatus::Draw(DrawReason::NoWinPossible));
+    assert_eq!(open_lines.status(), GameStatus::InProgress);
+}
+
+const COUNTED_MOVES: [(usize, usize); 12] = [
+    (0, 0),
+    (0, 1),
+    (0, 2),
+    (0, 3),
+    (1, 1),
+    (1, 0),
+    (1, 3),
+    (1, 2),
+    (2, 1),
+    (2, 0),
+    (2, 3),
+    (2, 2),
+];
+
+#[test]
+fn test_counted_moves_end_the_game_at_the_last_playable_turn() {
+    let rules = GameRules {
+        dead_position: DeadPositionCheck::MovesLeft,
+        ..GameRules::with_win_length(4)
+    };
+    let mut game = Game::with_rules(4, 2, rules);
+    game.add_player(&Player::new("Ann".to_string(), Symbol('X'), true, None))
+        .unwrap();
+    game.add_player(&Player::new("Bob".to_string(), Symbol('O'), true, None))
+        .unwrap();
+    let (last, rest) = COUNTED_MOVES.split_last().unwrap();
+    for &pos in rest {
+        game.make_move(pos).unwrap();
+        assert_eq!(game.status(), GameStatus::InProgress, "ended at {:?}", pos);
+    }
+    game.make_move(*last).unwrap();
+    assert_eq!(game.history().len(), 12);
+    assert_eq!(game.status(), GameStatus::Draw(DrawReason::NoWinPossible));
+    assert_eq!(game.make_move((3, 0)), Err(GameError::GameOver));
+}
+
+#[test]
+fn test_moves_are_checked_against_turn_owner() {
+    let mut game = two_player_game();
+    let ann = game.players()[0].id();
+    let bob = game.players()[1].id();
+    let stranger = Player::new("Ann".to_string(), Symbol('Z'), true, None);
+
+    assert!(matches!(
+        game.make_move_as(bob, (0, 0)),
+        Err(GameError::OutOfTurn)
+    ));
+    assert!(matches!(
+        game.make_move_as(stranger.id(), (0, 0)),
+        Err(GameError::UnknownPlayer)
+    ));
+    assert!(matches!(
+        game.add_player(&game.players()[0].clone()),
+        Err(GameError::MaxPlayersReached)
+    ));
+
+    for (player, pos) in [(ann, (0, 0)), (bob, (1, 0)), (ann, (0, 1)), (bob, (1, 1))] {
+        game.make_move_as(player, pos).unwrap();
+    }
+    game.make_move_as(ann, (0, 2)).unwrap();
+    assert_eq!(game.winner().unwrap().id(), ann);
+    assert_eq!(game.history()[1].player, bob);
+    assert!(matches!(
+        game.make_move_as(bob, (2, 2)),
+        Err(GameError::GameOver)
+    ));
+    assert!(matches!(game.make_move((2, 2)), Err(GameError::GameOver)));
+}
+
+#[test]
+fn test_players_keep_their_identity() {
+    let mut game = Game::new(3, 3);
+    let ann = Player::new("Ann".to_string(), Symbol('X'), true, None);
+    game.add_player(&ann).unwrap();
+    assert!(matches!(
+        game.add_player(&ann),
+        Err(GameError::PlayerAlreadyJoined)
+    ));
+
+    // Same name, different player
+    let other_ann = Player::new("Ann".to_string(), Symbol('O'), true, None);
+    assert_ne!(ann.id(), other_ann.id());
+    game.add_player(&other_ann).unwrap();
+
+    let loaded = Game::from_snapshot(&game.snapshot()).unwrap();
+    assert_eq!(loaded.players()[0].id(), ann.id());
+    assert_eq!(loaded.players()[1].id(), other_ann.id());
+}
+
+#[test]
+fn test_game_events_reach_observers_and_subscribers() {
+    let mut game = Game::new(3, 2);
+    let events = game.subscribe();
+    let wins = Arc::new(Mutex::new(Vec::new()));
+    let observed = Arc::clone(&wins);
+    game.add_observer(move |game: &Game, event: &GameEvent| {
+        if let GameEvent::GameWon { winner, .. } = event {
+            // Observers see the game after the event was applied
+            assert!(game.is_over());
+            observed.lock().unwrap().push(*winner);
+        }
+    });
+
+    let ann = Player::new("Ann".to_string(), Symbol('X'), true, None);
+    let bob = Player::new("Bob".to_string(), Symbol('O'), true, None);
+    game.add_player(&ann).unwrap();
+    game.add_player(&bob).unwrap();
+    for pos in [(0, 0), (1, 0), (0, 1), (1, 1), (0, 2)] {
+        game.make_move(pos).unwrap();
+    }
+
+    let events: Vec<GameEvent> = events.try_iter().collect();
+    assert!(matches!(events[0], GameEvent::PlayerJoined { player, .. } if player == ann.id()));
+    assert!(events.contains(&GameEvent::TurnChanged { player: bob.id() }));
+    assert!(events.contains(&GameEvent::ThreatCreated {
+        player: ann.id(),
+        positions: vec![(0, 2)],
+    }));
+    assert_eq!(
+        events.last(),
+        Some(&GameEvent::GameWon {
+            winner: ann.id(),
+            reason: WinReason::Streak,
+        })
+    );
+    assert_eq!(
+        events
+            .iter()
+            .filter(|e| matches!(e, GameEvent::MovePlayed(_)))
+            .count(),
+        5
+    );
+    assert_eq!(*wins.lock().unwrap(), vec![ann.id()]);
+
+    game.prepare_next_round();
+    assert_eq!(game.round(), 2);
+}
+
+#[test]
+fn test_threats_are_reported_when_created() {
+    let mut game = two_player_game();
+    let ann = game.players()[0].id();
+    let events = game.subscribe();
+    // X X .   <- Ann threatens the top row once, not again after (2, 0)
+    // O . .
+    // X . O
+    for pos in [(0, 0), (1, 0), (0, 1), (2, 2), (2, 0)] {
+        game.make_move(pos).unwrap();
+    }
+    let threats: Vec<GameEvent> = events
+        .try_iter()
+        .filter(|e| matches!(e, GameEvent::ThreatCreated { .. }))
+        .collect();
+    assert_eq!(
+        threats,
+        [GameEvent::ThreatCreated {
+            player: ann,
+            positions: vec![(0, 2)],
+        }]
+    );
+}
+
+#[test]
+fn test_round_survives_saves() {
+    let mut game = two_player_game();
+    game.prepare_next_round();
+    game.prepare_next_round();
+    let loaded = Game::from_snapshot(&game.snapshot()).unwrap();
+    assert_eq!(loaded.round(), 3);
+}
+
+#[test]
+fn test_rejected_moves_are_counted_per_player() {
+    let mut game = Game::new(3, 3);
+    for (name, symbol) in [("Ann", 'X'), ("Bob", 'O'), ("Cid", 'Z')] {
+        game.add_player(&Player::new(name.to_string(), Symbol(symbol), true, None))
+            .unwrap();
+    }
+    let ids: Vec<_> = game.players().iter().map(|p| p.id()).collect();
+
+    // Ann's two refused moves don't count against Bob's one
+    let mut runner = GameRunner::new();
+    runner.set_provider(
+        ids[0],
+        ScriptedProvider::from_actions(vec![
+            PlayerAction::Move((5, 5)),
+            PlayerAction::Move((5, 5)),
+            PlayerAction::Resign,
+        ]),
+    );
+    runner.set_provider(ids[1], ScriptedProvider::new(&[(5, 5), (1, 1)]));
+    runner.set_provider(ids[2], ScriptedProvider::new(&[]));
+    for _ in 0..5 {
+        runner.step(&mut game).unwrap();
+    }
+    assert!(game.has_resigned(ids[0]));
+    assert!(!game.has_resigned(ids[1]));
+    assert_eq!(game.board().get_cell((1, 1)), Some(Symbol('O')));
+}
+
+// Asks for the same thing every turn, however often it is refused
+struct RepeatingProvider(PlayerAction);
+
+impl MoveProvider for RepeatingProvider {
+    fn next_action(&mut self, _game: &Game, _ctx: &TurnContext) -> PlayerAction {
+        self.0.clone()
+    }
+
+    fn is_interactive(&self) -> bool {
+        true
+    }
+}
+
+#[test]
+fn test_every_rejected_action_counts() {
+    // Refused undos and choices forfeit like refused moves
+    for action in [
+        PlayerAction::Undo,
+        PlayerAction::ChooseOpening(OpeningChoice::Swap),
+    ] {
+        let mut game = two_player_game();
+        let (ann, bob) = (game.players()[0].id(), game.players()[1].id());
+        let mut runner = GameRunner::new();
+        runner.set_provider(ann, RepeatingProvider(action));
+        runner.set_provider(bob, ScriptedProvider::new(&[]));
+        let result = runner.run(&mut game).unwrap();
+        assert!(game.has_resigned(ann));
+        assert_eq!(result.winner, Some(bob));
+    }
+
+    // Players only take back their own moves, not the other player's
+    let mut game = two_player_game();
+    let (ann, bob) = (game.players()[0].id(), game.players()[1].id());
+    let mut runner = GameRunner::new();
+    runner.set_provider(
+        ann,
+        ScriptedProvider::from_actions(vec![PlayerAction::Move((0, 0)), PlayerAction::Undo]),
+    );
+    runner.set_provider(bob, RepeatingProvider(PlayerAction::Undo));
+    runner.step(&mut game).unwrap();
+    assert!(matches!(
+        runner.step(&mut game).unwrap(),
+        StepOutcome::Rejected { player, error: GameError::NothingToUndo } if player == bob
+    ));
+    assert_eq!(game.history().len(), 1);
+
+    // Their own move goes back together with the scripted reply after it
+    let mut game = two_player_game();
+    let (ann, bob) = (game.players()[0].id(), game.players()[1].id());
+    let mut runner = GameRunner::new();
+    runner.set_provider(ann, RepeatingProvider(PlayerAction::Move((0, 0))));
+    runner.set_provider(bob, ScriptedProvider::new(&[(1, 1)]));
+    runner.step(&mut game).unwrap();
+    runner.step(&mut game).unwrap();
+    runner.set_provider(ann, RepeatingProvider(PlayerAction::Undo));
+    assert_eq!(runner.step(&mut game).unwrap(), StepOutcome::Undone(2));
+    assert!(game.history().is_empty());
+}
+
+struct SlowProvider;
+
+impl MoveProvider for SlowProvider {
+    fn next_action(&mut self, game: &Game, _ctx: &TurnContext) -> PlayerAction {
+        thread::sleep(Duration::from_millis(20));
+        PlayerAction::Move(game.board().empty_positions()[0])
+    }
+}
+
+#[test]
+fn test_runner_drives_providers_to_a_result() {
+    let mut game = two_player_game();
+    let (ann, bob) = (game.players()[0].id(), game.players()[1].id());
+
+    let mut runner = GameRunner::new();
+    runner.set_provider(ann, ScriptedProvider::new(&[(0, 0), (0, 1), (0, 2)]));
+    runner.set_provider(bob, ScriptedProvider::new(&[(0, 0), (1, 0), (1, 1)]));
+
+    // Bob's (0, 0) is taken, which is rejected and leaves him to play
+    assert!(matches!(
+        runner.step(&mut game).unwrap(),
+        StepOutcome::Played(_)
+    ));
+    assert!(matches!(
+        runner.step(&mut game).unwrap(),
+        StepOutcome::Rejected { player, .. } if player == bob
+    ));
+
+    let result = runner.run(&mut game).unwrap();
+    assert_eq!(result.winner, Some(ann));
+    assert_eq!(result.moves.len(), 5);
+    assert!(!result.quit);
+    assert_eq!(
+        runner
+            .scores()
+            .get_stats(game.player(ann).unwrap())
+            .unwrap()
+            .wins(),
+        1
+    );
+    assert_eq!(runner.step(&mut game), Err(GameError::GameOver));
+
+    // Draw offers go to the other player's provider
+    game.prepare_next_round();
+    let offerer = game.current_player().unwrap().id();
+    let other = if offerer == ann { bob } else { ann };
+    runner.set_provider(
+        offerer,
+        ScriptedProvider::from_actions(vec![PlayerAction::OfferDraw]),
+    );
+    runner.set_provider(other, ScriptedProvider::default().accepting_draws());
+    let result = runner.run(&mut game).unwrap();
+    assert_eq!(result.status, GameStatus::Draw(DrawReason::Agreement));
+
+    // Going over the move time limit forfeits the game
+    game.prepare_next_round();
+    let slow = game.current_player().unwrap().id();
+    let fast = if slow == ann { bob } else { ann };
+    runner.set_provider(slow, SlowProvider);
+    runner.set_move_time_limit(Some(Duration::from_millis(5)));
+    let result = runner.run(&mut game).unwrap();
+    assert_eq!(result.timed_out, vec![slow]);
+    assert_eq!(result.winner, Some(fast));
+}
+
+fn timed_game(time_control: TimeControl) -> Game {
+    let rules = GameRules {
+        time_control: Some(time_control),
+        ..GameRules::default()
+    };
+    let mut game = Game::with_rules(3, 2, rules);
+    game.add_player(&Player::new("Ann".to_string(), Symbol('X'), true, None))
+        .unwrap();
+    game.add_player(&Player::new("Bob".to_string(), Symbol('O'), true, None))
+        .unwrap();
+    game
+}
+
+#[test]
+fn test_time_controls() {
+    // The increment is added after every move
+    let mut game = timed_game(TimeControl::new(
+        Duration::from_secs(1),
+        Duration::from_millis(500),
+    ));
+    let (ann, bob) = (game.players()[0].id(), game.players()[1].id());
+    game.make_move((0, 0)).unwrap();
+    assert!(game.time_left(ann).unwrap() > Duration::from_secs(1));
+    assert!(game.time_left(bob).unwrap() <= Duration::from_secs(1));
+    assert_eq!(Game::new(3, 2).time_left(ann), None);
+
+    // Running out of time loses the game
+    let mut game = timed_game(TimeControl::new(Duration::from_millis(30), Duration::ZERO));
+    let (ann, bob) = (game.players()[0].id(), game.players()[1].id());
+    game.make_move((0, 0)).unwrap();
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(game.make_move((1, 1)), Err(GameError::OutOfTime));
+    assert_eq!(game.time_left(bob), Some(Duration::ZERO));
+    assert!(game.has_timed_out(bob));
+    assert_eq!(game.winner().map(|w| w.id()), Some(ann));
+    assert_eq!(
+        game.status(),
+        GameStatus::Win("Ann".to_string(), WinReason::Timeout)
+    );
+
+    // Or only the turn, when configured so
+    let mut time_control = TimeControl::new(Duration::from_secs(10), Duration::ZERO);
+    time_control.per_move = Some(Duration::from_millis(10));
+    time_control.on_flag = FlagFall::SkipTurn;
+    let mut game = timed_game(time_control);
+    let ann = game.players()[0].id();
+    thread::sleep(Duration::from_millis(20));
+    assert!(game.check_clock());
+    assert!(!game.is_over());
+    assert!(!game.has_timed_out(ann));
+    assert_ne!(game.current_player().unwrap().id(), ann);
+    assert!(!game.check_clock());
+
+    // A clock that never has time would skip every turn as it starts
+    let mut empty = TimeControl::new(Duration::ZERO, Duration::ZERO);
+    empty.on_flag = FlagFall::SkipTurn;
+    assert!(!empty.is_playable());
+    let snapshot = timed_game(empty.clone()).snapshot();
+    assert!(matches!(
+        Game::from_snapshot(&snapshot),
+        Err(GameError::LoadFailed(_))
+    ));
+    empty.delay = Duration::from_secs(1);
+    assert!(empty.is_playable());
+    empty.per_move = Some(Duration::ZERO);
+    assert!(!empty.is_playable());
+}
+
+// Waits for input that never comes, like a human who stopped typing
+struct IdleProvider;
+
+impl MoveProvider for IdleProvider {
+    fn next_action(&mut self, game: &Game, _ctx: &TurnContext) -> PlayerAction {
+        if let Some(deadline) = game.flag_deadline() {
+            thread::sleep(deadline.saturating_duration_since(Instant::now()));
+        }
+        PlayerAction::TimeUp
+    }
+}
+
+#[test]
+fn test_clock_runs_out_while_waiting_for_input() {
+    let mut game = timed_game(TimeControl::new(Duration::from_millis(30), Duration::ZERO));
+    let (ann, bob) = (game.players()[0].id(), game.players()[1].id());
+    let deadline = game.flag_deadline().unwrap();
+    assert!(deadline <= Instant::now() + Duration::from_millis(30));
+    assert_eq!(two_player_game().flag_deadline(), None);
+
+    let mut runner = GameRunner::new();
+    runner.set_provider(ann, IdleProvider);
+    runner.set_provider(bob, IdleProvider);
+    assert_eq!(runner.step(&mut game).unwrap(), StepOutcome::TimedOut(ann));
+    assert_eq!(
+        game.status(),
+        GameStatus::Win("Bob".to_string(), WinReason::Timeout)
+    );
+}
+
+#[test]
+fn test_spectator_view() {
+    let mut game = two_player_game();
+    let events = game.subscribe();
+    let ann = game.current_player().unwrap().id();
+    game.make_move((1, 1)).unwrap();
+    game.make_move((0, 0)).unwrap();
+
+    assert_eq!(history(&game).len(), 2);
+    assert!(history(&game)[0].ends_with("1 1"));
+
+    // Everyone gets a score, Ann is to move again and gets a suggestion
+    let evaluations = evaluate(&game);
+    assert_eq!(evaluations.len(), 2);
+    let (ann_view, bob_view) = if evaluations[0].player == ann {
+        (&evaluations[0], &evaluations[1])
+    } else {
+        (&evaluations[1], &evaluations[0])
+    };
+    assert!(ann_view.score > bob_view.score);
+    assert!(ann_view.best_move.is_some());
+    assert_eq!(bob_view.best_move, None);
+
+    let described: Vec<String> = events
+        .try_iter()
+        .filter_map(|event| describe_event(&game, &event))
+        .collect();
+    assert!(described.iter().any(|line| line.ends_with("played 1 1")));
+}
+
+#[test]
+fn test_misere_play() {
+    let rules = GameRules {
+        misere: true,
+        dead_position: DeadPositionCheck::Off,
+        ..GameRules::default()
+    };
+    let mut game = Game::with_rules(3, 2, rules.clone());
+    game.add_player(&Player::new("Ann".to_string(), Symbol('X'), true, None))
+        .unwrap();
+    game.add_player(&Player::new("Bob".to_string(), Symbol('O'), true, None))
+        .unwrap();
+    for pos in [(0, 0), (1, 1), (0, 1), (2, 2)] {
+        game.make_move(pos).unwrap();
+    }
+    // The AI won't complete its own row
+    let ai = AI::new(Difficulty::Hard, Symbol('X'));
+    for _ in 0..10 {
+        assert_ne!(ai.decide_move(game.board()), (0, 2));
+    }
+    assert_eq!(game.board().evaluate(Symbol('X'), Symbol('O')), 0);
+
+    game.make_move((0, 2)).unwrap();
+    assert_eq!(
+        game.status(),
+        GameStatus::Win("Bob".to_string(), WinReason::Elimination)
+    );
+    assert!(BoardMarks::for_game(&game).winning_line.is_empty());
+    assert!(game.board().evaluate(Symbol('X'), Symbol('O')) < 0);
+    game.undo().unwrap();
+    assert_eq!(game.status(), GameStatus::InProgress);
+    assert_eq!(game.current_player().unwrap().name(), "Ann");
+
+    // With three players the first to complete a line is out and the others
+    // play on
+    let rules = GameRules {
+        win_length: 3,
+        ..rules
+    };
+    let mut game = Game::with_rules(4, 3, rules);
+    for (name, symbol) in [("Ann", 'X'), ("Bob", 'O'), ("Cat", 'Z')] {
+        game.add_player(&Player::new(name.to_string(), Symbol(symbol), true, None))
+            .unwrap();
+    }
+    let ann = game.players()[0].id();
+    for pos in [(0, 0), (3, 0), (3, 3), (0, 1), (3, 1), (2, 3), (0, 2)] {
+        game.make_move(pos).unwrap();
+    }
+    assert!(game.is_eliminated(ann));
+    assert_eq!(game.status(), GameStatus::InProgress);
+    assert_eq!(game.current_player().unwrap().name(), "Bob");
+    game.make_move((3, 2)).unwrap();
+    let status = GameStatus::Win("Cat".to_string(), WinReason::Elimination);
+    assert_eq!(game.status(), status);
+    let restored = Game::from_snapshot(&game.snapshot()).unwrap();
+    assert_eq!(restored.status(), status);
+}
+
+#[test]
+fn test_wild_play() {
+    let rules = GameRules {
+        wild: vec![Symbol('X'), Symbol('O')],
+        ..GameRules::default()
+    };
+    let mut game = Game::with_rules(3, 2, rules);
+    game.add_player(&Player::new("Ann".to_string(), Symbol('X'), true, None))
+        .unwrap();
+    game.add_player(&Player::new(
+        "Bob".to_string(),
+        Symbol('O'),
+        false,
+        Some(Difficulty::Hard),
+    ))
+    .unwrap();
+    assert!(matches!(
+        game.make_move_with((0, 0), Symbol('Z')),
+        Err(GameError::SymbolNotAllowed)
+    ));
+    game.make_move_with((0, 0), Symbol('O')).unwrap();
+    game.make_move_with((2, 2), Symbol('X')).unwrap();
+    // Undo and redo keep the chosen symbol
+    game.undo().unwrap();
+    game.redo().unwrap();
+    assert_eq!(game.board().get_cell((2, 2)), Some(Symbol('X')));
+
+    // Two O's in the top row: Bob's AI finishes the line with Ann's O
+    game.make_move_with((0, 1), Symbol('O')).unwrap();
+    game.play_ai_turns().unwrap();
+    assert_eq!(game.board().get_cell((0, 2)), Some(Symbol('O')));
+    assert_eq!(game.winner().unwrap().name(), "Bob");
+
+    // Otherwise it never leaves the opponent a line to finish, in either symbol
+    let ai = AI::new(Difficulty::Hard, Symbol('X'));
+    let symbols = [Symbol('X'), Symbol('O')];
+    let mut board = GameBoard::new(3);
+    board.apply_move((1, 1), Symbol('O')).unwrap();
+    let (pos, symbol) = ai.decide_wild_move(&board, &symbols);
+    board.apply_move(pos, symbol).unwrap();
+    for reply in board.empty_positions() {
+        assert!(symbols.iter().all(|&s| !board.completes_line(reply, s)));
+    }
+
+    // Completing a line of the other player's symbol still wins
+    let mut game = Game::with_rules(3, 2, game.rules().clone());
+    game.add_player(&Player::new("Ann".to_string(), Symbol('X'), true, None))
+        .unwrap();
+    game.add_player(&Player::new("Bob".to_string(), Symbol('O'), true, None))
+        .unwrap();
+    let (ann, bob) = (game.players()[0].id(), game.players()[1].id());
+    assert_eq!(
+        Command::parse("0 2 O"),
+        Ok(Command::Place((0, 2), Symbol('O')))
+    );
+    let mut runner = GameRunner::new();
+    runner.set_provider(
+        ann,
+        ScriptedProvider::from_actions(vec![
+            PlayerAction::Place((1, 1), Symbol('O')),
+            PlayerAction::Place((0, 2), Symbol('O')),
+        ]),
+    );
+    // Bob's plain moves place his own symbol
+    runner.set_provider(bob, ScriptedProvider::new(&[(2, 0)]));
+    let result = runner.run(&mut game).unwrap();
+    assert_eq!(result.winner, Some(ann));
+    assert_eq!(
+        result.status,
+        GameStatus::Win("Ann".to_string(), WinReason::Streak)
+    );
+}
+
+#[test]
+fn test_vanishing_play() {
+    let rules = GameRules {
+        max_marks: Some(3),
+        ..GameRules::default()
+    };
+    let mut game = Game::with_rules(3, 2, rules);
+    let bob = Player::new(
+        "Bob".to_string(),
+        Symbol('O'),
+        false,
+        Some(Difficulty::Hard),
+    );
+    game.add_player(&bob).unwrap();
+    game.add_player(&Player::new("Ann".to_string(), Symbol('X'), true, None))
+        .unwrap();
+    let removed = Arc::new(Mutex::new(Vec::new()));
+    let log = Arc::clone(&removed);
+    game.add_observer(move |_: &Game, event: &GameEvent| {
+        if let GameEvent::MarkRemoved { position, .. } = event {
+            log.lock().unwrap().push(*position);
+        }
+    });
+    for pos in [(0, 0), (1, 0), (1, 1), (0, 2), (2, 1), (1, 2)] {
+        game.make_move(pos).unwrap();
+    }
+    assert_eq!(game.next_to_vanish(), Some((0, 0)));
+
+    // (2, 2) would only finish the diagonal if (0, 0) stayed, (0, 1)
+    // wins with the marks Bob keeps
+    game.play_ai_turns().unwrap();
+    assert_eq!(game.board().get_cell((0, 0)), None);
+    assert_eq!(*removed.lock().unwrap(), vec![(0, 0)]);
+    assert_eq!(game.winner().unwrap().name(), "Bob");
+
+    // Taking the move back puts the vanished mark back
+    game.undo().unwrap();
+    assert_eq!(game.board().get_cell((0, 0)), Some(Symbol('O')));
+    assert_eq!(game.board().get_cell((0, 1)), None);
+    assert_eq!(game.next_to_vanish(), Some((0, 0)));
+    assert_eq!(
+        game.marks(bob.id())
+            .unwrap()
+            .iter()
+            .copied()
+            .collect::<Vec<_>>(),
+        vec![(0, 0), (1, 1), (2, 1)]
+    );
+
+    // Loading replays the removals
+    game.redo().unwrap();
+    let loaded = Game::from_snapshot(&game.snapshot()).unwrap();
+    assert_eq!(loaded.board().get_cell((0, 0)), None);
+    assert_eq!(loaded.status(), game.status());
+}
+
+#[test]
+fn test_multi_stone_turns() {
+    let rules = GameRules {
+        win_length: 6,
+        stones_per_turn: Some(2),
+        ..GameRules::default()
+    };
+    let mut game = Game::with_rules(10, 2, rules);
+    game.add_player(&Player::new("Ann".to_string(), Symbol('X'), true, None))
+        .unwrap();
+    game.add_player(&Player::new("Bob".to_string(), Symbol('O'), true, None))
+        .unwrap();
+
+    // One stone for the first turn, two after that
+    assert_eq!(game.turn_progress(), (0, 1));
+    game.make_move((0, 0)).unwrap();
+    assert_eq!(game.current_player().unwrap().name(), "Bob");
+    game.make_move((5, 5)).unwrap();
+    assert_eq!(game.current_player().unwrap().name(), "Bob");
+    assert_eq!(game.turn_progress(), (1, 2));
+    let loaded = Game::from_snapshot(&game.snapshot()).unwrap();
+    assert_eq!(loaded.turn_progress(), (1, 2));
+    // Saves can't hold a turn that already has all its stones
+    let mut snapshot = game.snapshot();
+    snapshot.stones_placed = 2;
+    assert!(matches!(
+        Game::from_snapshot(&snapshot),
+        Err(GameError::LoadFailed(_))
+    ));
+    let mut snapshot = game.snapshot();
+    snapshot.timed_out = vec![game.players()[0].id()];
+    assert!(matches!(
+        Game::from_snapshot(&snapshot),
+        Err(GameError::LoadFailed(_))
+    ));
+    game.make_move((5, 6)).unwrap();
+    assert_eq!(game.current_player().unwrap().name(), "Ann");
+    assert_eq!(game.turn_progress(), (0, 2));
+
+    game.undo().unwrap();
+    assert_eq!(game.current_player().unwrap().name(), "Bob");
+    assert_eq!(game.turn_progress(), (1, 2));
+    game.redo().unwrap();
+    assert_eq!(game.current_player().unwrap().name(), "Ann");
+
+    // Two stones finish a line of four
+    let ai = AI::new(Difficulty::Hard, Symbol('X'));
+    let mut board = GameBoard::with_win_length(10, 6);
+    for col in 1..5 {
+        board.apply_move((2, col), Symbol('X')).unwrap();
+    }
+    for pos in ai.decide_stones(&board, 2, 2) {
+        board.apply_move(pos, Symbol('X')).unwrap();
+    }
+    assert_eq!(board.winner_symbol(), Some(Symbol('X')));
+
+    // Against four in a row both stones go where every six still needs them
+    let mut board = GameBoard::with_win_length(10, 6);
+    for col in 2..6 {
+        board.apply_move((5, col), Symbol('O')).unwrap();
+    }
+    let mut stones = ai.decide_stones(&board, 2, 2);
+    stones.sort();
+    assert_eq!(stones, vec![(5, 1), (5, 6)]);
+
+    // Moves left count stones, and the turn in progress
+    let rules = GameRules {
+        stones_per_turn: Some(2),
+        ..GameRules::default()
+    };
+    let mut small = Game::with_rules(3, 2, rules);
+    small
+        .add_player(&Player::new("Ann".to_string(), Symbol('X'), true, None))
+        .unwrap();
+    small
+        .add_player(&Player::new("Bob".to_string(), Symbol('O'), true, None))
+        .unwrap();
+    let (ann, bob) = (small.players()[0].id(), small.players()[1].id());
+    assert_eq!((small.moves_left(ann), small.moves_left(bob)), (5, 4));
+    small.make_move((0, 0)).unwrap();
+    small.make_move((1, 1)).unwrap();
+    assert_eq!((small.moves_left(ann), small.moves_left(bob)), (4, 3));
+
+    // The AI plays the stones it planned together, in the runner and on its own
+    for use_runner in [true, false] {
+        let mut game = Game::with_rules(
+            10,
+            2,
+            GameRules {
+                win_length: 6,
+                stones_per_turn: Some(2),
+                ..GameRules::default()
+            },
+        );
+        game.add_player(&Player::new(
+            "Ann".to_string(),
+            Symbol('X'),
+            false,
+            Some(Difficulty::Hard),
+        ))
+        .unwrap();
+        game.add_player(&Player::new("Bob".to_string(), Symbol('O'), true, None))
+            .unwrap();
+        for pos in [
+            (2, 1),
+            (8, 0),
+            (8, 3),
+            (2, 2),
+            (2, 3),
+            (8, 6),
+            (9, 9),
+            (2, 4),
+            (6, 9),
+            (0, 0),
+            (0, 9),
+        ] {
+            game.make_move(pos).unwrap();
+        }
+        if use_runner {
+            let mut runner = GameRunner::for_game(&game, ScriptedProvider::new(&[]));
+            runner.step(&mut game).unwrap();
+            runner.step(&mut game).unwrap();
+        } else {
+            assert_eq!(game.play_ai_turns().unwrap(), 2);
+        }
+        assert_eq!(game.winner().unwrap().name(), "Ann");
+    }
+}
+
+#[test]
+fn test_ai_on_long_lines() {
+    // Line weights on a 19x19 board where only all 19 in a row win stay in range
+    let ai = AI::new(Difficulty::Hard, Symbol('X'));
+    let mut board = GameBoard::with_win_length(19, 19);
+    for col in 0..18 {
+        board.apply_move((9, col), Symbol('X')).unwrap();
+    }
+    for col in 1..18 {
+        board.apply_move((3, col), Symbol('O')).unwrap();
+    }
+    assert_eq!(ai.decide_move(&board), (9, 18));
+    let stones = ai.decide_stones(&board, 2, 2);
+    assert!(stones.contains(&(9, 18)));
+}
+
+fn gomoku_game(gomoku: GomokuRule, opening: Opening) -> (Game, Player, Player) {
+    let rules = GameRules {
+        win_length: 5,
+        gomoku,
+        opening,
+        ..GameRules::default()
+    };
+    let mut game = Game::with_rules(15, 2, rules);
+    let ann = Player::new("Ann".to_string(), Symbol('X'), true, None);
+    let bob = Player::new("Bob".to_string(), Symbol('O'), true, None);
+    game.add_player(&ann).unwrap();
+    game.add_player(&bob).unwrap();
+    (game, ann, bob)
+}
+
+#[test]
+fn test_gomoku_rules() {
+    // Standard gomoku: six in a row is not a win
+    let (mut game, _, _) = gomoku_game(GomokuRule::Standard, Opening::Free);
+    for (x, o) in [(0, 9), (1, 7), (2, 5), (3, 3), (5, 1)] {
+        game.make_move((0, x)).unwrap();
+        game.make_move((9, o)).unwrap();
+    }
+    game.make_move((0, 4)).unwrap();
+    assert_eq!(game.status(), GameStatus::InProgress);
+
+    // Renju: overlines, double fours and double threes are forbidden for black
+    let mut board = GameBoard::with_win_length(15, 5);
+    for col in [0, 1, 2, 4, 5] {
+        board.apply_move((5, col), Symbol('X')).unwrap();
+    }
+    assert_eq!(
+        gomoku::forbidden_move(&board, (5, 3), Symbol('X')),
+        Some(ForbiddenMove::Overline)
+    );
+    let mut board = GameBoard::with_win_length(15, 5);
+    for pos in [(5, 1), (5, 2), (5, 3), (2, 4), (3, 4), (4, 4)] {
+        board.apply_move(pos, Symbol('X')).unwrap();
+    }
+    assert_eq!(
+        gomoku::forbidden_move(&board, (5, 4), Symbol('X')),
+        Some(ForbiddenMove::DoubleFour)
+    );
+
+    let (mut game, _, _) = gomoku_game(GomokuRule::Renju, Opening::Free);
+    for (black, white) in [((7, 5), (0, 0)), ((7, 6), (0, 2)), ((5, 7), (0, 4))] {
+        game.make_move(black).unwrap();
+        game.make_move(white).unwrap();
+    }
+    game.make_move((6, 7)).unwrap();
+    assert_eq!(game.forbidden_move((7, 7)), None);
+    game.make_move((0, 6)).unwrap();
+    assert_eq!(
+        game.forbidden_move((7, 7)),
+        Some(ForbiddenMove::DoubleThree)
+    );
+    assert!(!game.is_move_valid((7, 7)));
+    assert_eq!(
+        game.make_move((7, 7)),
+        Err(GameError::ForbiddenMove(ForbiddenMove::DoubleThree))
+    );
+
+    // Swap opening: Ann places three stones and Bob picks a color
+    let (mut game, ann, bob) = gomoku_game(GomokuRule::Freestyle, Opening::Swap);
+    for (pos, symbol) in [((7, 7), 'X'), ((7, 8), 'O'), ((8, 8), 'X')] {
+        assert_eq!(
+            game.opening_stage(),
+            OpeningStage::Placing {
+                player: ann.id(),
+                symbol: Symbol(symbol),
+            }
+        );
+        game.make_move(pos).unwrap();
+    }
+    assert_eq!(game.make_move((0, 0)), Err(GameError::ChoicePending));
+    assert_eq!(
+        game.choose_opening(ann.id(), OpeningChoice::Black),
+        Err(GameError::OutOfTurn)
+    );
+    game.choose_opening(bob.id(), OpeningChoice::Black).unwrap();
+    assert_eq!(game.opening_stage(), OpeningStage::Done);
+    assert_eq!(game.current_player().unwrap().name(), "Ann");
+    assert_eq!(game.current_player().unwrap().symbol(), Symbol('O'));
+    let loaded = Game::from_snapshot(&game.snapshot()).unwrap();
+    assert_eq!(loaded.current_player().unwrap().symbol(), Symbol('O'));
+    game.make_move((6, 6)).unwrap();
+    assert_eq!(game.current_player().unwrap().name(), "Bob");
+
+    // Taking back the third stone takes the choice with it
+    game.undo().unwrap();
+    game.undo().unwrap();
+    assert_eq!(
+        game.opening_stage(),
+        OpeningStage::Placing {
+            player: ann.id(),
+            symbol: Symbol('X'),
+        }
+    );
+    assert_eq!(game.current_player().unwrap().symbol(), Symbol('X'));
+}
+
+#[test]
+fn test_pie_rule_and_opening_stones() {
+    let rules = GameRules {
+        opening: Opening::Pie,
+        opening_stones: vec![vec![(0, 0)], vec![(2, 2)]],
+        ..GameRules::default()
+    };
+    let mut game = Game::with_rules(3, 2, rules);
+    let ann = Player::new("Ann".to_string(), Symbol('X'), true, None);
+    let bob = Player::new("Bob".to_string(), Symbol('O'), true, None);
+    game.add_player(&ann).unwrap();
+    game.add_player(&bob).unwrap();
+    assert_eq!(game.board().get_cell((0, 0)), Some(Symbol('X')));
+    assert_eq!(game.board().get_cell((2, 2)), Some(Symbol('O')));
+
+    // Bob takes over Ann's first move and Ann plays on with his symbol
+    game.make_move((1, 1)).unwrap();
+    assert_eq!(
+        game.opening_stage(),
+        OpeningStage::Choosing {
+            player: bob.id(),
+            options: vec![OpeningChoice::Swap, OpeningChoice::Keep],
+        }
+    );
+    assert_eq!(
+        Command::parse("swap"),
+        Ok(Command::Choose(OpeningChoice::Swap))
+    );
+    game.choose_opening(bob.id(), OpeningChoice::Swap).unwrap();
+    assert_eq!(game.current_player().unwrap().name(), "Ann");
+    assert_eq!(game.current_player().unwrap().symbol(), Symbol('O'));
+
+    // The stones before the first move stay as they were, also after loading
+    let loaded = Game::from_snapshot(&game.snapshot()).unwrap();
+    assert_eq!(loaded.board().get_cell((0, 0)), Some(Symbol('X')));
+    assert_eq!(loaded.board().get_cell((2, 2)), Some(Symbol('O')));
+    assert_eq!(loaded.current_player().unwrap().symbol(), Symbol('O'));
+    game.undo().unwrap();
+    assert_eq!(game.undo(), Err(GameError::NothingToUndo));
+    assert_eq!(game.board().get_cell((0, 0)), Some(Symbol('X')));
+
+    // The AI opens away from the center and takes over a center opening
+    let ai = AI::new(Difficulty::Hard, Symbol('X'));
+    let mut board = GameBoard::new(3);
+    let first = ai.decide_pie_move(&board, Symbol('O'));
+    assert!([(0, 1), (1, 0), (1, 2), (2, 1)].contains(&first));
+    board.apply_move((1, 1), Symbol('X')).unwrap();
+    let options = [OpeningChoice::Swap, OpeningChoice::Keep];
+    let choice = AI::new(Difficulty::Hard, Symbol('O')).choose_opening(
+        &board,
+        Symbol('X'),
+        Symbol('O'),
+        &options,
+    );
+    assert_eq!(choice, OpeningChoice::Swap);
+
+    // Mirrored stones give both players the same position
+    let stones = mirrored_stones(5, 5, 3, &[]);
+    assert_eq!(stones[0].len(), 3);
+    for (&(row, col), &mirror) in stones[0].iter().zip(&stones[1]) {
+        assert_eq!(mirror, (4 - row, 4 - col));
+    }
+    let rules = GameRules {
+        opening_stones: stones,
+        ..GameRules::default()
+    };
+    assert!(rules.cells_fit(5, 5));
+    assert!(!rules.cells_fit(2, 5));
+}
+
+#[test]
+fn test_blocked_cells_and_shapes() {
+    let shape = BoardShape::parse("# . #\n. . . .\n# .").unwrap();
+    assert_eq!((shape.rows, shape.cols), (3, 4));
+    assert_eq!(
+        shape.blocked,
+        vec![(0, 0), (0, 2), (0, 3), (2, 0), (2, 2), (2, 3)]
+    );
+    assert_eq!(BoardShape::parse("..\n.."), Err(ShapeError::TooSmall(2, 2)));
+    assert_eq!(BoardShape::parse("..x"), Err(ShapeError::UnknownCell('x')));
+    let cross = BoardShape::named("cross", 3, 3).unwrap();
+    assert_eq!(cross.blocked, vec![(0, 0), (0, 2), (2, 0), (2, 2)]);
+    assert_eq!(BoardShape::named("diamond", 3, 3), Some(cross));
+
+    // A blocked cell takes no mark and breaks the lines through it
+    let rules = GameRules {
+        blocked: BoardShape::named("holes", 4, 4).unwrap().blocked,
+        ..GameRules::default()
+    };
+    let mut game = Game::with_rules(4, 2, rules);
+    game.add_player(&Player::new("Ann".to_string(), Symbol('X'), true, None))
+        .unwrap();
+    game.add_player(&Player::new("Bob".to_string(), Symbol('O'), true, None))
+        .unwrap();
+    assert!(!game.is_move_valid((1, 1)));
+    assert_eq!(
+        game.make_move((1, 1)),
+        Err(GameError::BoardError(BoardError::CellBlocked))
+    );
+    for (x, o) in [((0, 0), (3, 0)), ((2, 2), (3, 1))] {
+        game.make_move(x).unwrap();
+        game.make_move(o).unwrap();
+    }
+    game.make_move((3, 3)).unwrap();
+    assert_eq!(game.status(), GameStatus::InProgress);
+    assert!(!game.board().empty_positions().contains(&(1, 1)));
+    assert!(game
+        .board()
+        .lines(3)
+        .iter()
+        .all(|line| !line.contains(&(1, 1))));
+
+    // Save files keep the shape
+    let loaded = Game::from_snapshot(&game.snapshot()).unwrap();
+    assert!(loaded.board().is_blocked((1, 1)));
+    game.undo().unwrap();
+    assert!(game.board().is_blocked((1, 1)));
+}
+
+#[test]
+fn test_rectangular_boards() {
+    let mut game = Game::with_dimensions(4, 5, 2, GameRules::with_win_length(4));
+    game.add_player(&Player::new("Ann".to_string(), Symbol('X'), true, None))
+        .unwrap();
+    game.add_player(&Player::new("Bob".to_string(), Symbol('O'), true, None))
+        .unwrap();
+    assert_eq!((game.board().rows(), game.board().cols()), (4, 5));
+    assert!(game.is_move_valid((3, 4)));
+    assert!(!game.is_move_valid((4, 0)));
+    assert_eq!(
+        game.make_move((0, 5)),
+        Err(GameError::BoardError(BoardError::OutOfBounds))
+    );
+    for col in 1..4 {
+        game.make_move((0, col)).unwrap();
+        game.make_move((3, col)).unwrap();
+    }
+    game.make_move((0, 4)).unwrap();
+    assert_eq!(
+        game.status(),
+        GameStatus::Win("Ann".to_string(), WinReason::Streak)
+    );
+
+    // Save files keep both dimensions
+    let loaded = Game::from_snapshot(&game.snapshot()).unwrap();
+    assert_eq!((loaded.board().rows(), loaded.board().cols()), (4, 5));
+
+    // A long thin board only has rows of three and short diagonals
+    let board = GameBoard::with_dimensions(3, 10, 3);
+    assert_eq!(board.lines(3).len(), 8 * 3 + 10 + 2 * 8);
+    assert_eq!(board.empty_positions().len(), 30);
+
+    // The AI opens in the middle of the board
+    let board = GameBoard::with_dimensions(3, 5, 3);
+    assert_eq!(
+        AI::new(Difficulty::Hard, Symbol('X')).decide_move(&board),
+        (1, 2)
+    );
+}
@@ -0,0 +1,265 @@
+use rust_tic_tac_toe::http::ApiServer;
+use rust_tic_tac_toe::types::SymbolSet;
+use serde_json::{json, Value};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::thread;
+
+fn start_server() -> SocketAddr {
+    let server = ApiServer::bind("127.0.0.1:0", SymbolSet::Ascii).unwrap();
+    let addr = server.local_addr().unwrap();
+    thread::spawn(move || server.run());
+    addr
+}
+
+// Sends one request and returns the status code and JSON body
+fn request(addr: SocketAddr, method: &str, path: &str, body: Option<Value>) -> (u16, Value) {
+    let body = body.map(|body| body.to_string()).unwrap_or_default();
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+        method,
+        path,
+        body.len(),
+        body
+    )
+    .unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let status = head.split_whitespace().nth(1).unwrap().parse().unwrap();
+    let body = if body.is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_str(body).unwrap()
+    };
+    (status, body)
+}
+
+#[test]
+fn test_rest_api_plays_a_game() {
+    let addr = start_server();
+
+    let (status, game) = request(addr, "POST", "/games", Some(json!({ "board_size": 3 })));
+    assert_eq!(status, 201);
+    let path = format!("/games/{}", game["id"]);
+
+    let (status, ann) = request(
+        addr,
+        "POST",
+        &format!("{}/players", path),
+        Some(json!({ "name": "Ann", "symbol": "X" })),
+    );
+    assert_eq!(status, 201);
+    let token = ann["token"].clone();
+    assert!(token.is_string());
+    let ann = ann["player"].as_str().unwrap().to_string();
+
+    let (status, error) = request(
+        addr,
+        "POST",
+        &format!("{}/players", path),
+        Some(json!({ "name": "Bob", "symbol": "X" })),
+    );
+    assert_eq!(
+        (status, error["error"].as_str()),
+        (409, Some("symbol_taken"))
+    );
+
+    // The AI joins as the second player and answers every move right away
+    let (_, bot) = request(
+        addr,
+        "POST",
+        &format!("{}/players", path),
+        Some(json!({ "name": "Bot", "difficulty": "Hard" })),
+    );
+    assert_eq!(bot["game"]["current_player"].as_str(), Some(ann.as_str()));
+    assert!(bot["token"].is_null());
+
+    let moves = format!("{}/moves", path);
+    // The player id is public, the token isn't
+    let (status, error) = request(
+        addr,
+        "POST",
+        &moves,
+        Some(json!({ "player": ann, "token": "guess", "row": 0, "col": 0 })),
+    );
+    assert_eq!(
+        (status, error["error"].as_str()),
+        (403, Some("invalid_token"))
+    );
+    let (status, state) = request(
+        addr,
+        "POST",
+        &moves,
+        Some(json!({ "player": ann, "token": token, "row": 0, "col": 0 })),
+    );
+    assert_eq!(status, 200);
+    assert_eq!(state["board"][0][0], "X");
+    assert_eq!(state["current_player"].as_str(), Some(ann.as_str()));
+
+    let (status, error) = request(
+        addr,
+        "POST",
+        &moves,
+        Some(json!({ "player": ann, "token": token, "row": 0, "col": 0 })),
+    );
+    assert_eq!(
+        (status, error["error"].as_str()),
+        (409, Some("cell_occupied"))
+    );
+    let (status, error) = request(
+        addr,
+        "POST",
+        &moves,
+        Some(json!({ "player": ann, "token": token, "row": 5, "col": 0 })),
+    );
+    assert_eq!(status, 400);
+    assert!(error["message"].is_string());
+    let (status, _) = request(
+        addr,
+        "POST",
+        &moves,
+        Some(json!({ "player": bot["player"], "token": token, "row": 2, "col": 2 })),
+    );
+    assert_eq!(status, 403);
+
+    let (status, history) = request(addr, "GET", &format!("{}/history", path), None);
+    assert_eq!(status, 200);
+    assert_eq!(history.as_array().unwrap().len(), 2);
+    assert_eq!(history[0]["player"].as_str(), Some(ann.as_str()));
+
+    let (status, state) = request(addr, "GET", &path, None);
+    assert_eq!(status, 200);
+    assert_eq!(state["status"], "InProgress");
+    assert!(state["ai_error"].is_null());
+
+    assert_eq!(request(addr, "GET", "/games/99", None).0, 404);
+    assert_eq!(request(addr, "DELETE", &path, None).0, 405);
+    assert_eq!(
+        request(addr, "POST", "/games", Some(json!({ "board_size": 50 }))).0,
+        400
+    );
+    // Clocks that would overflow are refused up front
+    let endless = json!({
+        "board_size": 3,
+        "rules": {
+            "win_length": 3,
+            "time_control": {
+                "initial": { "secs": u64::MAX, "nanos": 0 },
+                "increment": { "secs": u64::MAX, "nanos": 0 },
+            },
+        },
+    });
+    let (status, error) = request(addr, "POST", "/games", Some(endless));
+    assert_eq!(
+        (status, error["error"].as_str()),
+        (400, Some("invalid_rules"))
+    );
+}
+
+#[test]
+fn test_rest_api_swap_opening() {
+    let addr = start_server();
+    let (_, game) = request(
+        addr,
+        "POST",
+        "/games",
+        Some(json!({ "board_size": 10, "rules": { "win_length": 5, "opening": "Swap" } })),
+    );
+    let path = format!("/games/{}", game["id"]);
+    let mut players = Vec::new();
+    for (name, symbol) in [("Ann", "X"), ("Bob", "O")] {
+        let (_, joined) = request(
+            addr,
+            "POST",
+            &format!("{}/players", path),
+            Some(json!({ "name": name, "symbol": symbol })),
+        );
+        players.push((joined["player"].clone(), joined["token"].clone()));
+    }
+    let [(ann, ann_token), (bob, bob_token)] = players.try_into().unwrap();
+
+    // Ann places the three opening stones, then Bob picks a color
+    let mut state = Value::Null;
+    for (row, col) in [(4, 4), (4, 5), (5, 5)] {
+        (_, state) = request(
+            addr,
+            "POST",
+            &format!("{}/moves", path),
+            Some(json!({ "player": ann, "token": ann_token, "row": row, "col": col })),
+        );
+    }
+    assert_eq!(state["choice"]["player"], bob);
+    assert_eq!(state["choice"]["options"], json!(["Black", "White"]));
+
+    let (status, error) = request(
+        addr,
+        "POST",
+        &format!("{}/choices", path),
+        Some(json!({ "player": ann, "token": ann_token, "choice": "Black" })),
+    );
+    assert_eq!(
+        (status, error["error"].as_str()),
+        (409, Some("out_of_turn"))
+    );
+    let (status, state) = request(
+        addr,
+        "POST",
+        &format!("{}/choices", path),
+        Some(json!({ "player": bob, "token": bob_token, "choice": "Black" })),
+    );
+    assert_eq!(status, 200);
+    assert!(state["choice"].is_null());
+    let bob_symbol = state["players"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|p| p["id"] == bob)
+        .map(|p| p["symbol"].clone());
+    assert_eq!(bob_symbol, Some(json!("X")));
+    assert_eq!(state["current_player"], ann);
+}
+
+#[test]
+fn test_rest_api_flags_fall_without_moves() {
+    let addr = start_server();
+    let timed = json!({
+        "board_size": 3,
+        "rules": {
+            "win_length": 3,
+            "time_control": { "initial": { "secs": 0, "nanos": 300_000_000 } },
+        },
+    });
+    let (_, game) = request(addr, "POST", "/games", Some(timed));
+    let path = format!("/games/{}", game["id"]);
+    for (name, symbol) in [("Ann", "X"), ("Bob", "O")] {
+        request(
+            addr,
+            "POST",
+            &format!("{}/players", path),
+            Some(json!({ "name": name, "symbol": symbol })),
+        );
+    }
+
+    // Clients see the clocks run down
+    let (_, state) = request(addr, "GET", &path, None);
+    let clocks: Vec<u64> = state["players"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| p["time_left_ms"].as_u64().unwrap())
+        .collect();
+    assert!(clocks.iter().all(|&left| left <= 300));
+
+    // Nobody moves, the flag falls all the same
+    thread::sleep(std::time::Duration::from_millis(400));
+    let (_, state) = request(addr, "GET", &path, None);
+    assert_eq!(
+        state["status_text"].as_str(),
+        Some("Bob wins, everyone else ran out of time!")
+    );
+    assert_eq!(state["players"][0]["time_left_ms"].as_u64(), Some(0));
+}
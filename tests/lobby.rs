@@ -0,0 +1,289 @@
+use rust_tic_tac_toe::client::GameClient;
+use rust_tic_tac_toe::lobby::LobbyServer;
+use rust_tic_tac_toe::protocol::{ClientMessage, GameInfo, GameStage, ServerMessage};
+use rust_tic_tac_toe::types::{GameStatus, PlayerId, SymbolSet, Token};
+use std::net::SocketAddr;
+use std::thread;
+use std::time::Duration;
+
+fn connect(addr: SocketAddr) -> GameClient {
+    let client = GameClient::connect(addr).unwrap();
+    client.set_timeout(Some(Duration::from_secs(5))).unwrap();
+    client
+}
+
+fn expect<T>(client: &mut GameClient, mut matches: impl FnMut(&ServerMessage) -> Option<T>) -> T {
+    loop {
+        let message = client.recv().unwrap();
+        if let Some(value) = matches(&message) {
+            return value;
+        }
+    }
+}
+
+fn send_login(client: &mut GameClient, name: &str, known: Option<(PlayerId, Token)>) {
+    let (player, token) = known.unzip();
+    client
+        .send(&ClientMessage::Login {
+            name: name.to_string(),
+            player,
+            token,
+        })
+        .unwrap();
+}
+
+// The player's id, rating and the token to come back with
+fn login(
+    client: &mut GameClient,
+    name: &str,
+    known: Option<(PlayerId, Token)>,
+) -> (PlayerId, i32, Token) {
+    send_login(client, name, known);
+    expect(client, |message| match message {
+        ServerMessage::LoggedIn {
+            player,
+            rating,
+            token,
+        } => Some((*player, *rating, token.clone())),
+        _ => None,
+    })
+}
+
+fn joined(client: &mut GameClient) -> (u64, bool) {
+    expect(client, |message| match message {
+        ServerMessage::Joined { game, spectator } => Some((*game, *spectator)),
+        _ => None,
+    })
+}
+
+fn list(client: &mut GameClient) -> Vec<GameInfo> {
+    client.send(&ClientMessage::ListGames).unwrap();
+    expect(client, |message| match message {
+        ServerMessage::Games { games } => Some(games.clone()),
+        _ => None,
+    })
+}
+
+fn expect_error(client: &mut GameClient) -> String {
+    expect(client, |message| match message {
+        ServerMessage::Error { message } => Some(message.clone()),
+        _ => None,
+    })
+}
+
+fn play(client: &mut GameClient, row: usize, col: usize) {
+    client
+        .send(&ClientMessage::Move {
+            row,
+            col,
+            symbol: None,
+        })
+        .unwrap();
+    expect(client, |message| match message {
+        ServerMessage::State { snapshot } => snapshot
+            .moves
+            .last()
+            .filter(|mv| mv.position == (row, col))
+            .map(|_| ()),
+        _ => None,
+    });
+}
+
+fn result(client: &mut GameClient) -> GameStatus {
+    expect(client, |message| match message {
+        ServerMessage::Result { status } => Some(status.clone()),
+        _ => None,
+    })
+}
+
+#[test]
+fn test_lobby_hosts_games_and_matches_players() {
+    let server = LobbyServer::bind("127.0.0.1:0", SymbolSet::Ascii).unwrap();
+    let addr = server.local_addr().unwrap();
+    thread::spawn(move || server.run());
+
+    let mut ann = connect(addr);
+    ann.send(&ClientMessage::ListGames).unwrap();
+    ann.send(&ClientMessage::CreateGame {
+        board_size: 3,
+        max_players: 2,
+        win_length: None,
+    })
+    .unwrap();
+    assert_eq!(expect_error(&mut ann), "Log in first");
+    let (ann_id, rating, ann_token) = login(&mut ann, "Ann", None);
+    assert_eq!(rating, 1200);
+
+    ann.send(&ClientMessage::CreateGame {
+        board_size: 3,
+        max_players: 2,
+        win_length: None,
+    })
+    .unwrap();
+    let (game, _) = joined(&mut ann);
+    let games = list(&mut ann);
+    assert_eq!(games.len(), 1);
+    assert_eq!(games[0].stage, GameStage::Open);
+    assert_eq!(games[0].players, ["Ann"]);
+    assert_eq!((games[0].board_size, games[0].board_cols), (3, 3));
+
+    let mut bob = connect(addr);
+    let (bob_id, _, bob_token) = login(&mut bob, "Bob", None);
+    bob.send(&ClientMessage::JoinGame { game, symbol: None })
+        .unwrap();
+    assert_eq!(joined(&mut bob), (game, false));
+
+    // Spectators get the game so far, and are refused moves
+    let mut eve = connect(addr);
+    eve.send(&ClientMessage::Spectate { game: Some(game) })
+        .unwrap();
+    assert_eq!(joined(&mut eve), (game, true));
+    let players = expect(&mut eve, |message| match message {
+        ServerMessage::State { snapshot } => Some(snapshot.players.len()),
+        _ => None,
+    });
+    assert_eq!(players, 2);
+    eve.send(&ClientMessage::Move {
+        row: 0,
+        col: 0,
+        symbol: None,
+    })
+    .unwrap();
+    assert_eq!(expect_error(&mut eve), "Spectators can't play");
+    let games = list(&mut eve);
+    assert_eq!(
+        (games[0].stage, games[0].spectators),
+        (GameStage::InProgress, 1)
+    );
+
+    // Ids are public, but logging in as someone else needs their token
+    let mut mallory = connect(addr);
+    send_login(&mut mallory, "Mallory", Some((ann_id, Token::random())));
+    assert_eq!(
+        expect_error(&mut mallory),
+        "Player is not part of this game"
+    );
+    mallory
+        .send(&ClientMessage::Login {
+            name: "Mallory".to_string(),
+            player: Some(bob_id),
+            token: None,
+        })
+        .unwrap();
+    assert_eq!(
+        expect_error(&mut mallory),
+        "Player is not part of this game"
+    );
+    mallory
+        .send(&ClientMessage::Move {
+            row: 0,
+            col: 0,
+            symbol: None,
+        })
+        .unwrap();
+    assert_eq!(expect_error(&mut mallory), "Join a game first");
+
+    let mut carl = connect(addr);
+    login(&mut carl, "Carl", None);
+    carl.send(&ClientMessage::JoinGame { game, symbol: None })
+        .unwrap();
+    assert_eq!(expect_error(&mut carl), "Maximum number of players reached");
+
+    // Whoever moves first wins along the top row
+    let snapshot = expect(&mut ann, |message| match message {
+        ServerMessage::State { snapshot } if snapshot.players.len() == 2 => Some(snapshot.clone()),
+        _ => None,
+    });
+    let first = snapshot.players[snapshot.current_player].id;
+    let (mut winner, mut loser) = if first == ann_id {
+        (ann, bob)
+    } else {
+        (bob, ann)
+    };
+    play(&mut winner, 0, 0);
+    play(&mut loser, 1, 0);
+    play(&mut winner, 0, 1);
+    play(&mut loser, 1, 1);
+    play(&mut winner, 0, 2);
+    let status = result(&mut eve);
+    assert!(matches!(status, GameStatus::Win(..)));
+    assert_eq!(result(&mut winner), status);
+    drop(winner);
+    drop(loser);
+
+    // Ratings are kept when players come back, and matchmaking pairs the
+    // winner with Carl, who is looking for the same kind of game
+    let (ann, bob) = ((ann_id, ann_token), (bob_id, bob_token));
+    let (winner_login, loser_login) = if first == ann_id {
+        (ann, bob)
+    } else {
+        (bob, ann)
+    };
+    let mut winner = connect(addr);
+    let (_, rating, _) = login(&mut winner, "Winner", Some(winner_login));
+    assert!(rating > 1200);
+    let mut loser = connect(addr);
+    let (_, rating, _) = login(&mut loser, "Loser", Some(loser_login));
+    assert!(rating < 1200);
+
+    let find_match = ClientMessage::FindMatch {
+        board_size: 4,
+        max_players: 2,
+    };
+    winner.send(&find_match).unwrap();
+    carl.send(&find_match).unwrap();
+    let (matched, _) = joined(&mut carl);
+    assert_eq!(joined(&mut winner).0, matched);
+    assert_ne!(matched, game);
+    assert_eq!(list(&mut loser).len(), 2);
+}
+
+#[test]
+fn test_players_leave_games_that_have_not_started() {
+    let server = LobbyServer::bind("127.0.0.1:0", SymbolSet::Ascii).unwrap();
+    let addr = server.local_addr().unwrap();
+    thread::spawn(move || server.run());
+
+    let mut ann = connect(addr);
+    login(&mut ann, "Ann", None);
+    let mut bob = connect(addr);
+    login(&mut bob, "Bob", None);
+
+    ann.send(&ClientMessage::CreateGame {
+        board_size: 3,
+        max_players: 3,
+        win_length: None,
+    })
+    .unwrap();
+    let (game, _) = joined(&mut ann);
+    bob.send(&ClientMessage::JoinGame { game, symbol: None })
+        .unwrap();
+    joined(&mut bob);
+
+    // Resigning before the game starts only gives up the seat
+    bob.send(&ClientMessage::Resign).unwrap();
+    bob.send(&ClientMessage::Leave).unwrap();
+    assert_eq!(expect_error(&mut bob), "You are not at a game");
+    let games = list(&mut bob);
+    assert_eq!(games[0].players, ["Ann"]);
+
+    // The last player to leave closes the game
+    ann.send(&ClientMessage::Leave).unwrap();
+    assert!(list(&mut ann).is_empty());
+
+    let find_match = ClientMessage::FindMatch {
+        board_size: 3,
+        max_players: 2,
+    };
+    ann.send(&find_match).unwrap();
+    bob.send(&find_match).unwrap();
+    let (matched, _) = joined(&mut ann);
+    assert_eq!(joined(&mut bob), (matched, false));
+    assert_ne!(matched, game);
+
+    ann.send(&ClientMessage::Leave).unwrap();
+    assert_eq!(
+        expect_error(&mut ann),
+        "The game has started, resign to leave it"
+    );
+}
@@ -0,0 +1,302 @@
+use rust_tic_tac_toe::client::GameClient;
+use rust_tic_tac_toe::game::Game;
+use rust_tic_tac_toe::player::Player;
+use rust_tic_tac_toe::protocol::{ClientMessage, ServerMessage, MAX_LINE};
+use rust_tic_tac_toe::rules::{GameRules, Opening};
+use rust_tic_tac_toe::server::GameServer;
+use rust_tic_tac_toe::types::{
+    Difficulty, GameStatus, OpeningChoice, PlayerId, Symbol, SymbolSet, Token, WinReason,
+};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+fn connect(addr: SocketAddr) -> GameClient {
+    let client = GameClient::connect(addr).unwrap();
+    client.set_timeout(Some(Duration::from_secs(5))).unwrap();
+    client
+}
+
+// Skips messages until one matches, panics on timeout
+fn expect<T>(client: &mut GameClient, mut matches: impl FnMut(&ServerMessage) -> Option<T>) -> T {
+    loop {
+        let message = client.recv().unwrap();
+        if let Some(value) = matches(&message) {
+            return value;
+        }
+    }
+}
+
+fn join(client: &mut GameClient, name: &str) -> (PlayerId, Token) {
+    client
+        .send(&ClientMessage::Join {
+            name: name.to_string(),
+            symbol: None,
+        })
+        .unwrap();
+    expect(client, |message| match message {
+        ServerMessage::Welcome { player, token, .. } => Some((*player, token.clone())),
+        _ => None,
+    })
+}
+
+fn expect_error(client: &mut GameClient) -> String {
+    expect(client, |message| match message {
+        ServerMessage::Error { message } => Some(message.clone()),
+        _ => None,
+    })
+}
+
+// Waits until the server has applied the move
+fn play(client: &mut GameClient, row: usize, col: usize) {
+    client
+        .send(&ClientMessage::Move {
+            row,
+            col,
+            symbol: None,
+        })
+        .unwrap();
+    expect(client, |message| match message {
+        ServerMessage::State { snapshot } => snapshot
+            .moves
+            .last()
+            .filter(|mv| mv.position == (row, col))
+            .map(|_| ()),
+        _ => None,
+    });
+}
+
+fn try_move(client: &mut GameClient, row: usize, col: usize) -> String {
+    client
+        .send(&ClientMessage::Move {
+            row,
+            col,
+            symbol: None,
+        })
+        .unwrap();
+    expect_error(client)
+}
+
+#[test]
+fn test_two_players_over_tcp() {
+    let server = GameServer::bind("127.0.0.1:0", Game::new(3, 2), SymbolSet::Ascii).unwrap();
+    let addr = server.local_addr().unwrap();
+    let handle = thread::spawn(move || server.run().unwrap());
+
+    let mut ann = connect(addr);
+    let (ann_id, _) = join(&mut ann, "Ann");
+    assert_eq!(try_move(&mut ann, 1, 1), "Waiting for more players to join");
+
+    let mut bob = connect(addr);
+    let (bob_id, bob_token) = join(&mut bob, "Bob");
+    assert_ne!(ann_id, bob_id);
+
+    // Moves are validated by the game on the server
+    assert_eq!(try_move(&mut bob, 1, 1), "Not your turn");
+    play(&mut ann, 1, 1);
+    assert!(try_move(&mut bob, 1, 1).contains("occupied"));
+
+    // A spectator joining midway gets the game so far and the AI's view of it
+    let mut eve = connect(addr);
+    eve.send(&ClientMessage::Spectate { game: None }).unwrap();
+    let moves = expect(&mut eve, |message| match message {
+        ServerMessage::State { snapshot } => Some(snapshot.moves.len()),
+        _ => None,
+    });
+    assert_eq!(moves, 1);
+    let evaluations = expect(&mut eve, |message| match message {
+        ServerMessage::Evaluation { evaluations } => Some(evaluations.clone()),
+        _ => None,
+    });
+    assert_eq!(evaluations.len(), 2);
+    assert!(evaluations[0].score > evaluations[1].score);
+    assert_eq!(try_move(&mut eve, 0, 0), "Spectators can't play");
+
+    // Everyone can see Bob's id, but only Bob has his token
+    eve.send(&ClientMessage::Rejoin {
+        player: bob_id,
+        token: Token::random(),
+    })
+    .unwrap();
+    assert_eq!(expect_error(&mut eve), "Player is not part of this game");
+    assert_eq!(try_move(&mut eve, 0, 0), "Spectators can't play");
+
+    bob.send(&ClientMessage::Chat {
+        text: "nice".to_string(),
+    })
+    .unwrap();
+    let chat = expect(&mut ann, |message| match message {
+        ServerMessage::Chat { from, text } => Some(format!("{}: {}", from, text)),
+        _ => None,
+    });
+    assert_eq!(chat, "Bob: nice");
+
+    // Bob drops the connection and takes his seat back
+    drop(bob);
+    let mut bob = GameClient::rejoin(addr, bob_id, &bob_token, 3).unwrap();
+    bob.set_timeout(Some(Duration::from_secs(5))).unwrap();
+    let rejoined = expect(&mut bob, |message| match message {
+        ServerMessage::Welcome { player, .. } => Some(*player),
+        _ => None,
+    });
+    assert_eq!(rejoined, bob_id);
+
+    play(&mut bob, 0, 0);
+    play(&mut ann, 0, 1);
+    play(&mut bob, 2, 2);
+    play(&mut ann, 2, 1);
+
+    let expected = GameStatus::Win("Ann".to_string(), WinReason::Streak);
+    for client in [&mut ann, &mut bob, &mut eve] {
+        let status = expect(client, |message| match message {
+            ServerMessage::Result { status } => Some(status.clone()),
+            _ => None,
+        });
+        assert_eq!(status, expected);
+    }
+    assert_eq!(handle.join().unwrap(), expected);
+}
+
+#[test]
+fn test_overlong_messages_drop_the_connection() {
+    let server = GameServer::bind("127.0.0.1:0", Game::new(3, 2), SymbolSet::Ascii).unwrap();
+    let addr = server.local_addr().unwrap();
+    thread::spawn(move || server.run());
+
+    // A line that never ends is cut off at the limit instead of filling memory
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let _ = stream.write_all(&vec![b'a'; MAX_LINE as usize + 1]);
+    let mut rest = Vec::new();
+    let closed = match stream.read_to_end(&mut rest) {
+        Ok(_) => true,
+        Err(e) => e.kind() == std::io::ErrorKind::ConnectionReset,
+    };
+    assert!(closed);
+
+    // Everyone else is still served
+    let mut ann = connect(addr);
+    join(&mut ann, "Ann");
+}
+
+#[test]
+fn test_wild_moves_over_tcp() {
+    let rules = GameRules {
+        wild: vec![Symbol('X'), Symbol('O')],
+        ..GameRules::default()
+    };
+    let game = Game::with_rules(3, 2, rules);
+    let server = GameServer::bind("127.0.0.1:0", game, SymbolSet::Ascii).unwrap();
+    let addr = server.local_addr().unwrap();
+    thread::spawn(move || server.run());
+
+    let mut ann = connect(addr);
+    join(&mut ann, "Ann");
+    let mut bob = connect(addr);
+    join(&mut bob, "Bob");
+
+    // Either player may place either symbol
+    let first = expect(&mut ann, |message| match message {
+        ServerMessage::State { snapshot } if snapshot.players.len() == 2 => {
+            Some(snapshot.current_player)
+        }
+        _ => None,
+    });
+    let mover = if first == 0 { &mut ann } else { &mut bob };
+    mover
+        .send(&ClientMessage::Move {
+            row: 1,
+            col: 1,
+            symbol: Some(Symbol('O')),
+        })
+        .unwrap();
+    let placed = expect(mover, |message| match message {
+        ServerMessage::State { snapshot } => snapshot.moves.last().map(|mv| mv.symbol),
+        _ => None,
+    });
+    assert_eq!(placed, Symbol('O'));
+}
+
+#[test]
+fn test_opening_choice_over_tcp() {
+    let rules = GameRules {
+        opening: Opening::Pie,
+        ..GameRules::default()
+    };
+    let game = Game::with_rules(3, 2, rules);
+    let server = GameServer::bind("127.0.0.1:0", game, SymbolSet::Ascii).unwrap();
+    let addr = server.local_addr().unwrap();
+    thread::spawn(move || server.run());
+
+    let mut ann = connect(addr);
+    join(&mut ann, "Ann");
+    let mut bob = connect(addr);
+    let (bob_id, _) = join(&mut bob, "Bob");
+
+    // Bob takes over Ann's first move instead of answering it
+    play(&mut ann, 1, 1);
+    bob.send(&ClientMessage::Move {
+        row: 0,
+        col: 0,
+        symbol: None,
+    })
+    .unwrap();
+    assert_eq!(expect_error(&mut bob), "A color has to be chosen first");
+    bob.send(&ClientMessage::Choose {
+        choice: OpeningChoice::Swap,
+    })
+    .unwrap();
+    let owner = expect(&mut bob, |message| match message {
+        ServerMessage::State { snapshot } if !snapshot.opening.is_empty() => snapshot
+            .players
+            .iter()
+            .find(|p| p.symbol == snapshot.moves[0].symbol)
+            .map(|p| p.id),
+        _ => None,
+    });
+    assert_eq!(owner, bob_id);
+}
+
+#[test]
+fn test_ai_players_on_the_server() {
+    let mut game = Game::new(3, 2);
+    let bot = Player::new(
+        "Bot".to_string(),
+        Symbol('O'),
+        false,
+        Some(Difficulty::Hard),
+    );
+    game.add_player(&bot).unwrap();
+    let server = GameServer::bind("127.0.0.1:0", game, SymbolSet::Ascii).unwrap();
+    let addr = server.local_addr().unwrap();
+    let handle = thread::spawn(move || server.run().unwrap());
+
+    // The AI answers every move, and the last state and the result arrive
+    // before the server shuts down
+    let mut ann = connect(addr);
+    let (ann_id, _) = join(&mut ann, "Ann");
+    let status = loop {
+        let message = ann.recv().unwrap();
+        match message {
+            ServerMessage::State { snapshot } => {
+                let game = Game::from_snapshot(&snapshot).unwrap();
+                if !game.is_over() && game.current_player().map(|p| p.id()) == Some(ann_id) {
+                    let (row, col) = game.board().empty_positions()[0];
+                    ann.send(&ClientMessage::Move {
+                        row,
+                        col,
+                        symbol: None,
+                    })
+                    .unwrap();
+                }
+            }
+            ServerMessage::Result { status } => break status,
+            _ => {}
+        }
+    };
+    assert_eq!(handle.join().unwrap(), status);
+    assert!(ann.recv().is_err());
+}
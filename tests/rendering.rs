@@ -0,0 +1,87 @@
+use rust_tic_tac_toe::game_board::GameBoard;
+use rust_tic_tac_toe::render::{display_width, BoardLayout, BoardMarks};
+use rust_tic_tac_toe::theme::Theme;
+use rust_tic_tac_toe::types::{PlayerError, Symbol, SymbolSet};
+
+fn assert_aligned(board: &GameBoard, symbol_set: SymbolSet) {
+    let layout = BoardLayout::for_board(board, symbol_set.cell_width());
+    let lines = layout.lines(board);
+    let width = display_width(&lines[1]);
+    for line in &lines[1..] {
+        assert_eq!(display_width(line), width, "misaligned line: {:?}", line);
+    }
+    assert!(display_width(&lines[0]) <= width);
+}
+
+#[test]
+fn test_board_alignment_for_symbol_sets() {
+    for symbol_set in [SymbolSet::Emoji, SymbolSet::Ascii] {
+        for size in [3, 5, 10] {
+            let mut board = GameBoard::new(size);
+            let first = symbol_set.pick_unique(&[]).unwrap();
+            let second = symbol_set.pick_unique(&[first]).unwrap();
+            board.apply_move((0, 0), first).unwrap();
+            board.apply_move((size - 1, size - 1), second).unwrap();
+            assert_aligned(&board, symbol_set);
+        }
+    }
+}
+
+#[test]
+fn test_ascii_symbols_are_handed_out_in_order() {
+    let x = SymbolSet::Ascii.pick_unique(&[]).unwrap();
+    let o = SymbolSet::Ascii.pick_unique(&[x]).unwrap();
+    assert_eq!((x, o), (Symbol('X'), Symbol('O')));
+    assert_eq!(SymbolSet::Ascii.cell_width(), 1);
+    assert_eq!(SymbolSet::Emoji.cell_width(), 2);
+}
+
+#[test]
+fn test_player_chosen_symbols_are_validated() {
+    assert_eq!(Symbol::parse(" Z ", &[]).unwrap(), Symbol('Z'));
+    assert!(matches!(
+        Symbol::parse("X", &[Symbol('X')]),
+        Err(PlayerError::SymbolTaken)
+    ));
+    assert!(matches!(
+        Symbol::parse("XO", &[]),
+        Err(PlayerError::InvalidSymbol)
+    ));
+    assert!(matches!(
+        Symbol::parse("", &[]),
+        Err(PlayerError::InvalidSymbol)
+    ));
+}
+
+#[test]
+fn test_plain_theme_renders_without_escape_codes() {
+    let mut board = GameBoard::new(3);
+    for pos in [(0, 2), (1, 1), (2, 0)] {
+        board.apply_move(pos, Symbol('X')).unwrap();
+    }
+    assert_eq!(board.winning_line(3), Some(vec![(0, 2), (1, 1), (2, 0)]));
+
+    let marks = BoardMarks {
+        players: vec![Symbol('X'), Symbol('O')],
+        last_move: Some((2, 0)),
+        vanishing: None,
+        winning_line: board.winning_line(3).unwrap(),
+    };
+    let layout = BoardLayout::for_board(&board, 1);
+    let plain = layout.styled_lines(&board, &Theme::plain(), &marks);
+    assert_eq!(plain, layout.lines(&board));
+    assert!(plain.iter().all(|line| !line.contains('\x1b')));
+
+    let colored = layout.styled_lines(&board, &Theme::colorblind(), &marks);
+    assert!(colored[2].contains('\x1b'));
+}
+
+#[test]
+fn test_blocked_cells_are_drawn_filled() {
+    let mut board = GameBoard::new(3);
+    board.block((0, 0)).unwrap();
+    board.apply_move((0, 1), Symbol('X')).unwrap();
+    let lines = BoardLayout::for_board(&board, 1).lines(&board);
+    assert_eq!(lines[2], "0 |###| X |   |");
+    assert_aligned(&board, SymbolSet::Emoji);
+}
@@ -0,0 +1,36 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use rust_tic_tac_toe::tui::{clamp_cursor, key_action, move_cursor, Action};
+
+#[test]
+fn test_key_actions() {
+    let press = |code| KeyEvent::new(code, KeyModifiers::NONE);
+    assert_eq!(key_action(press(KeyCode::Up)), Some(Action::Up));
+    assert_eq!(key_action(press(KeyCode::Char('k'))), Some(Action::Up));
+    assert_eq!(key_action(press(KeyCode::Char('l'))), Some(Action::Right));
+    assert_eq!(key_action(press(KeyCode::Enter)), Some(Action::Place));
+    assert_eq!(key_action(press(KeyCode::Char(':'))), Some(Action::Console));
+    assert_eq!(key_action(press(KeyCode::Char('x'))), None);
+    assert_eq!(
+        key_action(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+        Some(Action::Quit)
+    );
+
+    // Only presses count, not the release that follows
+    let mut release = press(KeyCode::Enter);
+    release.kind = KeyEventKind::Release;
+    assert_eq!(key_action(release), None);
+}
+
+#[test]
+fn test_cursor_movement() {
+    // The cursor wraps around a 3x4 board
+    assert_eq!(move_cursor((0, 0), Action::Up, 3, 4), (2, 0));
+    assert_eq!(move_cursor((2, 0), Action::Down, 3, 4), (0, 0));
+    assert_eq!(move_cursor((1, 0), Action::Left, 3, 4), (1, 3));
+    assert_eq!(move_cursor((1, 3), Action::Right, 3, 4), (1, 0));
+    assert_eq!(move_cursor((1, 1), Action::Place, 3, 4), (1, 1));
+
+    // A smaller board pulls it back onto the last row and column
+    assert_eq!(clamp_cursor((6, 2), 3, 4), (2, 2));
+    assert_eq!(clamp_cursor((1, 9), 3, 4), (1, 3));
+}
@@ -0,0 +1,146 @@
+use rust_tic_tac_toe::ai::AI;
+use rust_tic_tac_toe::player::Player;
+use rust_tic_tac_toe::render::{display_width, ultimate_lines, BoardMarks};
+use rust_tic_tac_toe::theme::Theme;
+use rust_tic_tac_toe::types::{BoardError, Difficulty, GameError, GameStatus, Symbol};
+use rust_tic_tac_toe::ultimate::{UltimateBoard, UltimateGame};
+
+fn x() -> Symbol {
+    Symbol::parse("X", &[]).unwrap()
+}
+
+fn o() -> Symbol {
+    Symbol::parse("O", &[]).unwrap()
+}
+
+#[test]
+fn test_moves_send_the_opponent_to_a_sub_board() {
+    let mut game = UltimateGame::new();
+    game.add_player(&Player::new("Ann".to_string(), x(), true, None))
+        .unwrap();
+    game.add_player(&Player::new("Bob".to_string(), o(), true, None))
+        .unwrap();
+
+    // Top-right cell of the center sub-board sends Bob to the top-right one
+    game.make_move((3, 5)).unwrap();
+    assert_eq!(game.board().active_boards(), [2]);
+    assert!(matches!(
+        game.make_move((4, 4)),
+        Err(GameError::BoardError(BoardError::InactiveSubBoard))
+    ));
+    assert!(matches!(
+        game.make_move((9, 0)),
+        Err(GameError::BoardError(BoardError::OutOfBounds))
+    ));
+    game.make_move((0, 7)).unwrap();
+    assert_eq!(game.board().active_boards(), [1]);
+}
+
+fn play(board: &mut UltimateBoard, moves: &[(usize, usize)]) {
+    for (i, &pos) in moves.iter().enumerate() {
+        let symbol = if i % 2 == 0 { x() } else { o() };
+        board.apply_move(pos, symbol).unwrap();
+    }
+}
+
+#[test]
+fn test_won_sub_boards_count_on_the_meta_board() {
+    // O keeps answering in a center cell, sending X back to the center
+    // sub-board, where X takes the left column
+    let mut board = UltimateBoard::new();
+    play(&mut board, &[(3, 3), (1, 1), (4, 3), (4, 1), (5, 3)]);
+    assert_eq!(board.sub_board_winner(4), Some(x()));
+    assert_eq!(board.meta().get_cell((1, 1)), Some(x()));
+    assert!(board.winning_cells().contains(&(4, 3)));
+    assert_eq!(board.active_boards(), [6]);
+
+    // Sent to the finished center sub-board, X may play in any other one
+    board.apply_move((7, 1), o()).unwrap();
+    assert!(board.is_decided(4));
+    assert_eq!(board.active_boards().len(), 8);
+    assert!(!board.is_move_valid((3, 4)));
+}
+
+#[test]
+fn test_drawn_sub_boards_block_their_meta_cell() {
+    // The top-left sub-board fills up without a line for either player
+    let mut board = UltimateBoard::new();
+    play(
+        &mut board,
+        &[
+            (0, 0),
+            (0, 1),
+            (0, 3),
+            (1, 1),
+            (3, 3),
+            (1, 2),
+            (3, 6),
+            (2, 0),
+            (6, 1),
+            (0, 4),
+            (0, 5),
+            (0, 6),
+            (1, 0),
+            (3, 0),
+            (2, 1),
+            (6, 3),
+            (2, 2),
+            (6, 6),
+            (0, 2),
+        ],
+    );
+    assert!(board.sub_board(0).is_full());
+    assert_eq!(board.sub_board_winner(0), None);
+    assert!(board.is_decided(0));
+    assert!(board.meta().is_blocked((0, 0)));
+    assert!(!board.active_boards().contains(&0));
+}
+
+#[test]
+fn test_ai_plays_legal_moves_to_the_end() {
+    for difficulty in [Difficulty::Easy, Difficulty::Hard] {
+        let mut game = UltimateGame::new();
+        game.add_player(&Player::new(
+            "One".to_string(),
+            x(),
+            false,
+            Some(difficulty),
+        ))
+        .unwrap();
+        game.add_player(&Player::new(
+            "Two".to_string(),
+            o(),
+            false,
+            Some(Difficulty::Medium),
+        ))
+        .unwrap();
+        while let Some(pos) = game.ai_move() {
+            assert!(game.is_move_valid(pos));
+            game.make_move(pos).unwrap();
+        }
+        assert!(game.is_over());
+        assert_ne!(game.status(), GameStatus::InProgress);
+    }
+
+    // O is one move from winning the top-left sub-board and takes it
+    let mut board = UltimateBoard::new();
+    play(&mut board, &[(3, 3), (0, 1), (0, 3), (2, 1), (6, 3)]);
+    let ai = AI::new(Difficulty::Hard, o());
+    assert_eq!(ai.decide_ultimate_move(&board), (1, 1));
+}
+
+#[test]
+fn test_ultimate_grid_is_aligned() {
+    let mut board = UltimateBoard::new();
+    board.apply_move((4, 4), x()).unwrap();
+    board.apply_move((3, 3), o()).unwrap();
+    let lines = ultimate_lines(&board, 1, &Theme::plain(), &BoardMarks::default());
+    assert_eq!(lines.len(), 2 + 2 * 9);
+    let width = display_width(&lines[1]);
+    for line in &lines[1..] {
+        assert_eq!(display_width(line), width, "misaligned line: {:?}", line);
+    }
+    // Only the top-left sub-board is open for X
+    assert_eq!(lines[2].matches('.').count(), 3);
+    assert!(lines[8].contains("# O |"));
+}